@@ -0,0 +1,61 @@
+//! Drives the game from an async runtime, awaiting a timer between ticks the
+//! way a bot or web backend embedding the engine would.
+//!
+//! Run with `cargo run --example async_driver`.
+
+use std::time::Duration;
+
+use rsnake::{Direction, Game, GameEvent};
+
+const MAX_TICKS: usize = 500;
+
+/// Heads straight for the food, which is enough to give the game something to
+/// do without any input.
+fn steer(game: &Game) -> Option<Direction> {
+    let head = game.snakes()[0].body().head();
+    let food = game.food().first()?;
+
+    if food.at.0 < head.0 {
+        Some(Direction::Left)
+    } else if food.at.0 > head.0 {
+        Some(Direction::Right)
+    } else if food.at.1 < head.1 {
+        Some(Direction::Up)
+    } else {
+        Some(Direction::Down)
+    }
+}
+
+#[tokio::main(flavor = "current_thread")]
+async fn main() {
+    let mut game = Game::new(1);
+    let mut interval = tokio::time::interval(Duration::from_millis(50));
+
+    for tick in 0..MAX_TICKS {
+        interval.tick().await;
+
+        if let Some(direction) = steer(&game) {
+            game.change_direction(0, direction);
+        }
+
+        match game.tick() {
+            Ok(events) => {
+                for event in events {
+                    if let GameEvent::AteFood { .. } = event {
+                        println!(
+                            "tick {}: ate food, length {}",
+                            tick,
+                            game.snakes()[0].body().len()
+                        );
+                    }
+                }
+            }
+            Err(over) => {
+                println!("tick {}: game over, the snake {}", tick, over.reason);
+                return;
+            }
+        }
+    }
+
+    println!("stopped after {} ticks", MAX_TICKS);
+}
@@ -0,0 +1,234 @@
+//! A computer player that steers player one's snake toward food.
+
+use std::{
+    cmp::Reverse,
+    collections::{BinaryHeap, HashMap, HashSet},
+};
+
+use crate::{CellKind, Direction, FoodKind, Game, Vector};
+
+/// Tried in this order whenever moves are otherwise equally good, so the same
+/// game always gets the same answer.
+const DIRECTIONS: [Direction; 4] = [
+    Direction::Left,
+    Direction::Up,
+    Direction::Right,
+    Direction::Down,
+];
+
+/// The way player one should turn next: along the shortest path A* finds to
+/// the nearest food worth eating, or failing that any move that doesn't crash
+/// straight away. `None` means every move crashes.
+pub fn next_move(game: &Game) -> Option<Direction> {
+    let snake = &game.snakes()[0];
+    let head = snake.body().head();
+
+    // Every segment but the tail, which moves out of the way, is in the way
+    // of every snake.
+    let grid = game.grid_snapshot();
+    let tails: HashSet<&Vector> = game
+        .snakes()
+        .iter()
+        .map(|snake| snake.body().tail())
+        .collect();
+    let open = |cell: &Vector| match grid.get(cell) {
+        Some(CellKind::Empty | CellKind::Terrain(_) | CellKind::Food(_)) => true,
+        Some(CellKind::Head(_) | CellKind::Body(_)) => tails.contains(cell),
+        _ => false,
+    };
+
+    let moves: Vec<(Direction, Vector)> = DIRECTIONS
+        .into_iter()
+        .filter(|direction| *direction != snake.direction().opposite())
+        .filter_map(|direction| Some((direction, game.moved(head, direction)?)))
+        .filter(|(_, to)| open(to))
+        .collect();
+
+    // Rotten food is only worth eating to clear the way for more.
+    let mut goals: Vec<&Vector> = game
+        .food()
+        .iter()
+        .filter(|food| food.kind != FoodKind::Rotten)
+        .map(|food| &food.at)
+        .collect();
+    if goals.is_empty() {
+        goals = game.food().iter().map(|food| &food.at).collect();
+    }
+
+    shortest_path(game, head, &goals, &moves, &open)
+        .or_else(|| moves.first().map(|(direction, _)| *direction))
+}
+
+/// The first move along the shortest path from `head` to any of `goals`.
+fn shortest_path(
+    game: &Game,
+    head: &Vector,
+    goals: &[&Vector],
+    moves: &[(Direction, Vector)],
+    open: &impl Fn(&Vector) -> bool,
+) -> Option<Direction> {
+    if goals.is_empty() {
+        return None;
+    }
+
+    let estimate = |cell: &Vector| {
+        goals
+            .iter()
+            .map(|goal| distance(game, cell, goal))
+            .min()
+            .unwrap_or(0)
+    };
+
+    // The first move taken to reach each cell, and how many steps it took.
+    let mut best: HashMap<Vector, (u32, Direction)> =
+        HashMap::from([(head.clone(), (0, moves.first()?.0))]);
+    // Cells waiting to be looked at, queued by index so that ties go to
+    // whichever was queued first.
+    let mut cells: Vec<(Vector, u32, Direction)> = Vec::new();
+    let mut queue = BinaryHeap::new();
+
+    for (direction, to) in moves {
+        best.insert(to.clone(), (1, *direction));
+        queue.push(Reverse((1 + estimate(to), cells.len())));
+        cells.push((to.clone(), 1, *direction));
+    }
+
+    while let Some(Reverse((_, i))) = queue.pop() {
+        let (cell, steps, first) = cells[i].clone();
+        if goals.contains(&&cell) {
+            return Some(first);
+        }
+        if best.get(&cell).is_some_and(|(known, _)| *known < steps) {
+            continue;
+        }
+
+        for direction in DIRECTIONS {
+            let next = match game.moved(&cell, direction) {
+                Some(next) => next,
+                None => continue,
+            };
+            if !open(&next)
+                || best
+                    .get(&next)
+                    .is_some_and(|(known, _)| *known <= steps + 1)
+            {
+                continue;
+            }
+
+            best.insert(next.clone(), (steps + 1, first));
+            queue.push(Reverse((steps + 1 + estimate(&next), cells.len())));
+            cells.push((next, steps + 1, first));
+        }
+    }
+
+    None
+}
+
+/// Which way the nearest food is from player one's head, going by whichever
+/// axis it's further along. Food across an edge that wraps counts as near if
+/// going around is shorter. `None` with no food on the board, or food right
+/// under the head.
+pub fn food_direction(game: &Game) -> Option<Direction> {
+    let head = game.snakes()[0].body().head();
+    let food = game
+        .food()
+        .iter()
+        .min_by_key(|food| distance(game, head, &food.at))?;
+
+    let (dx, dy) = offset(game, head, &food.at);
+    match (dx, dy) {
+        (0, 0) => None,
+        (dx, dy) if dx.abs() >= dy.abs() => Some(if dx < 0 {
+            Direction::Left
+        } else {
+            Direction::Right
+        }),
+        (_, dy) => Some(if dy < 0 {
+            Direction::Up
+        } else {
+            Direction::Down
+        }),
+    }
+}
+
+/// Steps between two cells, going around the edges that wrap where that's
+/// shorter.
+fn distance(game: &Game, from: &Vector, to: &Vector) -> u32 {
+    let (dx, dy) = offset(game, from, to);
+    dx.unsigned_abs() + dy.unsigned_abs()
+}
+
+/// How far `to` is from `from` along each axis the shorter way round, with
+/// up and left being negative.
+pub fn offset(game: &Game, from: &Vector, to: &Vector) -> (i32, i32) {
+    let dimensions = game.dimensions();
+    let (wrap_x, wrap_y) = game.wrap();
+    let width = (dimensions.x.1 - dimensions.x.0 + 1) as i32;
+    let height = (dimensions.y.1 - dimensions.y.0 + 1) as i32;
+
+    let shorter = |d: i32, size: i32, wraps: bool| {
+        if wraps && d.abs() * 2 > size {
+            d - size * d.signum()
+        } else {
+            d
+        }
+    };
+    (
+        shorter(to.0 as i32 - from.0 as i32, width, wrap_x),
+        shorter(to.1 as i32 - from.1 as i32, height, wrap_y),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A game with player one's head alone at `head` and normal food on
+    /// `cells`.
+    fn food_at(head: (u16, u16), cells: &[(u16, u16)], wrap: bool) -> Game {
+        let mut game = Game::with_seed(1, 1);
+        game.set_wrap(wrap, wrap);
+        let mut json = serde_json::to_value(&game).unwrap();
+        json["snakes"][0]["body"] = serde_json::json!([[head.0, head.1]]);
+        json["food"] = cells
+            .iter()
+            .map(|&(x, y)| serde_json::json!({ "at": [x, y], "kind": "normal" }))
+            .collect();
+        serde_json::from_value(json).unwrap()
+    }
+
+    fn compass(cells: &[(u16, u16)], wrap: bool) -> Option<Direction> {
+        food_direction(&food_at((5, 5), cells, wrap))
+    }
+
+    #[test]
+    fn points_along_the_axis_the_food_is_further_along() {
+        assert_eq!(compass(&[(9, 6)], false), Some(Direction::Right));
+        assert_eq!(compass(&[(2, 4)], false), Some(Direction::Left));
+        assert_eq!(compass(&[(6, 1)], false), Some(Direction::Up));
+        assert_eq!(compass(&[(4, 9)], false), Some(Direction::Down));
+        // A tie goes across.
+        assert_eq!(compass(&[(7, 7)], false), Some(Direction::Right));
+    }
+
+    #[test]
+    fn points_across_an_edge_that_wraps_when_thats_shorter() {
+        assert_eq!(compass(&[(18, 5)], false), Some(Direction::Right));
+        assert_eq!(compass(&[(18, 5)], true), Some(Direction::Left));
+        let from_the_top = |wrap| food_direction(&food_at((5, 2), &[(6, 9)], wrap));
+        assert_eq!(from_the_top(false), Some(Direction::Down));
+        assert_eq!(from_the_top(true), Some(Direction::Up));
+    }
+
+    #[test]
+    fn points_to_the_nearest_food() {
+        assert_eq!(compass(&[(15, 5), (5, 7)], false), Some(Direction::Down));
+        assert_eq!(compass(&[(10, 9), (20, 5)], true), Some(Direction::Left));
+        assert_eq!(compass(&[(10, 9), (20, 5)], false), Some(Direction::Right));
+    }
+
+    #[test]
+    fn points_nowhere_without_food() {
+        assert_eq!(compass(&[], false), None);
+    }
+}
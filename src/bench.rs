@@ -0,0 +1,111 @@
+//! A fixed headless run of the computer player, timed so performance can be
+//! compared from one version to the next.
+
+use anyhow::Result;
+use serde::Serialize;
+use std::time::{Duration, Instant};
+
+use rsnake::{ai, Game};
+
+const SEED: u64 = 1;
+const TICKS: u64 = 20_000;
+const OBSTACLES: u16 = 20;
+const FOOD: u32 = 3;
+
+/// What's printed, as a line of JSON.
+#[derive(Serialize)]
+struct Timings {
+    seed: u64,
+    ticks: u64,
+    /// Boards played, as a fresh one is started each time the snake dies or
+    /// fills the board.
+    games: u64,
+    /// Everything eaten across every game.
+    score: u64,
+    total_ms: f64,
+    mean_tick_us: f64,
+    max_tick_us: f64,
+}
+
+/// The board each game of the scenario is played on. Games after the first
+/// count up from `SEED` so the run as a whole is the same every time.
+fn scenario(game: u64) -> Game {
+    let mut board = Game::with_seed(1, SEED + game);
+    board.place_obstacles(OBSTACLES);
+    board.set_food_count(FOOD);
+    board
+}
+
+/// Plays the scenario out for `TICKS` ticks, timing each move the computer
+/// player picks and each tick, and prints the timings.
+pub fn run() -> Result<()> {
+    println!("{}", serde_json::to_string(&play(TICKS))?);
+    Ok(())
+}
+
+/// The scenario played out for `ticks` ticks.
+fn play(ticks: u64) -> Timings {
+    let mut games = 0;
+    let mut score = 0;
+    let mut game = scenario(games);
+    let mut slowest = Duration::ZERO;
+    let started = Instant::now();
+
+    for _ in 0..ticks {
+        let tick_started = Instant::now();
+        if let Some(direction) = ai::next_move(&game) {
+            game.change_direction(0, direction);
+        }
+        let done = game.tick().is_err() || game.is_full();
+        slowest = slowest.max(tick_started.elapsed());
+
+        if done {
+            score += game.snakes()[0].score() as u64;
+            games += 1;
+            game = scenario(games);
+        }
+    }
+
+    let total = started.elapsed();
+    Timings {
+        seed: SEED,
+        ticks,
+        games: games + 1,
+        score: score + game.snakes()[0].score() as u64,
+        total_ms: total.as_secs_f64() * 1e3,
+        mean_tick_us: total.as_secs_f64() * 1e6 / ticks as f64,
+        max_tick_us: slowest.as_secs_f64() * 1e6,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn the_scenario_plays_out_the_same_every_time() {
+        let first = play(300);
+        let second = play(300);
+        assert_eq!((first.games, first.score), (second.games, second.score));
+        assert_eq!(first.ticks, 300);
+        assert!(first.score > 0);
+        assert!(first.max_tick_us <= first.total_ms * 1e3);
+    }
+
+    #[test]
+    fn timings_print_as_one_line_of_json() {
+        let json = serde_json::to_value(play(10)).unwrap();
+        for field in [
+            "seed",
+            "ticks",
+            "games",
+            "score",
+            "total_ms",
+            "mean_tick_us",
+            "max_tick_us",
+        ] {
+            assert!(json.get(field).is_some(), "{}", field);
+        }
+        assert!(!serde_json::to_string(&play(10)).unwrap().contains('\n'));
+    }
+}
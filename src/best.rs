@@ -0,0 +1,388 @@
+//! The best run so far, kept in the user's data directory so it can be raced
+//! as a ghost, along with the latest scores and the record for `--trainer`.
+//! Runs can also be saved to and read back from any file, as JSON or in a
+//! compact binary format.
+
+use anyhow::{anyhow, Context, Result};
+use bincode::Options;
+use directories::ProjectDirs;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use std::{
+    cmp::Ordering,
+    fs,
+    path::{Path, PathBuf},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use rsnake::Replay;
+
+use crate::menu::Mode;
+
+/// What a run saved in the binary format starts with, followed by a byte for
+/// the version of the format.
+const MAGIC: &[u8] = b"rsnake-run";
+const VERSION: u8 = 2;
+
+/// How many of the latest scores are kept.
+const RECENT_SCORES: usize = 100;
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct BestRun {
+    pub score: u32,
+    pub replay: Replay,
+}
+
+impl BestRun {
+    /// Writes the run to `path`, in the binary format if it ends in `.bin`
+    /// and as JSON otherwise.
+    pub fn export(&self, path: &Path) -> Result<()> {
+        let contents = if path.extension().is_some_and(|ext| ext == "bin") {
+            let mut contents = [MAGIC, &[VERSION]].concat();
+            contents.extend(binary().serialize(self)?);
+            contents
+        } else {
+            serde_json::to_vec(self)?
+        };
+        fs::write(path, contents).with_context(|| format!("can't save run {}", path.display()))
+    }
+
+    /// Reads a run written by `export`, in either format whatever the file
+    /// is called.
+    pub fn import(path: &Path) -> Result<BestRun> {
+        let contents =
+            fs::read(path).with_context(|| format!("can't open run {}", path.display()))?;
+
+        let run = match contents.strip_prefix(MAGIC) {
+            Some([VERSION, rest @ ..]) => binary().deserialize(rest)?,
+            Some([version, ..]) => {
+                return Err(anyhow!(
+                    "{} is saved in version {} of the binary format, but this rsnake only reads version {}",
+                    path.display(),
+                    version,
+                    VERSION
+                ))
+            }
+            Some([]) => return Err(anyhow!("{} is cut off", path.display())),
+            None => serde_json::from_slice(&contents)
+                .with_context(|| path.display().to_string())?,
+        };
+        check_version(path, &run)?;
+        Ok(run)
+    }
+}
+
+/// Fails for a run recorded in another version of the game, which wouldn't
+/// play back as it was played.
+fn check_version(path: &Path, run: &BestRun) -> Result<()> {
+    if run.replay.is_current() {
+        return Ok(());
+    }
+    Err(anyhow!(
+        "{} was recorded in version {} of the game, but this rsnake only plays back version {}",
+        path.display(),
+        run.replay.version(),
+        Replay::current_version()
+    ))
+}
+
+/// Bincode with variable-length integers, as most of a run is small numbers.
+fn binary() -> impl Options {
+    bincode::DefaultOptions::new()
+}
+
+fn path(file: &str) -> Option<PathBuf> {
+    ProjectDirs::from("", "", "rsnake").map(|dirs| dirs.data_dir().join(file))
+}
+
+/// A missing or unreadable file just means there's no best run yet.
+pub fn load() -> Option<BestRun> {
+    read("best_run.json")
+}
+
+pub fn save(run: &BestRun) -> Result<()> {
+    write("best_run.json", run)
+}
+
+/// A finished single-player game's score, how long the snake got, the mode
+/// it was played in and when it finished in seconds since the Unix epoch.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ScoreEntry {
+    pub score: u32,
+    pub length: usize,
+    pub mode: Mode,
+    pub at: u64,
+}
+
+/// What the leaderboard is ordered by, best first.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ScoreSort {
+    Score,
+    Length,
+    /// Newest first.
+    Date,
+}
+
+impl ScoreSort {
+    pub fn name(&self) -> &'static str {
+        match self {
+            ScoreSort::Score => "score",
+            ScoreSort::Length => "length",
+            ScoreSort::Date => "date",
+        }
+    }
+
+    /// The order after this one, wrapping round.
+    pub fn next(self) -> ScoreSort {
+        match self {
+            ScoreSort::Score => ScoreSort::Length,
+            ScoreSort::Length => ScoreSort::Date,
+            ScoreSort::Date => ScoreSort::Score,
+        }
+    }
+
+    /// Which of `a` and `b` goes first. Ties go to the newer.
+    pub fn compare(&self, a: &ScoreEntry, b: &ScoreEntry) -> Ordering {
+        let by = match self {
+            ScoreSort::Score => b.score.cmp(&a.score),
+            ScoreSort::Length => b.length.cmp(&a.length),
+            ScoreSort::Date => Ordering::Equal,
+        };
+        by.then(b.at.cmp(&a.at))
+    }
+}
+
+/// `entries` from `mode`, or every mode if that's `None`, in `sort` order.
+pub fn leaderboard(
+    entries: &[ScoreEntry],
+    sort: ScoreSort,
+    mode: Option<Mode>,
+) -> Vec<&ScoreEntry> {
+    let mut board: Vec<&ScoreEntry> = entries
+        .iter()
+        .filter(|entry| mode.is_none_or(|mode| entry.mode == mode))
+        .collect();
+    board.sort_by(|a, b| sort.compare(a, b));
+    board
+}
+
+/// The latest scores, oldest first.
+pub fn load_scores() -> Vec<ScoreEntry> {
+    read("scores.json").unwrap_or_default()
+}
+
+/// Adds a game's `score`, with the `length` the snake got to in `mode`, to
+/// the latest scores, dropping the oldest past `RECENT_SCORES`.
+pub fn save_score(score: u32, length: usize, mode: Mode) -> Result<()> {
+    let at = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |since| since.as_secs());
+    let mut scores = load_scores();
+    scores.push(ScoreEntry {
+        score,
+        length,
+        mode,
+        at,
+    });
+    let excess = scores.len().saturating_sub(RECENT_SCORES);
+    scores.drain(..excess);
+    write("scores.json", &scores)
+}
+
+/// The most turns made on one `--trainer` course, zero if it's never been
+/// tried.
+pub fn load_turns() -> u32 {
+    read("best_turns.json").unwrap_or(0)
+}
+
+pub fn save_turns(turns: u32) -> Result<()> {
+    write("best_turns.json", &turns)
+}
+
+fn read<T: DeserializeOwned>(file: &str) -> Option<T> {
+    let contents = fs::read_to_string(path(file)?).ok()?;
+    serde_json::from_str(&contents).ok()
+}
+
+fn write<T: Serialize>(file: &str, value: &T) -> Result<()> {
+    let path = path(file).ok_or_else(|| anyhow!("no data directory to save to"))?;
+
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+
+    fs::write(path, serde_json::to_string(value)?)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rsnake::Game;
+    use std::env;
+
+    fn run() -> BestRun {
+        let mut game = Game::with_seed(1, 3);
+        game.tick().unwrap();
+        BestRun {
+            score: 4,
+            replay: game.replay().clone(),
+        }
+    }
+
+    /// Somewhere to save a run to that no other test uses.
+    fn temp(name: &str) -> PathBuf {
+        env::temp_dir().join(format!("rsnake-{}-{}", std::process::id(), name))
+    }
+
+    #[test]
+    fn runs_round_trip_in_both_formats() {
+        for name in ["run.json", "run.bin"] {
+            let path = temp(name);
+            run().export(&path).unwrap();
+            let read = BestRun::import(&path).unwrap();
+            fs::remove_file(&path).unwrap();
+            assert_eq!(read.score, 4);
+            assert_eq!(
+                serde_json::to_string(&read.replay).unwrap(),
+                serde_json::to_string(&run().replay).unwrap()
+            );
+        }
+    }
+
+    #[test]
+    fn json_and_binary_saves_play_back_the_same_game() {
+        let mut game = Game::with_seed(1, 8);
+        game.place_obstacles(4);
+        for _ in 0..60 {
+            if let Some(direction) = rsnake::ai::next_move(&game) {
+                game.change_direction(0, direction);
+            }
+            if game.tick().is_err() {
+                break;
+            }
+        }
+        let run = BestRun {
+            score: game.snakes()[0].score(),
+            replay: game.replay().clone(),
+        };
+
+        let mut saved = Vec::new();
+        for name in ["same.json", "same.bin"] {
+            let path = temp(name);
+            run.export(&path).unwrap();
+            let read = BestRun::import(&path).unwrap();
+            saved.push(fs::metadata(&path).unwrap().len());
+            fs::remove_file(&path).unwrap();
+            let played = read.replay.state_at(game.ticks()).unwrap();
+            assert_eq!(played.obstacles(), game.obstacles(), "{}", name);
+            // Obstacles are a set, in no order that JSON would keep.
+            let json = |game: &Game| {
+                let mut json = serde_json::to_value(game).unwrap();
+                json.as_object_mut().unwrap().remove("obstacles");
+                json
+            };
+            assert_eq!(json(&played), json(&game), "{}", name);
+        }
+        assert!(saved[1] < saved[0], "{:?}", saved);
+    }
+
+    #[test]
+    fn a_cut_off_binary_run_is_an_error() {
+        let path = temp("cut.bin");
+        fs::write(&path, MAGIC).unwrap();
+        let err = BestRun::import(&path).unwrap_err();
+        fs::remove_file(&path).unwrap();
+        assert!(err.to_string().contains("cut off"), "{}", err);
+    }
+
+    #[test]
+    fn binary_runs_from_another_format_version_are_turned_away() {
+        let path = temp("future.bin");
+        fs::write(&path, [MAGIC, &[VERSION + 1], b"..."].concat()).unwrap();
+        let err = BestRun::import(&path).unwrap_err();
+        fs::remove_file(&path).unwrap();
+        assert!(err.to_string().contains("version 3"), "{}", err);
+    }
+
+    #[test]
+    fn runs_from_an_older_game_are_turned_away() {
+        let path = temp("old.json");
+        let mut json: serde_json::Value = serde_json::to_value(run()).unwrap();
+        json["replay"].as_object_mut().unwrap().remove("version");
+        fs::write(&path, json.to_string()).unwrap();
+        let err = BestRun::import(&path).unwrap_err();
+        fs::remove_file(&path).unwrap();
+        assert!(err.to_string().contains("version 1"), "{}", err);
+    }
+
+    fn entry(score: u32, length: usize, mode: Mode, at: u64) -> ScoreEntry {
+        ScoreEntry {
+            score,
+            length,
+            mode,
+            at,
+        }
+    }
+
+    fn entries() -> Vec<ScoreEntry> {
+        vec![
+            entry(5, 9, Mode::Classic, 100),
+            entry(9, 4, Mode::Timed, 200),
+            entry(7, 12, Mode::Classic, 300),
+            entry(9, 6, Mode::Walls, 400),
+        ]
+    }
+
+    fn scores(board: &[&ScoreEntry]) -> Vec<(u32, u64)> {
+        board.iter().map(|entry| (entry.score, entry.at)).collect()
+    }
+
+    #[test]
+    fn sorts_by_score_with_ties_to_the_newer() {
+        let entries = entries();
+        let board = leaderboard(&entries, ScoreSort::Score, None);
+        assert_eq!(scores(&board), [(9, 400), (9, 200), (7, 300), (5, 100)]);
+    }
+
+    #[test]
+    fn sorts_by_length() {
+        let entries = entries();
+        let board = leaderboard(&entries, ScoreSort::Length, None);
+        let lengths: Vec<usize> = board.iter().map(|entry| entry.length).collect();
+        assert_eq!(lengths, [12, 9, 6, 4]);
+    }
+
+    #[test]
+    fn sorts_by_date_newest_first() {
+        let entries = entries();
+        let board = leaderboard(&entries, ScoreSort::Date, None);
+        let at: Vec<u64> = board.iter().map(|entry| entry.at).collect();
+        assert_eq!(at, [400, 300, 200, 100]);
+    }
+
+    #[test]
+    fn filters_by_mode() {
+        let entries = entries();
+        let board = leaderboard(&entries, ScoreSort::Score, Some(Mode::Classic));
+        assert_eq!(scores(&board), [(7, 300), (5, 100)]);
+        assert!(board.iter().all(|entry| entry.mode == Mode::Classic));
+    }
+
+    #[test]
+    fn filtering_keeps_the_order_and_can_leave_nothing() {
+        let entries = entries();
+        let board = leaderboard(&entries, ScoreSort::Length, Some(Mode::Classic));
+        let lengths: Vec<usize> = board.iter().map(|entry| entry.length).collect();
+        assert_eq!(lengths, [12, 9]);
+        // Only the first of them, which is classic.
+        assert!(leaderboard(&entries[..1], ScoreSort::Score, Some(Mode::Timed)).is_empty());
+    }
+
+    #[test]
+    fn sorts_cycle_round() {
+        let sort = ScoreSort::Score;
+        assert_eq!(sort.next(), ScoreSort::Length);
+        assert_eq!(sort.next().next(), ScoreSort::Date);
+        assert_eq!(sort.next().next().next(), ScoreSort::Score);
+    }
+}
@@ -0,0 +1,124 @@
+use anyhow::{anyhow, Result};
+use std::time::Duration;
+
+/// One board of a `Campaign`.
+#[derive(Debug, Clone)]
+pub struct LevelDef {
+    /// How many obstacles are scattered over the board.
+    pub obstacles: u16,
+    /// Points to score on this level to clear it.
+    pub target: u32,
+    /// How long each tick lasts.
+    pub tick: Duration,
+}
+
+/// A run of levels played one after another, each cleared by reaching its
+/// target score.
+#[derive(Debug, Clone)]
+pub struct Campaign {
+    levels: Vec<LevelDef>,
+    current: usize,
+}
+
+impl Campaign {
+    pub fn new(levels: Vec<LevelDef>) -> Result<Campaign> {
+        if levels.is_empty() {
+            return Err(anyhow!("a campaign needs at least one level"));
+        }
+
+        Ok(Campaign { levels, current: 0 })
+    }
+
+    pub fn level(&self) -> &LevelDef {
+        &self.levels[self.current]
+    }
+
+    /// The current level, counting from one.
+    pub fn number(&self) -> usize {
+        self.current + 1
+    }
+
+    /// Moves on to the next level, or returns `false` if the current one was
+    /// the last.
+    pub fn advance(&mut self) -> bool {
+        if self.current + 1 < self.levels.len() {
+            self.current += 1;
+            true
+        } else {
+            false
+        }
+    }
+}
+
+impl Default for Campaign {
+    /// Four levels, each with more obstacles, a higher target and a faster
+    /// snake than the last.
+    fn default() -> Campaign {
+        let level = |obstacles, target, tick| LevelDef {
+            obstacles,
+            target,
+            tick: Duration::from_millis(tick),
+        };
+
+        Campaign {
+            levels: vec![
+                level(0, 5, 120),
+                level(6, 8, 100),
+                level(12, 10, 85),
+                level(18, 12, 70),
+            ],
+            current: 0,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn level(target: u32) -> LevelDef {
+        LevelDef {
+            obstacles: 0,
+            target,
+            tick: Duration::from_millis(100),
+        }
+    }
+
+    #[test]
+    fn advancing_moves_through_the_levels_in_order() {
+        let mut campaign = Campaign::new(vec![level(1), level(2), level(3)]).unwrap();
+        assert_eq!((campaign.number(), campaign.level().target), (1, 1));
+        assert!(campaign.advance());
+        assert_eq!((campaign.number(), campaign.level().target), (2, 2));
+        assert!(campaign.advance());
+        assert_eq!((campaign.number(), campaign.level().target), (3, 3));
+    }
+
+    #[test]
+    fn the_last_level_completes_the_campaign() {
+        let mut campaign = Campaign::new(vec![level(1), level(2)]).unwrap();
+        assert!(campaign.advance());
+        assert!(!campaign.advance());
+        assert!(!campaign.advance());
+        assert_eq!((campaign.number(), campaign.level().target), (2, 2));
+    }
+
+    #[test]
+    fn a_campaign_needs_a_level() {
+        assert!(Campaign::new(Vec::new()).is_err());
+    }
+
+    #[test]
+    fn each_default_level_is_harder_than_the_last() {
+        let mut campaign = Campaign::default();
+        let mut previous = campaign.level().clone();
+        while campaign.advance() {
+            let level = campaign.level();
+            assert!(level.obstacles > previous.obstacles);
+            assert!(level.target > previous.target);
+            assert!(level.tick < previous.tick);
+            previous = level.clone();
+        }
+        assert_eq!(campaign.number(), 4);
+    }
+}
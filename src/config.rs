@@ -0,0 +1,833 @@
+//! Settings read from `config.toml` in the user's config directory.
+//!
+//! ```toml
+//! # Whether to list the keys you can press beneath the board.
+//! show_hints = true
+//!
+//! # Pick the mode, difficulty and board size on a menu before a
+//! # single-player game, rather than starting straight away.
+//! start_menu = true
+//!
+//! # Pause when the terminal loses focus. Turn this off if your terminal
+//! # doesn't report focus changes.
+//! pause_on_focus_loss = true
+//!
+//! # A direction key pressed again within this many milliseconds of the last
+//! # time is ignored, so holding a key down doesn't pile up turns.
+//! repeat_debounce_ms = 50
+//!
+//! # Move as soon as a turn is pressed instead of at the end of the tick,
+//! # which makes slow levels feel snappier but lets you speed the snake up.
+//! instant_turns = false
+//!
+//! # Speed the snake up as it grows: "easy" starts slower and picks up
+//! # gently, "normal" starts at the usual speed and "hard" starts faster and
+//! # gets quicker sooner. Left out, the speed never changes.
+//! difficulty = "normal"
+//!
+//! # Whether the snake comes back in on the other side when it goes off the
+//! # left or right edge, and the top or bottom. An edge that doesn't wrap is a
+//! # wall.
+//! wrap_x = true
+//! wrap_y = true
+//!
+//! # Bounce the snake back off an edge that doesn't wrap instead of crashing
+//! # into it. Bouncing straight back into its own body still bites itself.
+//! bounce = false
+//!
+//! # Points lost each time the snake goes through an edge that wraps, though
+//! # the score never drops below zero.
+//! wrap_penalty = 0
+//!
+//! # Mark the edges that wrap with arrows on the border.
+//! wrap_markers = true
+//!
+//! # Let the snake turn straight back the way it came, which bites itself
+//! # unless it's only the head.
+//! allow_reverse = false
+//!
+//! # Eating scores but never grows the snake, to see how long you can last at
+//! # the length you start with.
+//! no_growth = false
+//!
+//! # Each piece of food eaten within 20 ticks (two seconds at the default
+//! # speed) of the last scores one more time over than that one did, up to
+//! # five times, until the streak lapses.
+//! combos = false
+//!
+//! # "blocks" draws the snake as solid cells, "lines" as a connected line.
+//! body_style = "blocks"
+//!
+//! # The colours the snakes, obstacles and walls are drawn in: "classic",
+//! # "ocean", "ember" or "mono". `rsnake --list-themes` lists them.
+//! theme = "classic"
+//!
+//! # The key that cycles through the themes during a game. With
+//! # `persist_theme` on, the theme picked in the menu or with it is written
+//! # back here as `theme` on quitting.
+//! theme_key = "c"
+//! persist_theme = false
+//!
+//! # Shade the border from green through yellow to red as danger grows:
+//! # "space" for how little of the board the snake can still reach,
+//! # "hunger" for how close it is to losing a segment, and "shrink" for how
+//! # soon the next ring closes in. "none" leaves it the terminal's color.
+//! border_danger = "none"
+//!
+//! # How many columns wide each cell of the board is drawn. Terminal
+//! # characters are about twice as tall as they're wide, so 2 keeps cells
+//! # roughly square and 1 fits a bigger board on screen.
+//! cell_width = 2
+//!
+//! # How many frames to draw around the board, one inside the other, and how
+//! # many blank cells to leave between the innermost and the board.
+//! border_thickness = 1
+//! padding = 0
+//!
+//! # Make food pulse between two shades so it stands out.
+//! pulse_food = false
+//!
+//! # Show the whole board shrunk down beside it. `m` toggles it while playing.
+//! minimap = false
+//!
+//! # Light up the snake's head in this color for a moment when it eats.
+//! eat_flash = true
+//! eat_flash_color = "white"
+//!
+//! # "bell" rings the terminal bell when the snake eats, twice for golden
+//! # food, and three times when it dies. "silent" makes no sound.
+//! sound = "silent"
+//!
+//! # Leave a short glow behind the snake as it moves.
+//! trail = false
+//!
+//! # Shown over the board when the game ends, beneath the contents of
+//! # `game_over_art` if set. A relative art path is looked up next to this file.
+//! game_over_message = "game over :("
+//! game_over_art = "skull.txt"
+//!
+//! # Have the snake fall apart from the tail over about a second when it
+//! # dies, before the game over message. Any key skips it.
+//! death_animation = false
+//!
+//! # How many obstacles to scatter over the board. Eating a pickaxe lets the
+//! # snake smash through one.
+//! obstacles = 0
+//!
+//! # Close in a ring around the edge of the board this often, in milliseconds.
+//! # Zero keeps the board the same size. The ring about to close flashes for
+//! # `shrink_warning_ms` beforehand.
+//! shrink_interval_ms = 0
+//! shrink_warning_ms = 1000
+//!
+//! # Whether the snake keeps its length and score from one level of
+//! # `--campaign` to the next, rather than starting afresh.
+//! campaign_carry_over = true
+//!
+//! # Whether each new life with `--lives` gets freshly scattered obstacles.
+//! regen_obstacles_on_respawn = false
+//!
+//! # For this long at the start and after each lost life the snake blinks and
+//! # can pass through itself and obstacles, in milliseconds. Walls still count.
+//! grace_period_ms = 0
+//!
+//! # The snake loses a segment off its tail each time it goes this long
+//! # without eating, in milliseconds, and starves once it's down to
+//! # `min_length`. Zero turns hunger off.
+//! hunger_interval_ms = 0
+//!
+//! # How long the board stays empty after food is eaten, in milliseconds.
+//! food_respawn_delay_ms = 0
+//!
+//! # Rotten food never shrinks the snake shorter than this. With 1, or 0,
+//! # which means the same, a snake it would shrink away to nothing starves
+//! # and the game ends.
+//! min_length = 1
+//!
+//! # How many pieces of food are on the board at once.
+//! food_count = 1
+//!
+//! # Never put food right in front of the snake's head, or right behind it.
+//! food_clear_of_head = false
+//!
+//! # Put food at these cells one after another instead of at random, and win
+//! # once the last is eaten. Cells run from [1, 1] at the top left
+//! # to [20, 10] at the bottom right.
+//! food_path = [[5, 5], [15, 5], [15, 8]]
+//!
+//! # Relative odds of each kind of food being the next to spawn. Kinds left
+//! # out never spawn.
+//! [food_weights]
+//! normal = 77
+//! golden = 15
+//! rotten = 5
+//! magnet = 3
+//!
+//! # Named sets of settings picked with `--profile NAME`, each laid over the
+//! # settings above, which are laid over the defaults. A profile's
+//! # `food_weights` replaces the table above rather than adding to it.
+//! # Command-line flags win over all of them.
+//! [profiles.hardcore]
+//! wrap_x = false
+//! wrap_y = false
+//! obstacles = 20
+//! ```
+
+use anyhow::{anyhow, Context, Result};
+use directories::ProjectDirs;
+use serde::Deserialize;
+use std::{
+    collections::BTreeMap,
+    fs, io,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+use tui::style::Color;
+
+use crate::{
+    sound::SoundProfile,
+    theme::{self, Theme, THEMES},
+};
+use rsnake::{Difficulty, FoodKind, FoodTable, Vector};
+
+/// The file as written, before validation.
+#[derive(Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct File {
+    show_hints: Option<bool>,
+    start_menu: Option<bool>,
+    pause_on_focus_loss: Option<bool>,
+    instant_turns: Option<bool>,
+    difficulty: Option<Difficulty>,
+    repeat_debounce_ms: Option<u64>,
+    allow_reverse: Option<bool>,
+    no_growth: Option<bool>,
+    combos: Option<bool>,
+    wrap_x: Option<bool>,
+    wrap_y: Option<bool>,
+    bounce: Option<bool>,
+    wrap_penalty: Option<u32>,
+    wrap_markers: Option<bool>,
+    body_style: Option<BodyStyle>,
+    theme: Option<String>,
+    theme_key: Option<char>,
+    persist_theme: Option<bool>,
+    border_danger: Option<BorderDanger>,
+    cell_width: Option<u16>,
+    border_thickness: Option<u16>,
+    padding: Option<u16>,
+    trail: Option<bool>,
+    eat_flash: Option<bool>,
+    eat_flash_color: Option<ColorName>,
+    sound: Option<SoundProfile>,
+    minimap: Option<bool>,
+    pulse_food: Option<bool>,
+    game_over_message: Option<String>,
+    game_over_art: Option<PathBuf>,
+    death_animation: Option<bool>,
+    obstacles: Option<u16>,
+    regen_obstacles_on_respawn: Option<bool>,
+    grace_period_ms: Option<u64>,
+    hunger_interval_ms: Option<u64>,
+    campaign_carry_over: Option<bool>,
+    shrink_interval_ms: Option<u64>,
+    shrink_warning_ms: Option<u64>,
+    food_respawn_delay_ms: Option<u64>,
+    food_count: Option<u32>,
+    food_clear_of_head: Option<bool>,
+    food_path: Option<Vec<Vector>>,
+    min_length: Option<u32>,
+    food_weights: Option<BTreeMap<FoodKind, f64>>,
+    profiles: Option<BTreeMap<String, File>>,
+}
+
+impl File {
+    /// These settings with any left out taken from `base`.
+    fn over(self, base: File) -> File {
+        File {
+            show_hints: self.show_hints.or(base.show_hints),
+            start_menu: self.start_menu.or(base.start_menu),
+            pause_on_focus_loss: self.pause_on_focus_loss.or(base.pause_on_focus_loss),
+            instant_turns: self.instant_turns.or(base.instant_turns),
+            difficulty: self.difficulty.or(base.difficulty),
+            repeat_debounce_ms: self.repeat_debounce_ms.or(base.repeat_debounce_ms),
+            allow_reverse: self.allow_reverse.or(base.allow_reverse),
+            no_growth: self.no_growth.or(base.no_growth),
+            combos: self.combos.or(base.combos),
+            wrap_x: self.wrap_x.or(base.wrap_x),
+            wrap_y: self.wrap_y.or(base.wrap_y),
+            bounce: self.bounce.or(base.bounce),
+            wrap_penalty: self.wrap_penalty.or(base.wrap_penalty),
+            wrap_markers: self.wrap_markers.or(base.wrap_markers),
+            body_style: self.body_style.or(base.body_style),
+            theme: self.theme.or(base.theme),
+            theme_key: self.theme_key.or(base.theme_key),
+            persist_theme: self.persist_theme.or(base.persist_theme),
+            border_danger: self.border_danger.or(base.border_danger),
+            cell_width: self.cell_width.or(base.cell_width),
+            border_thickness: self.border_thickness.or(base.border_thickness),
+            padding: self.padding.or(base.padding),
+            trail: self.trail.or(base.trail),
+            eat_flash: self.eat_flash.or(base.eat_flash),
+            eat_flash_color: self.eat_flash_color.or(base.eat_flash_color),
+            sound: self.sound.or(base.sound),
+            minimap: self.minimap.or(base.minimap),
+            pulse_food: self.pulse_food.or(base.pulse_food),
+            game_over_message: self.game_over_message.or(base.game_over_message),
+            game_over_art: self.game_over_art.or(base.game_over_art),
+            death_animation: self.death_animation.or(base.death_animation),
+            obstacles: self.obstacles.or(base.obstacles),
+            regen_obstacles_on_respawn: self
+                .regen_obstacles_on_respawn
+                .or(base.regen_obstacles_on_respawn),
+            grace_period_ms: self.grace_period_ms.or(base.grace_period_ms),
+            hunger_interval_ms: self.hunger_interval_ms.or(base.hunger_interval_ms),
+            campaign_carry_over: self.campaign_carry_over.or(base.campaign_carry_over),
+            shrink_interval_ms: self.shrink_interval_ms.or(base.shrink_interval_ms),
+            shrink_warning_ms: self.shrink_warning_ms.or(base.shrink_warning_ms),
+            food_respawn_delay_ms: self.food_respawn_delay_ms.or(base.food_respawn_delay_ms),
+            food_count: self.food_count.or(base.food_count),
+            food_clear_of_head: self.food_clear_of_head.or(base.food_clear_of_head),
+            food_path: self.food_path.or(base.food_path),
+            min_length: self.min_length.or(base.min_length),
+            food_weights: self.food_weights.or(base.food_weights),
+            profiles: None,
+        }
+    }
+}
+
+/// One of the terminal's named colors.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ColorName {
+    Black,
+    Red,
+    Green,
+    Yellow,
+    Blue,
+    Magenta,
+    Cyan,
+    Gray,
+    DarkGray,
+    LightRed,
+    LightGreen,
+    LightYellow,
+    LightBlue,
+    LightMagenta,
+    LightCyan,
+    White,
+}
+
+impl From<ColorName> for Color {
+    fn from(name: ColorName) -> Color {
+        match name {
+            ColorName::Black => Color::Black,
+            ColorName::Red => Color::Red,
+            ColorName::Green => Color::Green,
+            ColorName::Yellow => Color::Yellow,
+            ColorName::Blue => Color::Blue,
+            ColorName::Magenta => Color::Magenta,
+            ColorName::Cyan => Color::Cyan,
+            ColorName::Gray => Color::Gray,
+            ColorName::DarkGray => Color::DarkGray,
+            ColorName::LightRed => Color::LightRed,
+            ColorName::LightGreen => Color::LightGreen,
+            ColorName::LightYellow => Color::LightYellow,
+            ColorName::LightBlue => Color::LightBlue,
+            ColorName::LightMagenta => Color::LightMagenta,
+            ColorName::LightCyan => Color::LightCyan,
+            ColorName::White => Color::White,
+        }
+    }
+}
+
+/// How a snake's body is drawn.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum BodyStyle {
+    Blocks,
+    Lines,
+}
+
+/// What the border's color warns about.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum BorderDanger {
+    None,
+    Space,
+    Hunger,
+    Shrink,
+}
+
+#[derive(Clone)]
+pub struct Config {
+    pub show_hints: bool,
+    pub start_menu: bool,
+    pub pause_on_focus_loss: bool,
+    pub instant_turns: bool,
+    pub difficulty: Option<Difficulty>,
+    pub repeat_debounce: Duration,
+    pub allow_reverse: bool,
+    pub no_growth: bool,
+    pub combos: bool,
+    pub wrap_x: bool,
+    pub wrap_y: bool,
+    pub bounce: bool,
+    pub wrap_penalty: u32,
+    pub wrap_markers: bool,
+    pub body_style: BodyStyle,
+    /// Where the theme the board is drawn in is in `THEMES`.
+    pub theme: usize,
+    pub theme_key: char,
+    pub persist_theme: bool,
+    pub border_danger: BorderDanger,
+    /// Never zero.
+    pub cell_width: u16,
+    /// Never zero either, as the title is shown in the outermost frame.
+    pub border_thickness: u16,
+    pub padding: u16,
+    pub trail: bool,
+    pub eat_flash: bool,
+    pub eat_flash_color: ColorName,
+    pub sound: SoundProfile,
+    pub minimap: bool,
+    pub pulse_food: bool,
+    pub game_over_message: String,
+    /// The contents of the art file, not its path.
+    pub game_over_art: Option<String>,
+    pub death_animation: bool,
+    pub obstacles: u16,
+    pub regen_obstacles_on_respawn: bool,
+    pub grace_period: Duration,
+    /// Zero means the snake never gets hungry.
+    pub hunger_interval: Duration,
+    pub campaign_carry_over: bool,
+    /// Zero means the board doesn't shrink.
+    pub shrink_interval: Duration,
+    pub shrink_warning: Duration,
+    pub food_respawn_delay: Duration,
+    pub food_count: u32,
+    pub food_clear_of_head: bool,
+    /// Empty means food spawns at random.
+    pub food_path: Vec<Vector>,
+    pub min_length: u32,
+    pub food_table: FoodTable,
+}
+
+impl Default for Config {
+    fn default() -> Config {
+        Config {
+            show_hints: true,
+            start_menu: true,
+            pause_on_focus_loss: true,
+            instant_turns: false,
+            difficulty: None,
+            repeat_debounce: Duration::from_millis(50),
+            allow_reverse: false,
+            no_growth: false,
+            combos: false,
+            wrap_x: true,
+            wrap_y: true,
+            bounce: false,
+            wrap_penalty: 0,
+            wrap_markers: true,
+            body_style: BodyStyle::Blocks,
+            theme: 0,
+            theme_key: 'c',
+            persist_theme: false,
+            border_danger: BorderDanger::None,
+            cell_width: 2,
+            border_thickness: 1,
+            padding: 0,
+            trail: false,
+            eat_flash: true,
+            eat_flash_color: ColorName::White,
+            sound: SoundProfile::Silent,
+            minimap: false,
+            pulse_food: false,
+            game_over_message: String::from("game over :("),
+            game_over_art: None,
+            death_animation: false,
+            obstacles: 0,
+            regen_obstacles_on_respawn: false,
+            grace_period: Duration::ZERO,
+            hunger_interval: Duration::ZERO,
+            campaign_carry_over: true,
+            shrink_interval: Duration::ZERO,
+            shrink_warning: Duration::from_secs(1),
+            food_respawn_delay: Duration::ZERO,
+            food_count: 1,
+            food_clear_of_head: false,
+            food_path: Vec::new(),
+            min_length: 1,
+            food_table: FoodTable::default(),
+        }
+    }
+}
+
+fn path() -> Option<PathBuf> {
+    ProjectDirs::from("", "", "rsnake").map(|dirs| dirs.config_dir().join("config.toml"))
+}
+
+/// `contents` with its top-level `theme` set to `name`, added at the top if
+/// it has none. Everything else, comments included, is left as it was.
+fn with_theme(contents: &str, name: &str) -> String {
+    let setting = format!("theme = {:?}", name);
+    let mut lines: Vec<&str> = contents.lines().collect();
+    // Anything after the first table header belongs to that table.
+    let top = lines
+        .iter()
+        .position(|line| line.trim_start().starts_with('['))
+        .unwrap_or(lines.len());
+    let theme = lines[..top].iter().position(|line| {
+        line.trim_start()
+            .strip_prefix("theme")
+            .is_some_and(|rest| rest.trim_start().starts_with('='))
+    });
+    match theme {
+        Some(at) => lines[at] = &setting,
+        None => lines.insert(0, &setting),
+    }
+    lines.iter().map(|line| format!("{}\n", line)).collect()
+}
+
+impl Config {
+    pub fn theme(&self) -> &'static Theme {
+        &THEMES[self.theme]
+    }
+
+    /// Falls back to the defaults if there's no config file, but a file that
+    /// can't be parsed or holds invalid settings is an error, as is a
+    /// `profile` the file doesn't have.
+    pub fn load(profile: Option<&str>) -> Result<Config> {
+        let path = match path() {
+            Some(path) => path,
+            None if profile.is_some() => {
+                return Err(anyhow!("there's no config file to pick a profile from"))
+            }
+            None => return Ok(Config::default()),
+        };
+
+        let contents = match fs::read_to_string(&path) {
+            Ok(contents) => contents,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => String::new(),
+            Err(err) => return Err(err.into()),
+        };
+        Config::parse(&contents, &path, profile)
+    }
+
+    /// The settings in `contents`, read from the config file at `path`, with
+    /// `profile` laid over them if given.
+    fn parse(contents: &str, path: &Path, profile: Option<&str>) -> Result<Config> {
+        let mut file: File =
+            toml::from_str(contents).map_err(|err| anyhow!("{}: {}", path.display(), err))?;
+
+        let mut profiles = file.profiles.take().unwrap_or_default();
+        if let Some((name, _)) = profiles
+            .iter()
+            .find(|(_, profile)| profile.profiles.is_some())
+        {
+            return Err(anyhow!(
+                "{}: profiles.{} can't have profiles of its own",
+                path.display(),
+                name
+            ));
+        }
+        if let Some(name) = profile {
+            file = profiles
+                .remove(name)
+                .ok_or_else(|| anyhow!("{}: there's no profile named {}", path.display(), name))?
+                .over(file);
+        }
+
+        let food_table = match file.food_weights {
+            Some(weights) => FoodTable::new(weights.into_iter().collect())
+                .map_err(|err| anyhow!("{}: food_weights: {}", path.display(), err))?,
+            None => FoodTable::default(),
+        };
+
+        let game_over_art = match file.game_over_art {
+            Some(art) => {
+                let art = path.parent().map_or(art.clone(), |dir| dir.join(&art));
+                let contents = fs::read_to_string(&art)
+                    .with_context(|| format!("{}: game_over_art", path.display()))?;
+                Some(contents.trim_end().to_string())
+            }
+            None => None,
+        };
+
+        let defaults = Config::default();
+
+        let theme = match file.theme {
+            Some(name) => theme::position(&name).ok_or_else(|| {
+                anyhow!(
+                    "{}: there's no theme named {}, only {}",
+                    path.display(),
+                    name,
+                    theme::available_themes().join(", ")
+                )
+            })?,
+            None => defaults.theme,
+        };
+        let cell_width = file.cell_width.unwrap_or(defaults.cell_width);
+        if cell_width == 0 {
+            return Err(anyhow!("{}: cell_width must be at least 1", path.display()));
+        }
+        let border_thickness = file.border_thickness.unwrap_or(defaults.border_thickness);
+        if border_thickness == 0 {
+            return Err(anyhow!(
+                "{}: border_thickness must be at least 1",
+                path.display()
+            ));
+        }
+
+        Ok(Config {
+            show_hints: file.show_hints.unwrap_or(defaults.show_hints),
+            start_menu: file.start_menu.unwrap_or(defaults.start_menu),
+            pause_on_focus_loss: file
+                .pause_on_focus_loss
+                .unwrap_or(defaults.pause_on_focus_loss),
+            instant_turns: file.instant_turns.unwrap_or(defaults.instant_turns),
+            difficulty: file.difficulty.or(defaults.difficulty),
+            repeat_debounce: file
+                .repeat_debounce_ms
+                .map_or(defaults.repeat_debounce, Duration::from_millis),
+            allow_reverse: file.allow_reverse.unwrap_or(defaults.allow_reverse),
+            no_growth: file.no_growth.unwrap_or(defaults.no_growth),
+            combos: file.combos.unwrap_or(defaults.combos),
+            wrap_x: file.wrap_x.unwrap_or(defaults.wrap_x),
+            wrap_y: file.wrap_y.unwrap_or(defaults.wrap_y),
+            bounce: file.bounce.unwrap_or(defaults.bounce),
+            wrap_penalty: file.wrap_penalty.unwrap_or(defaults.wrap_penalty),
+            wrap_markers: file.wrap_markers.unwrap_or(defaults.wrap_markers),
+            body_style: file.body_style.unwrap_or(defaults.body_style),
+            theme,
+            theme_key: file.theme_key.unwrap_or(defaults.theme_key),
+            persist_theme: file.persist_theme.unwrap_or(defaults.persist_theme),
+            border_danger: file.border_danger.unwrap_or(defaults.border_danger),
+            cell_width,
+            border_thickness,
+            padding: file.padding.unwrap_or(defaults.padding),
+            trail: file.trail.unwrap_or(defaults.trail),
+            eat_flash: file.eat_flash.unwrap_or(defaults.eat_flash),
+            eat_flash_color: file.eat_flash_color.unwrap_or(defaults.eat_flash_color),
+            sound: file.sound.unwrap_or(defaults.sound),
+            minimap: file.minimap.unwrap_or(defaults.minimap),
+            pulse_food: file.pulse_food.unwrap_or(defaults.pulse_food),
+            game_over_message: file.game_over_message.unwrap_or(defaults.game_over_message),
+            game_over_art,
+            death_animation: file.death_animation.unwrap_or(defaults.death_animation),
+            obstacles: file.obstacles.unwrap_or(defaults.obstacles),
+            regen_obstacles_on_respawn: file
+                .regen_obstacles_on_respawn
+                .unwrap_or(defaults.regen_obstacles_on_respawn),
+            grace_period: file
+                .grace_period_ms
+                .map_or(defaults.grace_period, Duration::from_millis),
+            hunger_interval: file
+                .hunger_interval_ms
+                .map_or(defaults.hunger_interval, Duration::from_millis),
+            campaign_carry_over: file
+                .campaign_carry_over
+                .unwrap_or(defaults.campaign_carry_over),
+            shrink_interval: file
+                .shrink_interval_ms
+                .map_or(defaults.shrink_interval, Duration::from_millis),
+            shrink_warning: file
+                .shrink_warning_ms
+                .map_or(defaults.shrink_warning, Duration::from_millis),
+            food_respawn_delay: file
+                .food_respawn_delay_ms
+                .map_or(defaults.food_respawn_delay, Duration::from_millis),
+            food_count: file.food_count.unwrap_or(defaults.food_count),
+            food_clear_of_head: file
+                .food_clear_of_head
+                .unwrap_or(defaults.food_clear_of_head),
+            food_path: file.food_path.unwrap_or(defaults.food_path),
+            min_length: file.min_length.unwrap_or(defaults.min_length),
+            food_table,
+        })
+    }
+
+    /// The art, if any, with the message beneath it.
+    pub fn game_over_banner(&self) -> String {
+        match &self.game_over_art {
+            Some(art) => format!("{}\n{}", art, self.game_over_message),
+            None => self.game_over_message.clone(),
+        }
+    }
+
+    /// Writes this theme into the config file, making one if there isn't
+    /// one yet.
+    pub fn save_theme(&self) -> Result<()> {
+        let path = path().ok_or_else(|| anyhow!("no config directory to save the theme to"))?;
+        let contents = match fs::read_to_string(&path) {
+            Ok(contents) => contents,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => String::new(),
+            Err(err) => return Err(err.into()),
+        };
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(&path, with_theme(&contents, self.theme().name))?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+
+    fn parse(contents: &str) -> Result<Config> {
+        Config::parse(contents, Path::new("config.toml"), None)
+    }
+
+    /// What's wrong with `contents`, which had better be something.
+    fn error(contents: &str) -> String {
+        match parse(contents) {
+            Ok(_) => panic!("{:?} parsed", contents),
+            Err(err) => err.to_string(),
+        }
+    }
+
+    #[test]
+    fn game_over_message_defaults_to_the_sad_face() {
+        let config = parse("").unwrap();
+        assert_eq!(config.game_over_banner(), "game over :(");
+    }
+
+    #[test]
+    fn configured_game_over_message_replaces_the_default() {
+        let config = parse(r#"game_over_message = "splat""#).unwrap();
+        assert_eq!(config.game_over_banner(), "splat");
+    }
+
+    #[test]
+    fn game_over_art_is_read_next_to_the_config_and_goes_above_the_message() {
+        let dir = env::temp_dir().join(format!("rsnake-art-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("skull.txt"), " ___\n(x x)\n").unwrap();
+        let config = Config::parse(
+            "game_over_message = \"rip\"\ngame_over_art = \"skull.txt\"",
+            &dir.join("config.toml"),
+            None,
+        );
+        fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(config.unwrap().game_over_banner(), " ___\n(x x)\nrip");
+    }
+
+    #[test]
+    fn missing_game_over_art_is_an_error() {
+        let err = error(r#"game_over_art = "no-such-art.txt""#);
+        assert!(err.contains("game_over_art"), "{}", err);
+    }
+
+    #[test]
+    fn theme_is_picked_by_a_name_it_lists() {
+        for (i, name) in theme::available_themes().into_iter().enumerate() {
+            let config = parse(&format!("theme = {:?}", name)).unwrap();
+            assert_eq!(config.theme, i);
+            assert_eq!(config.theme().name, name);
+        }
+        assert_eq!(parse("").unwrap().theme().name, "classic");
+
+        let err = error(r#"theme = "neon""#);
+        assert!(
+            err.contains("no theme named neon, only classic, ocean"),
+            "{}",
+            err
+        );
+    }
+
+    #[test]
+    fn theme_key_defaults_to_c() {
+        let config = parse("").unwrap();
+        assert_eq!(config.theme_key, 'c');
+        assert!(!config.persist_theme);
+        assert_eq!(parse(r#"theme_key = "t""#).unwrap().theme_key, 't');
+        assert!(parse(r#"theme_key = "tt""#).is_err());
+    }
+
+    #[test]
+    fn saving_a_theme_replaces_the_old_one_and_keeps_the_rest() {
+        let contents =
+            "# Mine.\ntheme = \"classic\"\nwrap_x = false\n\n[profiles.dark]\ntheme = \"mono\"\n";
+        let saved = with_theme(contents, "ocean");
+        assert_eq!(
+            saved,
+            "# Mine.\ntheme = \"ocean\"\nwrap_x = false\n\n[profiles.dark]\ntheme = \"mono\"\n"
+        );
+        let config = parse(&saved).unwrap();
+        assert_eq!(config.theme().name, "ocean");
+        assert!(!config.wrap_x);
+        let dark = Config::parse(&saved, Path::new("config.toml"), Some("dark")).unwrap();
+        assert_eq!(dark.theme().name, "mono");
+    }
+
+    #[test]
+    fn saving_a_theme_adds_one_to_a_file_without() {
+        assert_eq!(with_theme("", "ember"), "theme = \"ember\"\n");
+        let saved = with_theme("[food_weights]\nnormal = 1\n", "ember");
+        assert_eq!(parse(&saved).unwrap().theme().name, "ember");
+    }
+
+    const PROFILES: &str = r#"
+        wrap_x = false
+        obstacles = 5
+        food_count = 3
+
+        [food_weights]
+        normal = 1
+
+        [profiles.hardcore]
+        obstacles = 20
+        wrap_y = false
+
+        [profiles.golden]
+        [profiles.golden.food_weights]
+        golden = 1
+    "#;
+
+    fn profile(name: &str) -> Result<Config> {
+        Config::parse(PROFILES, Path::new("config.toml"), Some(name))
+    }
+
+    #[test]
+    fn a_profile_overrides_only_what_it_sets() {
+        let defaults = Config::default();
+        let base = parse(PROFILES).unwrap();
+        assert_eq!(base.obstacles, 5);
+        assert!(base.wrap_y);
+
+        let hardcore = profile("hardcore").unwrap();
+        assert_eq!(hardcore.obstacles, 20);
+        assert!(!hardcore.wrap_y);
+        // Left to the settings beneath the profile...
+        assert!(!hardcore.wrap_x);
+        assert_eq!(hardcore.food_count, 3);
+        assert_eq!(hardcore.food_table.weights(), base.food_table.weights());
+        // ...and to the defaults beneath those.
+        assert_eq!(hardcore.show_hints, defaults.show_hints);
+        assert_eq!(hardcore.min_length, defaults.min_length);
+    }
+
+    #[test]
+    fn a_profiles_food_weights_replace_the_tables_beneath() {
+        let golden = profile("golden").unwrap();
+        assert_eq!(golden.food_table.weights(), &[(FoodKind::Golden, 1.0)]);
+        assert_eq!(golden.obstacles, 5);
+    }
+
+    #[test]
+    fn an_unknown_profile_is_an_error() {
+        let err = profile("chill").err().unwrap().to_string();
+        assert!(err.contains("no profile named chill"), "{}", err);
+    }
+
+    #[test]
+    fn profiles_cant_have_profiles_of_their_own() {
+        let err = error("[profiles.a.profiles.b]\nobstacles = 1");
+        assert!(err.contains("profiles.a can't have profiles"), "{}", err);
+    }
+}
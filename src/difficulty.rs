@@ -0,0 +1,45 @@
+use anyhow::{anyhow, Error};
+use serde::{Deserialize, Serialize};
+use std::str::FromStr;
+
+/// How the speed picks up as the snake grows, each tick taking a little less
+/// time for every segment down to a floor.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Difficulty {
+    Easy,
+    Normal,
+    Hard,
+}
+
+impl Difficulty {
+    /// How many times as long each tick takes as usual for a snake `length`
+    /// segments long.
+    pub fn interval_scale(&self, length: usize) -> f64 {
+        // How long a tick starts out, how much of it each segment past the
+        // head keeps, and the shortest it gets.
+        let (start, per_segment, floor) = match self {
+            Difficulty::Easy => (1.25, 0.99, 0.75),
+            Difficulty::Normal => (1.0, 0.985, 0.5),
+            Difficulty::Hard => (0.8, 0.975, 0.35),
+        };
+        let grown = length.saturating_sub(1).min(i32::MAX as usize) as i32;
+        (start * f64::powi(per_segment, grown)).max(floor)
+    }
+}
+
+impl FromStr for Difficulty {
+    type Err = Error;
+
+    fn from_str(name: &str) -> Result<Difficulty, Error> {
+        match name {
+            "easy" => Ok(Difficulty::Easy),
+            "normal" => Ok(Difficulty::Normal),
+            "hard" => Ok(Difficulty::Hard),
+            _ => Err(anyhow!(
+                "no difficulty called {}, pick easy, normal or hard",
+                name
+            )),
+        }
+    }
+}
@@ -0,0 +1,196 @@
+use anyhow::{anyhow, Result};
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+use crate::Vector;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum FoodKind {
+    Normal,
+    Golden,
+    Rotten,
+    /// Pulls food toward the snake for a while instead of feeding it.
+    Magnet,
+    /// Lets the snake smash through the next obstacle it hits.
+    Pickaxe,
+    /// Feeds the snake like normal food, then hurries the game along for a
+    /// while.
+    Speed,
+}
+
+impl FoodKind {
+    pub fn points(&self) -> u32 {
+        match self {
+            FoodKind::Normal | FoodKind::Speed => 1,
+            FoodKind::Golden => 3,
+            FoodKind::Rotten => 0,
+            FoodKind::Magnet | FoodKind::Pickaxe => 0,
+        }
+    }
+
+    /// How many segments eating it adds to the snake. Rotten food shrinks it
+    /// instead, though never below the game's minimum length.
+    pub fn growth(&self) -> i32 {
+        match self {
+            FoodKind::Normal | FoodKind::Speed => 1,
+            FoodKind::Golden => 3,
+            FoodKind::Rotten => -2,
+            FoodKind::Magnet | FoodKind::Pickaxe => 0,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Food {
+    pub at: Vector,
+    pub kind: FoodKind,
+}
+
+/// Where food appears.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FoodSource {
+    /// Anywhere the snake can reach, chosen by the game's seed.
+    #[default]
+    Random,
+    /// Normal food at each of these cells in turn, one at a time. Eating the
+    /// last clears the board.
+    Scripted(Vec<Vector>),
+}
+
+/// How likely each kind of food is to be the next one spawned.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FoodTable {
+    weights: Vec<(FoodKind, f64)>,
+}
+
+impl FoodTable {
+    /// Weights are relative to each other and get normalized to sum to one.
+    /// They must not be negative and at least one must be positive.
+    pub fn new(weights: Vec<(FoodKind, f64)>) -> Result<FoodTable> {
+        if let Some((kind, weight)) = weights
+            .iter()
+            .find(|(_, weight)| !weight.is_finite() || *weight < 0.0)
+        {
+            return Err(anyhow!("invalid weight {} for {:?} food", weight, kind));
+        }
+
+        let total: f64 = weights.iter().map(|(_, weight)| weight).sum();
+        if total <= 0.0 {
+            return Err(anyhow!("at least one kind of food needs a positive weight"));
+        }
+
+        Ok(FoodTable {
+            weights: weights
+                .into_iter()
+                .map(|(kind, weight)| (kind, weight / total))
+                .collect(),
+        })
+    }
+
+    pub fn weights(&self) -> &[(FoodKind, f64)] {
+        &self.weights
+    }
+}
+
+impl Default for FoodTable {
+    fn default() -> FoodTable {
+        FoodTable {
+            weights: vec![
+                (FoodKind::Normal, 0.77),
+                (FoodKind::Golden, 0.15),
+                (FoodKind::Rotten, 0.05),
+                (FoodKind::Magnet, 0.03),
+            ],
+        }
+    }
+}
+
+/// Draws a kind of food with probability proportional to its weight, falling
+/// back to normal food if none of the weights are positive.
+pub fn pick_food_kind(rng: &mut impl Rng, weights: &[(FoodKind, f64)]) -> FoodKind {
+    let total: f64 = weights.iter().map(|(_, weight)| weight.max(0.0)).sum();
+    if total <= 0.0 {
+        return FoodKind::Normal;
+    }
+
+    let mut roll = rng.gen_range(0.0..total);
+
+    for (kind, weight) in weights {
+        let weight = weight.max(0.0);
+        if roll < weight {
+            return *kind;
+        }
+        roll -= weight;
+    }
+
+    // Rounding can leave the roll just past the last weight.
+    weights
+        .iter()
+        .rev()
+        .find(|(_, weight)| *weight > 0.0)
+        .map_or(FoodKind::Normal, |(kind, _)| *kind)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::{rngs::StdRng, SeedableRng};
+
+    #[test]
+    fn draws_follow_the_weights() {
+        let table = FoodTable::new(vec![
+            (FoodKind::Normal, 8.0),
+            (FoodKind::Golden, 1.5),
+            (FoodKind::Rotten, 0.5),
+        ])
+        .unwrap();
+        let mut rng = StdRng::seed_from_u64(3);
+        let draws = 20_000;
+        let mut counts = [0; 3];
+        for _ in 0..draws {
+            match pick_food_kind(&mut rng, table.weights()) {
+                FoodKind::Normal => counts[0] += 1,
+                FoodKind::Golden => counts[1] += 1,
+                FoodKind::Rotten => counts[2] += 1,
+                kind => panic!("drew {:?}, which has no weight", kind),
+            }
+        }
+        for (count, expected) in counts.iter().zip([0.8, 0.15, 0.05]) {
+            let share = f64::from(*count) / f64::from(draws);
+            assert!(
+                (share - expected).abs() < 0.01,
+                "{} against {}",
+                share,
+                expected
+            );
+        }
+    }
+
+    #[test]
+    fn weights_are_normalized() {
+        let table = FoodTable::new(vec![(FoodKind::Normal, 3.0), (FoodKind::Golden, 1.0)]).unwrap();
+        assert_eq!(
+            table.weights(),
+            [(FoodKind::Normal, 0.75), (FoodKind::Golden, 0.25)]
+        );
+    }
+
+    #[test]
+    fn bad_weights_are_refused() {
+        assert!(FoodTable::new(vec![(FoodKind::Normal, -1.0)]).is_err());
+        assert!(FoodTable::new(vec![(FoodKind::Normal, f64::NAN)]).is_err());
+        assert!(FoodTable::new(vec![(FoodKind::Normal, 0.0)]).is_err());
+        assert!(FoodTable::new(Vec::new()).is_err());
+    }
+
+    #[test]
+    fn no_positive_weight_draws_normal_food() {
+        let mut rng = StdRng::seed_from_u64(3);
+        assert_eq!(
+            pick_food_kind(&mut rng, &[(FoodKind::Golden, 0.0)]),
+            FoodKind::Normal
+        );
+    }
+}
@@ -0,0 +1,3570 @@
+use log::{debug, trace};
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha12Rng;
+use serde::{Deserialize, Serialize};
+use std::{
+    cmp::Ordering,
+    collections::{HashMap, HashSet, VecDeque},
+    fmt, iter, mem,
+};
+
+use anyhow::{anyhow, Result};
+
+use crate::{
+    pick_food_kind, CellKind, Difficulty, Food, FoodKind, FoodSource, FoodTable, Grid, Replay,
+    TerrainKind,
+};
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct Vector(pub u16, pub u16);
+
+impl Vector {
+    pub fn new(x: u16, y: u16) -> Vector {
+        Vector(x, y)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Dimensions {
+    pub x: (u16, u16),
+    pub y: (u16, u16),
+}
+
+impl Dimensions {
+    /// A board `width` cells across and `height` down, with the top left
+    /// cell at (1, 1).
+    pub fn new(width: u16, height: u16) -> Dimensions {
+        Dimensions {
+            x: (1, width),
+            y: (1, height),
+        }
+    }
+
+    /// Fails for a board smaller than `MIN_SIDE` either way, or with its
+    /// ends the wrong way round.
+    fn validate(&self) -> Result<()> {
+        let side = |(start, end): (u16, u16)| end.checked_sub(start).map(|d| d + 1).unwrap_or(0);
+        let (width, height) = (side(self.x), side(self.y));
+        if width < MIN_SIDE || height < MIN_SIDE {
+            return Err(anyhow!(
+                "the board is {}x{} but has to be at least {}x{}",
+                width,
+                height,
+                MIN_SIDE,
+                MIN_SIDE
+            ));
+        }
+        Ok(())
+    }
+}
+
+impl Default for Dimensions {
+    fn default() -> Dimensions {
+        Dimensions {
+            x: (1, 20),
+            y: (1, 10),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Direction {
+    Left,
+    Up,
+    Right,
+    Down,
+    /// The diagonals are only used once a game allows them.
+    UpLeft,
+    UpRight,
+    DownLeft,
+    DownRight,
+}
+
+impl Direction {
+    pub fn opposite(&self) -> Direction {
+        match self {
+            Direction::Left => Direction::Right,
+            Direction::Up => Direction::Down,
+            Direction::Right => Direction::Left,
+            Direction::Down => Direction::Up,
+            Direction::UpLeft => Direction::DownRight,
+            Direction::UpRight => Direction::DownLeft,
+            Direction::DownLeft => Direction::UpRight,
+            Direction::DownRight => Direction::UpLeft,
+        }
+    }
+
+    /// How far a step this way moves along each axis, with up being negative.
+    fn offset(&self) -> (i8, i8) {
+        match self {
+            Direction::Left => (-1, 0),
+            Direction::Up => (0, -1),
+            Direction::Right => (1, 0),
+            Direction::Down => (0, 1),
+            Direction::UpLeft => (-1, -1),
+            Direction::UpRight => (1, -1),
+            Direction::DownLeft => (-1, 1),
+            Direction::DownRight => (1, 1),
+        }
+    }
+
+    /// The direction that steps this far along each axis, if any does.
+    fn from_offset(offset: (i8, i8)) -> Option<Direction> {
+        [
+            Direction::Left,
+            Direction::Up,
+            Direction::Right,
+            Direction::Down,
+            Direction::UpLeft,
+            Direction::UpRight,
+            Direction::DownLeft,
+            Direction::DownRight,
+        ]
+        .into_iter()
+        .find(|direction| direction.offset() == offset)
+    }
+
+    pub fn is_diagonal(&self) -> bool {
+        matches!(
+            self,
+            Direction::UpLeft | Direction::UpRight | Direction::DownLeft | Direction::DownRight
+        )
+    }
+}
+
+/// The most moves that can be undone in a row, however many undos a game
+/// allows, so the history stays small.
+const MAX_HISTORY: usize = 16;
+
+/// The narrowest or shortest a board can be, which still leaves room for
+/// where the snakes start and for food.
+const MIN_SIDE: u16 = 5;
+
+/// Picks where food and obstacles go. Named outright rather than taken as
+/// `rand`'s `StdRng`, which may change algorithm from one version to the
+/// next, so a seed always plays out the same way.
+type GameRng = ChaCha12Rng;
+
+/// A shrinking board stops closing in once the open area would be narrower
+/// or shorter than this.
+const MIN_OPEN: u16 = 4;
+
+/// How many ticks eating a magnet keeps pulling food in for.
+const MAGNET_TICKS: u32 = 50;
+
+/// How many ticks landing on fast or slow terrain changes the speed for.
+const TERRAIN_TICKS: u64 = 10;
+
+/// How many ticks eating speed food hurries the game along for, and how many
+/// times as long each of them takes as usual.
+const SPEED_TICKS: u64 = 30;
+const SPEED_SCALE: f64 = 0.6;
+
+/// With combos on, how many ticks a snake has to eat again to keep its streak
+/// going, and the most times over a piece of food can score.
+const COMBO_TICKS: u64 = 20;
+const MAX_COMBO: u32 = 5;
+
+/// How many turns a snake holds to take on the ticks to come.
+const QUEUED_TURNS: usize = 2;
+
+/// What a snake ran into.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum GameOverReason {
+    HitSelf,
+    /// Ran into the other player's body.
+    HitSnake,
+    /// Both heads tried to move into the same cell.
+    HeadOn,
+    HitObstacle,
+    /// Ran into part of the board that had closed in.
+    HitWall,
+    /// Shrank away to nothing from eating rotten food.
+    Starved,
+    /// Lasted until the game's time limit.
+    TimeUp,
+}
+
+impl fmt::Display for GameOverReason {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            GameOverReason::HitSelf => "bit itself",
+            GameOverReason::HitSnake => "ran into the other snake",
+            GameOverReason::HeadOn => "crashed head-on",
+            GameOverReason::HitObstacle => "hit an obstacle",
+            GameOverReason::HitWall => "hit the wall",
+            GameOverReason::Starved => "starved",
+            GameOverReason::TimeUp => "ran out of time",
+        })
+    }
+}
+
+/// How a `Game::tick` ended the game.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GameOver {
+    /// The player whose snake crashed; with a head-on crash, the first of
+    /// the two.
+    pub player: usize,
+    pub reason: GameOverReason,
+}
+
+/// Where a game has got to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GameState {
+    Playing,
+    /// A scripted food source has run out and its last food has been eaten.
+    Cleared,
+    Over(GameOver),
+}
+
+impl fmt::Display for GameOver {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "player {}'s snake {}", self.player + 1, self.reason)
+    }
+}
+
+impl std::error::Error for GameOver {}
+
+/// Something that happened during a `Game::tick`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum GameEvent {
+    AteFood {
+        player: usize,
+        kind: FoodKind,
+    },
+    FoodSpawned(Food),
+    /// A snake used up a wall-break charge to clear the obstacle at this cell.
+    ObstacleSmashed(Vector),
+    /// The snake crashed and started again on a spare life.
+    LostLife(GameOverReason),
+    /// Another ring around the edge of the board closed in.
+    BoardShrank,
+}
+
+/// A snake's segments, which always include a head.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "Vec<Vector>", into = "Vec<Vector>")]
+pub struct Body {
+    head: Vector,
+    /// Everything behind the head, tail last.
+    rest: VecDeque<Vector>,
+    /// How many segments are on each cell, so telling whether the body
+    /// covers one doesn't mean going over all of it. More than one only for
+    /// a snake allowed to pass through itself.
+    cells: HashMap<Vector, u32>,
+}
+
+impl Body {
+    fn new(head: Vector) -> Body {
+        Body {
+            cells: HashMap::from([(head.clone(), 1)]),
+            head,
+            rest: VecDeque::new(),
+        }
+    }
+
+    pub fn head(&self) -> &Vector {
+        &self.head
+    }
+
+    /// The last segment, which is the head itself on a snake one long.
+    pub fn tail(&self) -> &Vector {
+        self.rest.back().unwrap_or(&self.head)
+    }
+
+    #[allow(clippy::len_without_is_empty)]
+    pub fn len(&self) -> usize {
+        self.rest.len() + 1
+    }
+
+    /// Head first.
+    pub fn iter(&self) -> impl Iterator<Item = &Vector> {
+        iter::once(&self.head).chain(&self.rest)
+    }
+
+    pub fn contains(&self, at: &Vector) -> bool {
+        self.cells.contains_key(at)
+    }
+
+    fn push_head(&mut self, head: Vector) {
+        *self.cells.entry(head.clone()).or_insert(0) += 1;
+        let neck = mem::replace(&mut self.head, head);
+        self.rest.push_front(neck);
+    }
+
+    /// Drops the tail, unless the head is all there is.
+    fn pop_tail(&mut self) {
+        if let Some(tail) = self.rest.pop_back() {
+            if let Some(count) = self.cells.get_mut(&tail) {
+                *count -= 1;
+                if *count == 0 {
+                    self.cells.remove(&tail);
+                }
+            }
+        }
+    }
+}
+
+impl TryFrom<Vec<Vector>> for Body {
+    type Error = &'static str;
+
+    fn try_from(segments: Vec<Vector>) -> Result<Body, &'static str> {
+        let mut cells = HashMap::new();
+        for segment in &segments {
+            *cells.entry(segment.clone()).or_insert(0) += 1;
+        }
+
+        let mut segments = VecDeque::from(segments);
+        match segments.pop_front() {
+            Some(head) => Ok(Body {
+                head,
+                rest: segments,
+                cells,
+            }),
+            None => Err("a snake needs at least a head"),
+        }
+    }
+}
+
+impl From<Body> for Vec<Vector> {
+    fn from(body: Body) -> Vec<Vector> {
+        iter::once(body.head).chain(body.rest).collect()
+    }
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Snake {
+    body: Body,
+    direction: Direction,
+    /// Turns still to be taken, one a tick, oldest first.
+    queued: VecDeque<Direction>,
+    score: u32,
+    /// Segments still to be added, one per tick, from food already eaten.
+    pending_growth: u32,
+    /// Ticks left before a magnet stops pulling food toward the head.
+    magnet: u32,
+    /// Ticks since the snake last ate, or last lost a segment to hunger.
+    unfed: u64,
+    /// Which way the snake moved on its last tick, to tell when it turns.
+    heading: Direction,
+    /// How many ticks the snake has moved a different way from the tick
+    /// before.
+    turns: u32,
+    /// How many times over the last piece of food scored, one more for each
+    /// piece eaten within `COMBO_TICKS` of the one before.
+    combo: u32,
+    /// Ticks left to eat again before the streak is lost.
+    combo_left: u64,
+}
+
+impl Snake {
+    fn new(head: Vector, direction: Direction) -> Snake {
+        Snake {
+            body: Body::new(head),
+            direction,
+            queued: VecDeque::new(),
+            score: 0,
+            pending_growth: 0,
+            magnet: 0,
+            unfed: 0,
+            heading: direction,
+            turns: 0,
+            combo: 0,
+            combo_left: 0,
+        }
+    }
+
+    pub fn body(&self) -> &Body {
+        &self.body
+    }
+
+    pub fn direction(&self) -> Direction {
+        self.direction
+    }
+
+    pub fn score(&self) -> u32 {
+        self.score
+    }
+
+    /// How many times over the last piece of food scored while there's still
+    /// time to eat again and score one more, zero once that's run out.
+    pub fn combo(&self) -> u32 {
+        if self.combo_left > 0 {
+            self.combo
+        } else {
+            0
+        }
+    }
+
+    /// How many times the snake has moved off in a new direction, counted
+    /// as it moves rather than as turns are pressed.
+    pub fn turns(&self) -> u32 {
+        self.turns
+    }
+
+    /// How many more ticks food is pulled toward this snake, or zero.
+    pub fn magnet(&self) -> u32 {
+        self.magnet
+    }
+
+    /// Returns whether the snake would have shrunk away to nothing, which can
+    /// only happen with a `min_length` of one. Called with the new head
+    /// already on and before the tail moves up. Food that would grow the
+    /// snake doesn't unless `grow` allows it, and it only scores more with a
+    /// streak if `combos` are on.
+    fn eat(&mut self, kind: FoodKind, min_length: u32, grow: bool, combos: bool) -> bool {
+        self.combo = if combos && self.combo_left > 0 {
+            (self.combo + 1).min(MAX_COMBO)
+        } else {
+            1
+        };
+        self.combo_left = if combos { COMBO_TICKS } else { 0 };
+        self.score += kind.points() * self.combo;
+
+        if kind == FoodKind::Magnet {
+            self.magnet = MAGNET_TICKS;
+        }
+
+        // Growth from earlier food that hasn't come through yet is added to
+        // rather than replaced, and shrinking takes it back before any
+        // segments, so the snake always ends up the sum of what it ate.
+        let growth = kind.growth();
+        if growth > 0 {
+            if grow {
+                self.pending_growth += growth as u32;
+            }
+        } else {
+            let shrink = growth.unsigned_abs();
+            let cancelled = shrink.min(self.pending_growth);
+            self.pending_growth -= cancelled;
+
+            // The tail still moves up after this, which takes one more.
+            let floor = min_length.max(1) as usize;
+            for _ in cancelled..shrink {
+                if self.body.len() > floor + 1 {
+                    self.body.pop_tail();
+                } else if floor == 1 {
+                    return true;
+                }
+            }
+        }
+
+        false
+    }
+
+    /// The way the snake will be heading once its queued turns are taken.
+    fn planned(&self) -> Direction {
+        self.queued.back().copied().unwrap_or(self.heading)
+    }
+
+    /// Whether the snake may turn `direction` after its queued turns, which
+    /// it can't straight back the way it would then be going unless `reverse`
+    /// allows it. A lone head has nothing to reverse into.
+    fn may_turn(&self, direction: Direction, reverse: bool) -> bool {
+        reverse || self.body.len() == 1 || direction != self.planned().opposite()
+    }
+}
+
+/// Everything `Game::undo` needs to put back the state before a tick.
+#[derive(Clone)]
+struct Checkpoint {
+    snakes: Vec<Snake>,
+    food: Vec<Food>,
+    obstacles: HashSet<Vector>,
+    wall_break_charges: u16,
+    spare_lives: u32,
+    closed_rings: u16,
+    next_food_at: u64,
+    scripted: usize,
+    grace_until: u64,
+    terrain_speed: Option<TerrainKind>,
+    terrain_speed_until: u64,
+    sped_until: u64,
+    ticks: u64,
+    rng: GameRng,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Game {
+    snakes: Vec<Snake>,
+    dimensions: Dimensions,
+    food: Vec<Food>,
+    /// How much food the board is kept stocked with.
+    food_count: u32,
+    food_table: FoodTable,
+    food_source: FoodSource,
+    /// How many of a scripted source's cells have had food so far.
+    scripted: usize,
+    obstacles: HashSet<Vector>,
+    /// Obstacles laid out by a level, which scattered ones are added to.
+    layout: HashSet<Vector>,
+    /// Fast and slow floor, which never shares a cell with obstacles or food.
+    #[serde(with = "crate::terrain")]
+    terrain: HashMap<Vector, TerrainKind>,
+    /// The terrain a head last landed on, while its effect lasts.
+    terrain_speed: Option<TerrainKind>,
+    /// The tick that effect wears off on.
+    terrain_speed_until: u64,
+    /// The tick the hurry from the last speed food eaten wears off on.
+    sped_until: u64,
+    /// How the speed picks up as player one grows, if it does.
+    difficulty: Option<Difficulty>,
+    /// Obstacles the snakes can still smash through instead of crashing.
+    wall_break_charges: u16,
+    obstacle_count: u16,
+    regenerate_obstacles: bool,
+    spare_lives: u32,
+    /// Ticks between each ring of the board closing in, or zero for a board
+    /// that never shrinks.
+    shrink_every: u64,
+    /// How many rings around the edge have closed so far.
+    closed_rings: u16,
+    /// Ticks the board goes without food after some is eaten.
+    food_delay: u64,
+    /// The tick from which food can spawn again.
+    next_food_at: u64,
+    /// Ticks a snake can go without eating before it loses a segment, or
+    /// zero for snakes that never get hungry.
+    hunger_every: u64,
+    /// The tick the game ends on whatever happens, or zero for no limit.
+    time_limit: u64,
+    /// Ticks at the start and after each lost life during which running
+    /// into things doesn't count.
+    grace: u64,
+    /// The tick the current grace period ends on.
+    grace_until: u64,
+    ticks: u64,
+    /// Whether the snakes may turn diagonally.
+    diagonal: bool,
+    /// The shortest rotten food can shrink a snake to. Zero lets it starve.
+    min_length: u32,
+    /// Whether a snake may turn straight back on itself.
+    allow_reverse: bool,
+    /// Whether food only scores, leaving the snakes the length they are.
+    no_growth: bool,
+    /// Whether food eaten in quick succession scores more each time.
+    combos: bool,
+    /// Whether a snake can run through its own body, for setting up a board
+    /// by hand.
+    no_self_collision: bool,
+    /// Whether food stays off the cells just ahead of and behind each head.
+    food_clear_of_heads: bool,
+    /// Whether going off the left or right edge comes back in at the other,
+    /// rather than crashing.
+    wrap_x: bool,
+    /// The same for the top and bottom edges.
+    wrap_y: bool,
+    /// Points lost each time a snake goes through an edge that wraps.
+    wrap_penalty: u32,
+    /// Whether snakes bounce off edges that don't wrap instead of crashing.
+    bounce: bool,
+    /// How the game ended, once it has.
+    over: Option<GameOver>,
+    #[serde(skip, default = "GameRng::from_entropy")]
+    rng: GameRng,
+    #[serde(skip)]
+    replay: Replay,
+    #[serde(skip)]
+    history: VecDeque<Checkpoint>,
+    undos_left: u32,
+}
+
+/// Where and facing which way `player`'s snake starts.
+fn start(player: usize, dimensions: &Dimensions) -> (Vector, Direction) {
+    if player == 0 {
+        (
+            Vector::new(dimensions.x.0 + 4, dimensions.y.0 + 4),
+            Direction::Right,
+        )
+    } else {
+        (
+            Vector::new(dimensions.x.1 - 4, dimensions.y.1 - 4),
+            Direction::Left,
+        )
+    }
+}
+
+impl Game {
+    pub fn new(players: usize) -> Game {
+        Game::with_seed(players, rand::random())
+    }
+
+    /// A game whose food appears in the same places every time for a given
+    /// `seed`.
+    pub fn with_seed(players: usize, seed: u64) -> Game {
+        Game::with_dimensions(players, seed, Dimensions::default())
+            .expect("the default board is big enough")
+    }
+
+    /// A seeded game on a board of any size, which fails if the board is too
+    /// small to play on.
+    pub fn with_dimensions(players: usize, seed: u64, dimensions: Dimensions) -> Result<Game> {
+        dimensions.validate()?;
+
+        let snakes = (0..players.clamp(1, 2))
+            .map(|player| {
+                let (head, direction) = start(player, &dimensions);
+                Snake::new(head, direction)
+            })
+            .collect();
+
+        Ok(Game {
+            snakes,
+            replay: Replay::new(seed, dimensions.clone()),
+            dimensions,
+            food: Vec::new(),
+            food_count: 1,
+            food_table: FoodTable::default(),
+            food_source: FoodSource::Random,
+            scripted: 0,
+            obstacles: HashSet::new(),
+            wall_break_charges: 0,
+            layout: HashSet::new(),
+            terrain: HashMap::new(),
+            terrain_speed: None,
+            terrain_speed_until: 0,
+            sped_until: 0,
+            difficulty: None,
+            obstacle_count: 0,
+            regenerate_obstacles: false,
+            spare_lives: 0,
+            shrink_every: 0,
+            closed_rings: 0,
+            food_delay: 0,
+            next_food_at: 0,
+            hunger_every: 0,
+            time_limit: 0,
+            grace: 0,
+            grace_until: 0,
+            ticks: 0,
+            diagonal: false,
+            min_length: 1,
+            allow_reverse: false,
+            no_growth: false,
+            combos: false,
+            no_self_collision: false,
+            food_clear_of_heads: false,
+            wrap_x: true,
+            wrap_y: true,
+            wrap_penalty: 0,
+            bounce: false,
+            over: None,
+            rng: GameRng::seed_from_u64(seed),
+            history: VecDeque::new(),
+            undos_left: 0,
+        })
+    }
+
+    pub fn snakes(&self) -> &[Snake] {
+        &self.snakes
+    }
+
+    pub fn food(&self) -> &[Food] {
+        &self.food
+    }
+
+    /// Keeps `count` pieces of food on the board at once rather than one.
+    pub fn set_food_count(&mut self, count: u32) {
+        self.replay.set_food_count(count);
+        self.food_count = count.max(1);
+    }
+
+    /// Replaces the odds of each kind of food spawning, from the next spawn
+    /// on.
+    pub fn set_food_table(&mut self, table: FoodTable) {
+        self.replay.set_food_table(table.clone());
+        self.food_table = table;
+    }
+
+    /// Switches where food appears, from the next spawn on. Scripted cells
+    /// have to be on the board and clear of a layout's obstacles and terrain,
+    /// so set those first; scattered obstacles are moved out of their way.
+    pub fn set_food_source(&mut self, source: FoodSource) -> Result<()> {
+        if let FoodSource::Scripted(cells) = &source {
+            for cell in cells {
+                if !self.on_board(cell) {
+                    return Err(anyhow!("food at ({}, {}) is off the board", cell.0, cell.1));
+                }
+                if self.layout.contains(cell) {
+                    return Err(anyhow!(
+                        "food at ({}, {}) is on an obstacle",
+                        cell.0,
+                        cell.1
+                    ));
+                }
+                if self.terrain.contains_key(cell) {
+                    return Err(anyhow!("food at ({}, {}) is on terrain", cell.0, cell.1));
+                }
+            }
+        }
+
+        self.replay.set_food_source(source.clone());
+        self.food_source = source;
+        self.scripted = 0;
+        // Obstacles scattered before the food was known may be in its way.
+        let scripted = self.scripted_cells();
+        if self.obstacles.iter().any(|cell| scripted.contains(cell)) {
+            self.scatter_obstacles();
+        }
+        Ok(())
+    }
+
+    /// Every cell a scripted food source puts food on, which scattered
+    /// obstacles keep clear of.
+    fn scripted_cells(&self) -> HashSet<Vector> {
+        match &self.food_source {
+            FoodSource::Random => HashSet::new(),
+            FoodSource::Scripted(cells) => cells.iter().cloned().collect(),
+        }
+    }
+
+    /// Whether a scripted food source has run out and its last food has been
+    /// eaten.
+    pub fn is_cleared(&self) -> bool {
+        match &self.food_source {
+            FoodSource::Random => false,
+            FoodSource::Scripted(cells) => self.scripted == cells.len() && self.food.is_empty(),
+        }
+    }
+
+    /// Whether the game is still going, cleared or over, and how it ended
+    /// if it is.
+    pub fn state(&self) -> GameState {
+        match self.over {
+            Some(over) => GameState::Over(over),
+            None if self.is_cleared() => GameState::Cleared,
+            None => GameState::Playing,
+        }
+    }
+
+    pub fn obstacles(&self) -> &HashSet<Vector> {
+        &self.obstacles
+    }
+
+    /// A copy of the game with the board topped up with food, which it
+    /// otherwise only gets at the end of the first tick, for showing a board
+    /// that hasn't been played yet.
+    pub fn with_food(&self) -> Game {
+        let mut game = self.clone();
+        game.spawn_food();
+        game
+    }
+
+    /// What's in each cell right now, for anything that would otherwise
+    /// piece it together from the snakes, food and obstacles. A head covers
+    /// a body, which covers food.
+    pub fn grid_snapshot(&self) -> Grid {
+        let mut grid = Grid::new(&self.dimensions);
+        let (x, y) = (self.dimensions.x, self.dimensions.y);
+
+        for row in y.0..=y.1 {
+            for column in x.0..=x.1 {
+                let at = Vector::new(column, row);
+                if self.is_closed(&at) {
+                    grid.set(&at, CellKind::Wall);
+                }
+            }
+        }
+        for (at, kind) in &self.terrain {
+            grid.set(at, CellKind::Terrain(*kind));
+        }
+        for at in &self.obstacles {
+            grid.set(at, CellKind::Obstacle);
+        }
+        for food in &self.food {
+            grid.set(&food.at, CellKind::Food(food.kind));
+        }
+        for (player, snake) in self.snakes.iter().enumerate() {
+            for at in snake.body.iter().skip(1) {
+                grid.set(at, CellKind::Body(player));
+            }
+        }
+        for (player, snake) in self.snakes.iter().enumerate() {
+            grid.set(snake.body.head(), CellKind::Head(player));
+        }
+
+        grid
+    }
+
+    /// Every cell with something in it and what that is, a row at a time
+    /// from the top left, matching `grid_snapshot` without the empty cells.
+    /// Cheaper than a whole grid when most of the board is open.
+    pub fn cells(&self) -> impl Iterator<Item = (Vector, CellKind)> {
+        let mut cells = HashMap::new();
+
+        if self.closed_rings > 0 {
+            let (x, y) = (self.dimensions.x, self.dimensions.y);
+            for row in y.0..=y.1 {
+                for column in x.0..=x.1 {
+                    let at = Vector::new(column, row);
+                    if self.is_closed(&at) {
+                        cells.insert(at, CellKind::Wall);
+                    }
+                }
+            }
+        }
+        for (at, kind) in &self.terrain {
+            cells.insert(at.clone(), CellKind::Terrain(*kind));
+        }
+        for at in &self.obstacles {
+            cells.insert(at.clone(), CellKind::Obstacle);
+        }
+        for food in &self.food {
+            cells.insert(food.at.clone(), CellKind::Food(food.kind));
+        }
+        for (player, snake) in self.snakes.iter().enumerate() {
+            for at in snake.body.iter().skip(1) {
+                cells.insert(at.clone(), CellKind::Body(player));
+            }
+        }
+        for (player, snake) in self.snakes.iter().enumerate() {
+            cells.insert(snake.body.head().clone(), CellKind::Head(player));
+        }
+
+        let mut cells: Vec<_> = cells.into_iter().collect();
+        cells.sort_by_key(|(at, _)| (at.1, at.0));
+        cells.into_iter()
+    }
+
+    /// Scatters `count` obstacles over the board, keeping clear of the rows
+    /// the snakes start out moving along and never walling off part of the
+    /// board. Call it before the first tick.
+    pub fn place_obstacles(&mut self, count: u16) {
+        self.replay.set_obstacles(count);
+        self.obstacle_count = count;
+        self.scatter_obstacles();
+    }
+
+    /// Puts obstacles on exactly these cells, with any scattered ones on top,
+    /// for a level drawn by hand. None may be where a snake starts. Call it
+    /// before the first tick.
+    pub fn set_layout(&mut self, layout: HashSet<Vector>) -> Result<()> {
+        for player in 0..self.snakes.len() {
+            let (head, _) = start(player, &self.dimensions);
+            if layout.contains(&head) {
+                return Err(anyhow!(
+                    "the level has an obstacle at ({}, {}), where a snake starts",
+                    head.0,
+                    head.1
+                ));
+            }
+        }
+        if let Some(cell) = layout.iter().find(|cell| !self.on_board(cell)) {
+            return Err(anyhow!(
+                "the level has an obstacle off the board at ({}, {})",
+                cell.0,
+                cell.1
+            ));
+        }
+
+        self.replay.set_layout(layout.iter().cloned().collect());
+        self.layout = layout;
+        self.scatter_obstacles();
+        Ok(())
+    }
+
+    /// Lays fast and slow floor over these cells. None may hold an obstacle
+    /// or food, or be off the board, so place a level's layout first. Call it
+    /// before the first tick.
+    pub fn set_terrain(&mut self, terrain: HashMap<Vector, TerrainKind>) -> Result<()> {
+        for cell in terrain.keys() {
+            if !self.on_board(cell) {
+                return Err(anyhow!(
+                    "the level has terrain off the board at ({}, {})",
+                    cell.0,
+                    cell.1
+                ));
+            }
+            if self.obstacles.contains(cell) || self.food.iter().any(|food| food.at == *cell) {
+                return Err(anyhow!(
+                    "the level has terrain at ({}, {}), which is already taken",
+                    cell.0,
+                    cell.1
+                ));
+            }
+        }
+
+        self.replay.set_terrain(
+            terrain
+                .iter()
+                .map(|(at, kind)| (at.clone(), *kind))
+                .collect(),
+        );
+        self.terrain = terrain;
+        Ok(())
+    }
+
+    pub fn terrain(&self) -> &HashMap<Vector, TerrainKind> {
+        &self.terrain
+    }
+
+    /// How many times as long the current tick should take as usual, going by
+    /// the terrain a head last landed on, any speed food eaten lately and how
+    /// long player one has grown under the difficulty. A front-end scales its
+    /// tick interval by this.
+    pub fn interval_scale(&self) -> f64 {
+        let terrain = match self.terrain_speed {
+            Some(kind) if self.ticks < self.terrain_speed_until => kind.interval_scale(),
+            _ => 1.0,
+        };
+        let food = if self.ticks < self.sped_until {
+            SPEED_SCALE
+        } else {
+            1.0
+        };
+        let difficulty = self.difficulty.map_or(1.0, |difficulty| {
+            difficulty.interval_scale(self.snakes[0].body.len())
+        });
+        terrain * food * difficulty
+    }
+
+    /// Speeds the game up as player one grows, by `difficulty`'s curve, or
+    /// keeps the speed steady with `None`.
+    pub fn set_difficulty(&mut self, difficulty: Option<Difficulty>) {
+        self.replay.set_difficulty(difficulty);
+        self.difficulty = difficulty;
+    }
+
+    pub fn difficulty(&self) -> Option<Difficulty> {
+        self.difficulty
+    }
+
+    fn scatter_obstacles(&mut self) {
+        /// Layouts tried before settling for one that isn't fully connected.
+        const ATTEMPTS: usize = 100;
+
+        let rows: Vec<u16> = (0..self.snakes.len())
+            .map(|player| start(player, &self.dimensions).0 .1)
+            .collect();
+        // Scattered obstacles stay off terrain and wherever scripted food
+        // will go.
+        let mut clear = self.scripted_cells();
+        clear.extend(self.terrain.keys().cloned());
+        let free = (self.dimensions.x.1 - self.dimensions.x.0 + 1) as usize
+            * (self.dimensions.y.1 - self.dimensions.y.0 + 1 - rows.len() as u16) as usize
+            - self
+                .layout
+                .iter()
+                .chain(&clear)
+                .filter(|cell| !rows.contains(&cell.1))
+                .count();
+        let count = self.layout.len() + (self.obstacle_count as usize).min(free);
+
+        for _ in 0..ATTEMPTS {
+            self.obstacles = self.layout.clone();
+
+            while self.obstacles.len() < count {
+                let at = Vector::new(
+                    self.rng
+                        .gen_range(self.dimensions.x.0..=self.dimensions.x.1),
+                    self.rng
+                        .gen_range(self.dimensions.y.0..=self.dimensions.y.1),
+                );
+                if !rows.contains(&at.1) && !clear.contains(&at) {
+                    self.obstacles.insert(at);
+                }
+            }
+
+            if self.is_connected() {
+                return;
+            }
+        }
+    }
+
+    /// Whether every open cell can be reached from every other.
+    fn is_connected(&self) -> bool {
+        let (x, y) = (self.dimensions.x, self.dimensions.y);
+        let open: Vec<Vector> = (y.0..=y.1)
+            .flat_map(|row| (x.0..=x.1).map(move |column| Vector::new(column, row)))
+            .filter(|cell| !self.is_blocked(cell))
+            .collect();
+
+        match open.first() {
+            Some(first) => self.reachable_from(first.clone()).len() == open.len(),
+            None => true,
+        }
+    }
+
+    /// Every cell a snake starting at `from` could get to, going around
+    /// obstacles and the closed-in edge. Snakes are ignored since they move
+    /// out of the way.
+    fn reachable_from(&self, from: Vector) -> HashSet<Vector> {
+        self.flood(from, |cell| self.is_blocked(cell))
+    }
+
+    /// Every open cell player one's head can get to as the board stands, with
+    /// every snake in the way, the head's own cell included. Checking this
+    /// against the snake's length tells whether it's boxing itself in.
+    pub fn reachable_set(&self) -> HashSet<Vector> {
+        self.flood(self.snakes[0].body.head().clone(), |cell| {
+            self.is_blocked(cell) || self.snake_at(cell).is_some()
+        })
+    }
+
+    /// How many cells `reachable_set` holds.
+    pub fn reachable_cells(&self) -> usize {
+        self.reachable_set().len()
+    }
+
+    /// A breadth-first search out from `from` through every cell `blocked`
+    /// lets past.
+    fn flood(&self, from: Vector, blocked: impl Fn(&Vector) -> bool) -> HashSet<Vector> {
+        let mut seen = HashSet::from([from.clone()]);
+        let mut queue = VecDeque::from([from]);
+
+        while let Some(cell) = queue.pop_front() {
+            for neighbour in self.neighbours(&cell) {
+                if !blocked(&neighbour) && seen.insert(neighbour.clone()) {
+                    queue.push_back(neighbour);
+                }
+            }
+        }
+
+        seen
+    }
+
+    /// Whether player one's snake covers every cell it could get to, leaving
+    /// nowhere for more food.
+    pub fn is_full(&self) -> bool {
+        let snake = &self.snakes[0];
+        self.reachable_from(snake.body.head().clone())
+            .iter()
+            .all(|cell| snake.body.contains(cell))
+    }
+
+    pub(crate) fn is_blocked(&self, vec: &Vector) -> bool {
+        self.obstacles.contains(vec) || self.is_closed(vec)
+    }
+
+    /// The cells next to `vec`, wrapping around the edges that wrap.
+    fn neighbours(&self, vec: &Vector) -> Vec<Vector> {
+        [
+            Direction::Left,
+            Direction::Up,
+            Direction::Right,
+            Direction::Down,
+        ]
+        .into_iter()
+        .filter_map(|direction| self.moved(vec, direction))
+        .collect()
+    }
+
+    /// Gives a single-player game `lives` goes in total: crashing with any
+    /// to spare starts the snake again from the beginning, keeping its score.
+    pub fn set_lives(&mut self, lives: u32) {
+        self.replay.set_lives(lives);
+        self.spare_lives = lives.saturating_sub(1);
+    }
+
+    /// Lives left after the current one.
+    pub fn spare_lives(&self) -> u32 {
+        self.spare_lives
+    }
+
+    /// Whether each new life gets a freshly scattered set of obstacles rather
+    /// than the same ones.
+    pub fn regenerate_obstacles_on_respawn(&mut self, regenerate: bool) {
+        self.replay.set_regenerate_obstacles(regenerate);
+        self.regenerate_obstacles = regenerate;
+    }
+
+    /// Starts player one off with `snake`'s score, growing out to its length
+    /// over the first few ticks, as if carried over from an earlier game.
+    pub fn carry_over(&mut self, snake: &Snake) {
+        let player = &mut self.snakes[0];
+        player.score = snake.score;
+        player.pending_growth = snake.body.len() as u32 - 1 + snake.pending_growth;
+    }
+
+    /// Stops rotten food shrinking a snake shorter than `length`, zero counting
+    /// as one. With one, a snake that would shrink away to nothing starves
+    /// and the game ends instead.
+    pub fn set_min_length(&mut self, length: u32) {
+        self.replay.set_min_length(length);
+        self.min_length = length;
+    }
+
+    /// Takes a segment off a snake's tail every `ticks` ticks it goes without
+    /// eating, with zero turning hunger off. A snake already down to the
+    /// minimum length starves instead.
+    pub fn set_hunger_interval(&mut self, ticks: u64) {
+        self.replay.set_hunger_every(ticks);
+        self.hunger_every = ticks;
+    }
+
+    /// How many ticks until player one's snake next loses a segment to
+    /// hunger, if that's on.
+    pub fn ticks_until_hungry(&self) -> Option<u64> {
+        (self.hunger_every > 0).then(|| self.hunger_every - self.snakes[0].unfed)
+    }
+
+    /// How many ticks without food cost a segment, zero if hunger is off.
+    pub fn hunger_interval(&self) -> u64 {
+        self.hunger_every
+    }
+
+    /// Ends the game once it's gone `ticks` ticks, spare lives or not, with
+    /// zero for no limit.
+    pub fn set_time_limit(&mut self, ticks: u64) {
+        self.replay.set_time_limit(ticks);
+        self.time_limit = ticks;
+    }
+
+    /// How many more ticks the game has before it runs out of time, if it
+    /// has a limit.
+    pub fn ticks_left(&self) -> Option<u64> {
+        (self.time_limit > 0).then(|| self.time_limit.saturating_sub(self.ticks))
+    }
+
+    /// Lets the snakes pass through each other, themselves and obstacles for
+    /// `ticks` ticks from now and after every lost life. Walls still count.
+    pub fn set_grace_period(&mut self, ticks: u64) {
+        self.replay.set_grace(ticks);
+        self.grace = ticks;
+        self.grace_until = self.ticks + ticks;
+    }
+
+    /// Whether a grace period is running.
+    pub fn in_grace(&self) -> bool {
+        self.ticks < self.grace_until
+    }
+
+    /// Leaves the board empty for `ticks` ticks after food is eaten before the
+    /// next appears.
+    pub fn set_food_delay(&mut self, ticks: u64) {
+        self.replay.set_food_delay(ticks);
+        self.food_delay = ticks;
+    }
+
+    /// Closes in another ring around the edge of the board every `ticks`
+    /// ticks, until `MIN_OPEN` is reached. Zero turns shrinking off.
+    pub fn shrink_every(&mut self, ticks: u64) {
+        self.replay.set_shrink_every(ticks);
+        self.shrink_every = ticks;
+    }
+
+    /// How many ticks apart rings close in, zero if the board never shrinks.
+    pub fn shrink_interval(&self) -> u64 {
+        self.shrink_every
+    }
+
+    /// How many rings around the edge of the board have closed in.
+    pub fn closed_rings(&self) -> u16 {
+        self.closed_rings
+    }
+
+    /// How many more ticks until the next ring closes in, if one ever will.
+    pub fn ticks_until_shrink(&self) -> Option<u64> {
+        if self.shrink_every == 0 || !self.can_shrink() {
+            return None;
+        }
+
+        Some(self.shrink_every - self.ticks % self.shrink_every)
+    }
+
+    fn can_shrink(&self) -> bool {
+        let (width, height) = (
+            self.dimensions.x.1 - self.dimensions.x.0 + 1,
+            self.dimensions.y.1 - self.dimensions.y.0 + 1,
+        );
+        let next = 2 * (self.closed_rings + 1);
+
+        width.saturating_sub(next) >= MIN_OPEN && height.saturating_sub(next) >= MIN_OPEN
+    }
+
+    /// Which ring `vec` lies on, counting the outermost as zero.
+    pub fn ring(&self, vec: &Vector) -> u16 {
+        [
+            vec.0 - self.dimensions.x.0,
+            self.dimensions.x.1 - vec.0,
+            vec.1 - self.dimensions.y.0,
+            self.dimensions.y.1 - vec.1,
+        ]
+        .into_iter()
+        .min()
+        .unwrap_or(0)
+    }
+
+    /// Whether `vec` is part of the board that has closed in.
+    pub fn is_closed(&self, vec: &Vector) -> bool {
+        self.ring(vec) < self.closed_rings
+    }
+
+    fn shrink(&mut self) {
+        self.closed_rings += 1;
+        // Food shut in by the new wall, or cut off by it, is replaced on this
+        // tick.
+        let head = self.snakes[0].body.head().clone();
+        let reachable = self.reachable_from(head);
+        self.food.retain(|food| reachable.contains(&food.at));
+    }
+
+    fn respawn(&mut self) {
+        self.spare_lives -= 1;
+
+        let (head, direction) = start(0, &self.dimensions);
+        let score = self.snakes[0].score;
+        self.snakes[0] = Snake::new(head.clone(), direction);
+        self.snakes[0].score = score;
+        self.grace_until = self.ticks + self.grace;
+
+        if self.regenerate_obstacles {
+            self.scatter_obstacles();
+            // Food buried or walled off by the new layout is replaced on this
+            // tick.
+            let reachable = self.reachable_from(head);
+            self.food.retain(|food| reachable.contains(&food.at));
+        }
+    }
+
+    pub fn wall_break_charges(&self) -> u16 {
+        self.wall_break_charges
+    }
+
+    pub fn dimensions(&self) -> &Dimensions {
+        &self.dimensions
+    }
+
+    /// How many ticks the game has survived. This is the game's only clock:
+    /// magnets, food delays and the shrinking board all count ticks rather
+    /// than wall-clock time, so they stand still whenever nothing calls
+    /// `tick`, such as while the game is paused.
+    pub fn ticks(&self) -> u64 {
+        self.ticks
+    }
+
+    /// Player one's run so far.
+    pub fn replay(&self) -> &Replay {
+        &self.replay
+    }
+
+    /// Lets the player take back up to `undos` moves over the whole game.
+    pub fn allow_undos(&mut self, undos: u32) {
+        self.undos_left = undos;
+    }
+
+    pub fn undos_left(&self) -> u32 {
+        self.undos_left
+    }
+
+    /// Puts the game back to how it was before the last tick, including one
+    /// that ended it. Returns `false` if there's nothing to undo or no undos
+    /// left.
+    pub fn undo(&mut self) -> bool {
+        if self.undos_left == 0 {
+            return false;
+        }
+
+        match self.history.pop_back() {
+            Some(checkpoint) => {
+                self.snakes = checkpoint.snakes;
+                self.food = checkpoint.food;
+                self.obstacles = checkpoint.obstacles;
+                self.wall_break_charges = checkpoint.wall_break_charges;
+                self.spare_lives = checkpoint.spare_lives;
+                self.closed_rings = checkpoint.closed_rings;
+                self.next_food_at = checkpoint.next_food_at;
+                self.scripted = checkpoint.scripted;
+                self.grace_until = checkpoint.grace_until;
+                self.terrain_speed = checkpoint.terrain_speed;
+                self.terrain_speed_until = checkpoint.terrain_speed_until;
+                self.sped_until = checkpoint.sped_until;
+                self.ticks = checkpoint.ticks;
+                self.rng = checkpoint.rng;
+                self.over = None;
+                self.replay.rewind(self.ticks);
+                self.undos_left -= 1;
+                debug!("undid back to tick {}", self.ticks);
+                true
+            }
+            None => false,
+        }
+    }
+
+    fn checkpoint(&mut self) {
+        let capacity = (self.undos_left as usize).min(MAX_HISTORY);
+        if capacity == 0 {
+            return;
+        }
+
+        while self.history.len() >= capacity {
+            self.history.pop_front();
+        }
+
+        self.history.push_back(Checkpoint {
+            snakes: self.snakes.clone(),
+            food: self.food.clone(),
+            obstacles: self.obstacles.clone(),
+            wall_break_charges: self.wall_break_charges,
+            spare_lives: self.spare_lives,
+            closed_rings: self.closed_rings,
+            next_food_at: self.next_food_at,
+            scripted: self.scripted,
+            grace_until: self.grace_until,
+            terrain_speed: self.terrain_speed,
+            terrain_speed_until: self.terrain_speed_until,
+            sped_until: self.sped_until,
+            ticks: self.ticks,
+            rng: self.rng.clone(),
+        });
+    }
+
+    /// Lets the snakes move diagonally as well as straight, stepping along
+    /// both axes at once.
+    pub fn allow_diagonals(&mut self, diagonal: bool) {
+        self.replay.set_diagonal(diagonal);
+        self.diagonal = diagonal;
+    }
+
+    pub fn diagonal(&self) -> bool {
+        self.diagonal
+    }
+
+    /// Chooses which pairs of edges the snakes pass through to the other side;
+    /// the rest are walls. Both wrap by default.
+    pub fn set_wrap(&mut self, x: bool, y: bool) {
+        self.replay.set_wrap(x, y);
+        self.wrap_x = x;
+        self.wrap_y = y;
+    }
+
+    /// Turns snakes back off the edges that don't wrap, reversing whichever
+    /// way they were heading into the edge, rather than letting them crash.
+    /// A snake turned back into its own body still bites itself.
+    pub fn set_bounce(&mut self, bounce: bool) {
+        self.replay.set_bounce(bounce);
+        self.bounce = bounce;
+    }
+
+    pub fn bounces(&self) -> bool {
+        self.bounce
+    }
+
+    /// Costs a snake `points` each time it goes through an edge that wraps,
+    /// though never taking its score below zero, so hugging the edges isn't
+    /// free.
+    pub fn set_wrap_penalty(&mut self, points: u32) {
+        self.replay.set_wrap_penalty(points);
+        self.wrap_penalty = points;
+    }
+
+    /// Whether the left and right edges wrap, and the top and bottom.
+    pub fn wrap(&self) -> (bool, bool) {
+        (self.wrap_x, self.wrap_y)
+    }
+
+    /// Keeps new food off the cell straight ahead of each head, which would
+    /// be a free point, and the one straight behind, which can't be reached
+    /// without turning around.
+    pub fn keep_food_clear_of_heads(&mut self, clear: bool) {
+        self.replay.set_food_clear_of_heads(clear);
+        self.food_clear_of_heads = clear;
+    }
+
+    /// Stops food growing the snakes, for surviving as long as possible at a
+    /// fixed length. It still scores, and rotten food still shrinks them.
+    pub fn disable_growth(&mut self, disabled: bool) {
+        self.replay.set_no_growth(disabled);
+        self.no_growth = disabled;
+    }
+
+    pub fn growth_disabled(&self) -> bool {
+        self.no_growth
+    }
+
+    /// Makes each piece of food a snake eats within `COMBO_TICKS` of the last
+    /// score once more over than that one did, up to `MAX_COMBO` times, so
+    /// eating quickly pays.
+    pub fn enable_combos(&mut self, enabled: bool) {
+        self.replay.set_combos(enabled);
+        self.combos = enabled;
+    }
+
+    /// Lets each snake pass through its own body, a debug aid for steering
+    /// into a particular shape. Walls, obstacles, other snakes and scoring
+    /// work as usual.
+    pub fn disable_self_collision(&mut self, disabled: bool) {
+        self.replay.set_no_self_collision(disabled);
+        self.no_self_collision = disabled;
+    }
+
+    pub fn self_collision_disabled(&self) -> bool {
+        self.no_self_collision
+    }
+
+    /// Lets a snake turn straight back the way it came instead of ignoring
+    /// the turn. Anything longer than just its head bites itself doing so.
+    pub fn allow_reverse(&mut self, reverse: bool) {
+        self.replay.set_allow_reverse(reverse);
+        self.allow_reverse = reverse;
+    }
+
+    /// Queues a turn for `player`, taken as the snake moves on a later
+    /// `tick` without moving it. Up to `QUEUED_TURNS` are held, one taken each
+    /// tick, so two quick turns within a tick are made on the next two rather
+    /// than the first being lost; any more are refused. Each turn is checked
+    /// against the way the snake will be going after the one queued before
+    /// it, so no run of quick turns brings it back on itself, and diagonal
+    /// turns are refused unless the game allows them. Returns whether the
+    /// turn was taken.
+    pub fn apply_direction(&mut self, player: usize, direction: Direction) -> bool {
+        if direction.is_diagonal() && !self.diagonal {
+            return false;
+        }
+
+        let reverse = self.allow_reverse;
+        match self.snakes.get_mut(player) {
+            Some(snake) if snake.may_turn(direction, reverse) => {
+                // Going the way it will be already takes up no turn.
+                if direction == snake.planned() {
+                    return true;
+                }
+                if snake.queued.len() == QUEUED_TURNS {
+                    return false;
+                }
+                snake.queued.push_back(direction);
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Like `apply_direction`, but returns whether the snake will end up
+    /// heading a different way than it would have.
+    pub fn change_direction(&mut self, player: usize, direction: Direction) -> bool {
+        let Some(before) = self.snakes.get(player).map(Snake::planned) else {
+            return false;
+        };
+        self.apply_direction(player, direction) && direction != before
+    }
+
+    /// Advances the game by one step: takes queued turns, moves every snake,
+    /// pulls food toward a snake holding a magnet and spawns food if the board
+    /// has none. This never sleeps or touches
+    /// I/O, so the caller decides how long a tick lasts. An `Err` means the
+    /// game is over, and every tick after it gives the same `Err` until an
+    /// undo.
+    pub fn tick(&mut self) -> Result<Vec<GameEvent>, GameOver> {
+        trace!("tick {}", self.ticks);
+        if let Some(over) = self.over {
+            return Err(over);
+        }
+        if self.ticks_left() == Some(0) {
+            let over = GameOver {
+                player: 0,
+                reason: GameOverReason::TimeUp,
+            };
+            self.over = Some(over);
+            return Err(over);
+        }
+        self.checkpoint();
+
+        let mut events = match self.go_forward() {
+            Ok(events) => events,
+            Err(over) if self.spare_lives > 0 && self.snakes.len() == 1 => {
+                self.respawn();
+                vec![GameEvent::LostLife(over.reason)]
+            }
+            Err(over) => {
+                debug!("tick {}: game over, {}", self.ticks, over);
+                self.over = Some(over);
+                return Err(over);
+            }
+        };
+        self.pull_food();
+        self.ticks += 1;
+        self.replay.record_tick();
+
+        if self.ticks_until_shrink() == Some(self.shrink_every) {
+            self.shrink();
+            events.push(GameEvent::BoardShrank);
+        }
+
+        events.extend(self.spawn_food().into_iter().map(GameEvent::FoodSpawned));
+
+        for event in &events {
+            debug!("tick {}: {:?}", self.ticks, event);
+        }
+
+        Ok(events)
+    }
+
+    /// Whether `player`'s snake, moving its head to `new_head`, has turned
+    /// straight back the way it came with a body behind its head.
+    fn reversed(&self, player: usize, new_head: &Vector) -> bool {
+        self.snakes[player].body.iter().nth(1) == Some(new_head)
+    }
+
+    /// Like `tick`, but leaves this game as it is and returns the next one,
+    /// with player one turned to `input` first if given. Randomness comes from
+    /// the game's own seed, so stepping the same game twice gives the same
+    /// result.
+    pub fn step(&self, input: Option<Direction>) -> Result<(Game, Vec<GameEvent>), GameOver> {
+        let mut next = self.clone();
+        if let Some(direction) = input {
+            next.change_direction(0, direction);
+        }
+        let events = next.tick()?;
+        Ok((next, events))
+    }
+
+    fn go_forward(&mut self) -> Result<Vec<GameEvent>, GameOver> {
+        // Queued turns were checked as they came in, so all that's left is to
+        // take the oldest.
+        for (player, snake) in self.snakes.iter_mut().enumerate() {
+            match snake.queued.pop_front() {
+                Some(direction) if direction != snake.direction => {
+                    snake.direction = direction;
+                    debug!(
+                        "tick {}: player {} turned {:?}",
+                        self.ticks,
+                        player + 1,
+                        direction
+                    );
+                    if player == 0 {
+                        self.replay.record_turn(self.ticks, direction);
+                    }
+                }
+                _ => (),
+            }
+        }
+
+        let mut new_heads = Vec::with_capacity(self.snakes.len());
+        let mut wrapped = Vec::with_capacity(self.snakes.len());
+        // The way each snake heads off after bouncing, if it did.
+        let mut bounced = Vec::with_capacity(self.snakes.len());
+
+        for (player, snake) in self.snakes.iter().enumerate() {
+            let head = snake.body.head();
+            match self.moved_wrapping(head, snake.direction) {
+                Some((new_head, wraps)) => {
+                    new_heads.push(new_head);
+                    wrapped.push(wraps);
+                    bounced.push(None);
+                }
+                None => match self.bounced(head, snake.direction).filter(|_| self.bounce) {
+                    Some((new_head, direction)) => {
+                        new_heads.push(new_head);
+                        wrapped.push(false);
+                        bounced.push(Some(direction));
+                    }
+                    None => {
+                        return Err(GameOver {
+                            player,
+                            reason: GameOverReason::HitWall,
+                        })
+                    }
+                },
+            }
+        }
+
+        // Tails move up out of the way on the same tick, unless the snake is
+        // growing, so a head may follow straight into one.
+        let vacated: Vec<Option<Vector>> = self
+            .snakes
+            .iter()
+            .zip(&new_heads)
+            .map(|(snake, new_head)| {
+                let eats = self
+                    .food
+                    .iter()
+                    .any(|food| food.at == *new_head && food.kind.growth() > 0)
+                    && !self.no_growth;
+                if snake.pending_growth > 0 || eats {
+                    None
+                } else {
+                    Some(snake.body.tail().clone())
+                }
+            })
+            .collect();
+
+        let mut events = Vec::new();
+
+        for (player, new_head) in new_heads.iter().enumerate() {
+            let head_on = new_heads
+                .iter()
+                .enumerate()
+                .any(|(other, head)| player != other && head == new_head);
+
+            let reason = if head_on {
+                Some(GameOverReason::HeadOn)
+            } else if self.is_closed(new_head) {
+                Some(GameOverReason::HitWall)
+            } else if self.obstacles.contains(new_head) {
+                Some(GameOverReason::HitObstacle)
+            } else if self.reversed(player, new_head) {
+                // Straight back into its neck, even when that's the tail
+                // moving out of the way.
+                Some(GameOverReason::HitSelf)
+            } else {
+                self.snake_hit(new_head, &vacated).map(|hit| {
+                    if hit == player {
+                        GameOverReason::HitSelf
+                    } else {
+                        GameOverReason::HitSnake
+                    }
+                })
+            };
+
+            // Walls are the only thing a grace period doesn't let snakes through.
+            let reason = reason
+                .filter(|reason| *reason == GameOverReason::HitWall || !self.in_grace())
+                .filter(|reason| *reason != GameOverReason::HitSelf || !self.no_self_collision);
+            // A charge is only spent on a crash it saves the snake from.
+            if reason == Some(GameOverReason::HitObstacle) && self.wall_break_charges > 0 {
+                self.wall_break_charges -= 1;
+                self.obstacles.remove(new_head);
+                events.push(GameEvent::ObstacleSmashed(new_head.clone()));
+                continue;
+            }
+            if let Some(reason) = reason {
+                return Err(GameOver { player, reason });
+            }
+        }
+
+        for (player, (((snake, new_head), wraps), bounce)) in self
+            .snakes
+            .iter_mut()
+            .zip(new_heads)
+            .zip(wrapped)
+            .zip(bounced)
+            .enumerate()
+        {
+            // Bouncing isn't a turn the player made, and any still queued
+            // were checked against the way it was going before.
+            if let Some(direction) = bounce {
+                snake.direction = direction;
+                snake.heading = direction;
+                snake.queued.clear();
+            }
+            if wraps {
+                snake.score = snake.score.saturating_sub(self.wrap_penalty);
+            }
+            if snake.direction != snake.heading {
+                snake.heading = snake.direction;
+                snake.turns += 1;
+            }
+
+            let eaten = self
+                .food
+                .iter()
+                .position(|food| food.at == new_head)
+                .map(|i| self.food.remove(i));
+
+            if let Some(kind) = self.terrain.get(&new_head) {
+                self.terrain_speed = Some(*kind);
+                self.terrain_speed_until = self.ticks + 1 + TERRAIN_TICKS;
+            }
+
+            snake.body.push_head(new_head);
+
+            let ate = eaten.is_some();
+            if let Some(food) = eaten {
+                if snake.eat(food.kind, self.min_length, !self.no_growth, self.combos) {
+                    return Err(GameOver {
+                        player,
+                        reason: GameOverReason::Starved,
+                    });
+                }
+                if food.kind == FoodKind::Pickaxe {
+                    self.wall_break_charges += 1;
+                }
+                if food.kind == FoodKind::Speed {
+                    self.sped_until = self.ticks + 1 + SPEED_TICKS;
+                }
+                self.next_food_at = self.ticks + 1 + self.food_delay;
+                events.push(GameEvent::AteFood {
+                    player,
+                    kind: food.kind,
+                });
+            }
+
+            if snake.pending_growth > 0 {
+                snake.pending_growth -= 1;
+            } else {
+                snake.body.pop_tail();
+            }
+
+            if !ate {
+                snake.combo_left = snake.combo_left.saturating_sub(1);
+            }
+            snake.unfed = if ate { 0 } else { snake.unfed + 1 };
+            if self.hunger_every > 0 && snake.unfed >= self.hunger_every {
+                snake.unfed = 0;
+                if snake.body.len() as u32 <= self.min_length.max(1) {
+                    return Err(GameOver {
+                        player,
+                        reason: GameOverReason::Starved,
+                    });
+                }
+                snake.body.pop_tail();
+            }
+        }
+
+        Ok(events)
+    }
+
+    /// Moves each piece of food one cell toward the head of the first snake
+    /// with an active magnet, along whichever axis it's furthest on that has
+    /// room.
+    fn pull_food(&mut self) {
+        for snake in &mut self.snakes {
+            snake.magnet = snake.magnet.saturating_sub(1);
+        }
+
+        let head = match self.snakes.iter().find(|snake| snake.magnet > 0) {
+            Some(snake) => snake.body.head().clone(),
+            None => return,
+        };
+
+        for i in 0..self.food.len() {
+            if let Some(to) = self.pull_toward(&self.food[i].at, &head) {
+                self.food[i].at = to;
+            }
+        }
+    }
+
+    /// Where food at `at` moves to when pulled toward `head`, if it can move.
+    fn pull_toward(&self, at: &Vector, head: &Vector) -> Option<Vector> {
+        let step = |from: u16, to: u16| match from.cmp(&to) {
+            Ordering::Less => from + 1,
+            Ordering::Greater => from - 1,
+            Ordering::Equal => from,
+        };
+        let horizontal = Vector::new(step(at.0, head.0), at.1);
+        let vertical = Vector::new(at.0, step(at.1, head.1));
+
+        let moves = if at.0.abs_diff(head.0) >= at.1.abs_diff(head.1) {
+            [horizontal, vertical]
+        } else {
+            [vertical, horizontal]
+        };
+
+        moves
+            .into_iter()
+            .find(|to| to != at && self.food_can_move_to(to))
+    }
+
+    fn on_board(&self, vec: &Vector) -> bool {
+        (self.dimensions.x.0..=self.dimensions.x.1).contains(&vec.0)
+            && (self.dimensions.y.0..=self.dimensions.y.1).contains(&vec.1)
+    }
+
+    /// Food may only be moved onto an empty cell on the board.
+    fn food_can_move_to(&self, vec: &Vector) -> bool {
+        self.on_board(vec)
+            && !self.is_blocked(vec)
+            && self.snake_at(vec).is_none()
+            && self.food.iter().all(|food| food.at != *vec)
+    }
+
+    /// Tops the board back up to `food_count`, once any delay since food was
+    /// last eaten has passed. Food only lands where player one's snake can
+    /// get to it, never in a pocket walled off by obstacles, and never on a
+    /// snake or other food. Each piece is picked from a list of the free
+    /// cells, so it takes one draw however full the board is. When there
+    /// isn't room for it all, as much as fits is placed.
+    fn spawn_food(&mut self) -> Vec<Food> {
+        if self.ticks < self.next_food_at {
+            return Vec::new();
+        }
+
+        if let FoodSource::Scripted(cells) = &self.food_source {
+            return match cells.get(self.scripted) {
+                Some(at) if self.food.is_empty() => {
+                    let food = Food {
+                        at: at.clone(),
+                        kind: FoodKind::Normal,
+                    };
+                    self.scripted += 1;
+                    self.food.push(food.clone());
+                    vec![food]
+                }
+                _ => Vec::new(),
+            };
+        }
+
+        let head = self.snakes[0].body.head().clone();
+        let reachable = self.reachable_from(head);
+        let mut excluded = HashSet::new();
+        if self.food_clear_of_heads {
+            excluded.extend(
+                self.snakes
+                    .iter()
+                    .flat_map(|snake| {
+                        let head = snake.body.head();
+                        [snake.direction, snake.direction.opposite()]
+                            .map(|direction| self.moved(head, direction))
+                    })
+                    .flatten(),
+            );
+        }
+        let (x, y) = (self.dimensions.x, self.dimensions.y);
+        // Walked in order rather than taken from `reachable`, whose order
+        // changes from run to run, so the seed alone decides where food goes.
+        let mut free: Vec<Vector> = (y.0..=y.1)
+            .flat_map(|row| (x.0..=x.1).map(move |column| Vector::new(column, row)))
+            .filter(|at| {
+                reachable.contains(at)
+                    && !excluded.contains(at)
+                    && self.snakes.iter().all(|snake| !snake.body.contains(at))
+                    && !self.terrain.contains_key(at)
+                    && self.food.iter().all(|food| food.at != *at)
+            })
+            .collect();
+
+        let mut spawned = Vec::new();
+
+        while self.food.len() < self.food_count as usize && !free.is_empty() {
+            let at = free.swap_remove(self.rng.gen_range(0..free.len()));
+            let food = Food {
+                at,
+                kind: pick_food_kind(&mut self.rng, self.food_table.weights()),
+            };
+            self.food.push(food.clone());
+            spawned.push(food);
+        }
+
+        spawned
+    }
+
+    /// The player whose body `vec` would run into once the `vacated` tails
+    /// have moved on, if any.
+    fn snake_hit(&self, vec: &Vector, vacated: &[Option<Vector>]) -> Option<usize> {
+        self.snakes
+            .iter()
+            .zip(vacated)
+            .position(|(snake, vacated)| vacated.as_ref() != Some(vec) && snake.body.contains(vec))
+    }
+
+    /// The player whose body covers `vec`, if any.
+    fn snake_at(&self, vec: &Vector) -> Option<usize> {
+        self.snakes
+            .iter()
+            .position(|snake| snake.body.contains(vec))
+    }
+
+    /// The cell one step from `vec` in `direction`, wrapping around the edges
+    /// that wrap. `None` if it runs into an edge that doesn't.
+    pub(crate) fn moved(&self, vec: &Vector, direction: Direction) -> Option<Vector> {
+        self.moved_wrapping(vec, direction).map(|(to, _)| to)
+    }
+
+    /// The same as `moved`, along with whether the step went through an edge
+    /// and came back in on the other side.
+    fn moved_wrapping(&self, vec: &Vector, direction: Direction) -> Option<(Vector, bool)> {
+        let (dx, dy) = direction.offset();
+        let (x, wrapped_x) = shift(vec.0, dx, self.dimensions.x, self.wrap_x)?;
+        let (y, wrapped_y) = shift(vec.1, dy, self.dimensions.y, self.wrap_y)?;
+        Some((Vector::new(x, y), wrapped_x || wrapped_y))
+    }
+
+    /// Where a snake heading `direction` from `vec` into an edge that doesn't
+    /// wrap ends up bouncing off it, and which way it heads from there: back
+    /// along whichever axes ran into an edge, both in a corner.
+    fn bounced(&self, vec: &Vector, direction: Direction) -> Option<(Vector, Direction)> {
+        let (dx, dy) = direction.offset();
+        let flip = |off: bool, delta: i8| if off { -delta } else { delta };
+        let direction = Direction::from_offset((
+            flip(runs_off(vec.0, dx, self.dimensions.x, self.wrap_x), dx),
+            flip(runs_off(vec.1, dy, self.dimensions.y, self.wrap_y), dy),
+        ))?;
+        Some((self.moved(vec, direction)?, direction))
+    }
+}
+
+/// Like `Game::step`, but with the tick's randomness drawn from `rng` rather
+/// than the game's own seed, so a search can try the same move under many
+/// different food spawns. A game that ends on this tick comes back over, its
+/// state saying why, rather than as an `Err`. The game returned no longer
+/// plays back from its replay, since its seed has changed.
+pub fn step(state: &Game, input: Option<Direction>, rng: &mut impl Rng) -> (Game, Vec<GameEvent>) {
+    let mut next = state.clone();
+    next.rng = GameRng::seed_from_u64(rng.gen());
+    if let Some(direction) = input {
+        next.change_direction(0, direction);
+    }
+    let events = next.tick().unwrap_or_default();
+    (next, events)
+}
+
+/// Whether moving `value` by `delta` runs off an end of `low..=high` that
+/// doesn't `wrap`.
+fn runs_off(value: u16, delta: i8, (low, high): (u16, u16), wrap: bool) -> bool {
+    !wrap && ((delta == -1 && value == low) || (delta == 1 && value == high))
+}
+
+/// Moves `value` by `delta` within `low..=high`, coming back in at the other
+/// end if `wrap` and stopping at the edge otherwise. The flag is whether it
+/// came back in.
+fn shift(value: u16, delta: i8, (low, high): (u16, u16), wrap: bool) -> Option<(u16, bool)> {
+    match delta {
+        -1 if value == low => wrap.then_some((high, true)),
+        -1 => Some((value - 1, false)),
+        1 if value == high => wrap.then_some((low, true)),
+        1 => Some((value + 1, false)),
+        _ => Some((value, false)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::rngs::StdRng;
+    use std::collections::HashMap;
+
+    fn cells(cells: &[(u16, u16)]) -> Vec<Vector> {
+        cells.iter().map(|&(x, y)| Vector::new(x, y)).collect()
+    }
+
+    /// Lays player `player`'s snake over `body`, head first, last having
+    /// moved `heading` and about to move `direction`.
+    fn lay(
+        game: &mut Game,
+        player: usize,
+        body: &[(u16, u16)],
+        heading: Direction,
+        direction: Direction,
+    ) {
+        let snake = &mut game.snakes[player];
+        snake.body = Body::try_from(cells(body)).unwrap();
+        snake.heading = heading;
+        snake.direction = direction;
+    }
+
+    #[test]
+    fn a_lone_head_is_its_own_tail() {
+        let mut body = Body::new(Vector::new(3, 3));
+        assert_eq!(body.len(), 1);
+        assert_eq!(body.tail(), body.head());
+        body.pop_tail();
+        assert_eq!(body.len(), 1);
+        assert!(body.contains(&Vector::new(3, 3)));
+    }
+
+    #[test]
+    fn a_body_cant_be_empty() {
+        assert!(Body::try_from(Vec::new()).is_err());
+        assert!(serde_json::from_str::<Body>("[]").is_err());
+        let body: Body = serde_json::from_str("[[2, 1], [1, 1]]").unwrap();
+        assert_eq!(body.tail(), &Vector::new(1, 1));
+    }
+
+    #[test]
+    fn a_one_segment_snake_moves_grows_and_crashes() {
+        let mut game = Game::with_seed(1, 1);
+        game.food = vec![Food {
+            at: Vector::new(6, 5),
+            kind: FoodKind::Normal,
+        }];
+        game.tick().unwrap();
+        let body: Vec<&Vector> = game.snakes()[0].body().iter().collect();
+        assert_eq!(body, [&Vector::new(6, 5), &Vector::new(5, 5)]);
+
+        let mut game = Game::with_seed(1, 1);
+        game.tick().unwrap();
+        assert_eq!(game.snakes()[0].body().len(), 1);
+        assert_eq!(game.snakes()[0].body().head(), &Vector::new(6, 5));
+        game.set_wrap(false, false);
+        for _ in 6..20 {
+            game.tick().unwrap();
+        }
+        assert_eq!(reason(&mut game), GameOverReason::HitWall);
+    }
+
+    fn distance(a: &Vector, b: &Vector) -> u16 {
+        a.0.abs_diff(b.0) + a.1.abs_diff(b.1)
+    }
+
+    /// A game with the snake holding a magnet and a piece of food out at
+    /// `at`.
+    fn magnetic(at: (u16, u16)) -> Game {
+        let mut game = Game::with_seed(1, 1);
+        game.snakes[0].magnet = 5;
+        game.food = vec![Food {
+            at: Vector::new(at.0, at.1),
+            kind: FoodKind::Normal,
+        }];
+        game
+    }
+
+    fn food_at(game: &Game) -> &Vector {
+        &game.food()[0].at
+    }
+
+    #[test]
+    fn magnet_pulls_food_closer_every_tick() {
+        let mut game = magnetic((12, 9));
+        for _ in 0..4 {
+            let head = game.snakes()[0].body().head();
+            let before = distance(food_at(&game), head);
+            game.tick().unwrap();
+            let head = game.snakes()[0].body().head();
+            let after = distance(food_at(&game), head);
+            assert!(after < before, "{} then {}", before, after);
+        }
+    }
+
+    #[test]
+    fn magnet_never_pulls_food_onto_an_obstacle() {
+        let mut game = magnetic((12, 9));
+        game.set_layout(HashSet::from([Vector::new(11, 9), Vector::new(12, 8)]))
+            .unwrap();
+        game.tick().unwrap();
+        assert_eq!(game.food[0].at, Vector::new(12, 9));
+    }
+
+    #[test]
+    fn food_stays_put_once_the_magnet_wears_off() {
+        let mut game = magnetic((12, 9));
+        game.snakes[0].magnet = 1;
+        game.tick().unwrap();
+        assert_eq!(food_at(&game), &Vector::new(12, 9));
+    }
+
+    fn reason(game: &mut Game) -> GameOverReason {
+        game.tick().unwrap_err().reason
+    }
+
+    #[test]
+    fn running_off_a_wall_is_hit_wall() {
+        let mut game = Game::with_seed(1, 1);
+        game.set_wrap(false, false);
+        lay(&mut game, 0, &[(1, 5)], Direction::Left, Direction::Left);
+        assert_eq!(reason(&mut game), GameOverReason::HitWall);
+    }
+
+    #[test]
+    fn running_into_itself_is_hit_self() {
+        let mut game = Game::with_seed(1, 1);
+        lay(
+            &mut game,
+            0,
+            &[(5, 5), (5, 6), (6, 6), (6, 5), (7, 5)],
+            Direction::Right,
+            Direction::Right,
+        );
+        assert_eq!(reason(&mut game), GameOverReason::HitSelf);
+    }
+
+    #[test]
+    fn running_into_an_obstacle_is_hit_obstacle() {
+        let mut game = Game::with_seed(1, 1);
+        game.set_layout(HashSet::from([Vector::new(6, 5)])).unwrap();
+        assert_eq!(reason(&mut game), GameOverReason::HitObstacle);
+    }
+
+    #[test]
+    fn going_hungry_at_the_minimum_length_is_starved() {
+        let mut game = Game::with_seed(1, 1);
+        game.set_hunger_interval(1);
+        assert_eq!(reason(&mut game), GameOverReason::Starved);
+    }
+
+    #[test]
+    fn running_into_the_other_snake_is_hit_snake() {
+        let mut game = Game::with_seed(2, 1);
+        lay(&mut game, 0, &[(5, 5)], Direction::Right, Direction::Right);
+        lay(
+            &mut game,
+            1,
+            &[(6, 4), (6, 5), (6, 6)],
+            Direction::Up,
+            Direction::Up,
+        );
+        assert_eq!(
+            game.tick().unwrap_err(),
+            GameOver {
+                player: 0,
+                reason: GameOverReason::HitSnake
+            }
+        );
+    }
+
+    /// A snake about to run into an obstacle, with `charges` to break it.
+    fn facing_an_obstacle(charges: u16) -> Game {
+        let mut game = Game::with_seed(1, 1);
+        game.obstacles = HashSet::from([Vector::new(6, 5)]);
+        game.wall_break_charges = charges;
+        game
+    }
+
+    #[test]
+    fn a_charge_smashes_the_obstacle() {
+        let mut game = facing_an_obstacle(1);
+        let events = game.tick().unwrap();
+        assert!(events.contains(&GameEvent::ObstacleSmashed(Vector::new(6, 5))));
+        assert!(!game.obstacles().contains(&Vector::new(6, 5)));
+        assert_eq!(game.snakes()[0].body().head(), &Vector::new(6, 5));
+        assert_eq!(game.wall_break_charges(), 0);
+    }
+
+    #[test]
+    fn no_charge_no_smashing() {
+        let mut game = facing_an_obstacle(0);
+        assert_eq!(reason(&mut game), GameOverReason::HitObstacle);
+    }
+
+    #[test]
+    fn eating_a_pickaxe_gives_a_charge() {
+        let mut game = Game::with_seed(1, 1);
+        game.food = vec![Food {
+            at: Vector::new(6, 5),
+            kind: FoodKind::Pickaxe,
+        }];
+        game.tick().unwrap();
+        assert_eq!(game.wall_break_charges(), 1);
+    }
+
+    #[test]
+    fn growth_from_food_eaten_back_to_back_adds_up() {
+        let mut game = a_row_of(&[FoodKind::Normal, FoodKind::Normal, FoodKind::Golden]);
+        for _ in 0..3 {
+            game.tick().unwrap();
+        }
+        assert!(game.food().is_empty());
+        assert_eq!(game.snakes[0].pending_growth, 2);
+
+        for _ in 0..3 {
+            game.tick().unwrap();
+        }
+        assert_eq!(game.snakes()[0].body().len(), 6);
+        assert_eq!(game.snakes[0].pending_growth, 0);
+    }
+
+    #[test]
+    fn rotten_food_takes_back_growth_still_to_come_first() {
+        let mut game = a_row_of(&[FoodKind::Golden, FoodKind::Rotten]);
+        game.tick().unwrap();
+        assert_eq!(game.snakes[0].pending_growth, 2);
+        game.tick().unwrap();
+        assert_eq!(game.snakes[0].pending_growth, 0);
+        assert_eq!(game.snakes()[0].body().len(), 2);
+    }
+
+    /// The obstacles after each of two lives lost straight away, with or
+    /// without a new scattering each time.
+    fn obstacles_after_respawns(regenerate: bool) -> Vec<HashSet<Vector>> {
+        let mut game = Game::with_seed(1, 3);
+        game.set_lives(3);
+        game.place_obstacles(20);
+        game.regenerate_obstacles_on_respawn(regenerate);
+        (0..2)
+            .map(|_| {
+                // Obstacles keep clear of the starting row, so one is put in
+                // the snake's way by hand.
+                game.obstacles.insert(Vector::new(6, 5));
+                let events = game.tick().unwrap();
+                assert!(events.contains(&GameEvent::LostLife(GameOverReason::HitObstacle)));
+                game.obstacles().clone()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn each_life_gets_new_obstacles_when_regenerating() {
+        let layouts = obstacles_after_respawns(true);
+        assert_ne!(layouts[0], layouts[1]);
+        assert_eq!(layouts, obstacles_after_respawns(true));
+    }
+
+    #[test]
+    fn each_life_keeps_the_obstacles_otherwise() {
+        let layouts = obstacles_after_respawns(false);
+        assert_eq!(layouts[0], layouts[1]);
+    }
+
+    /// A snake four long in a square, about to move into its own tail.
+    fn chasing_its_tail() -> Game {
+        let mut game = Game::with_seed(1, 1);
+        lay(
+            &mut game,
+            0,
+            &[(5, 5), (5, 6), (6, 6), (6, 5)],
+            Direction::Right,
+            Direction::Right,
+        );
+        game
+    }
+
+    #[test]
+    fn head_follows_into_the_tail_as_it_moves_away() {
+        let mut game = chasing_its_tail();
+        game.tick().unwrap();
+        let body = game.snakes()[0].body();
+        assert_eq!(body.head(), &Vector::new(6, 5));
+        assert_eq!(body.len(), 4);
+    }
+
+    #[test]
+    fn tail_stays_put_while_growth_is_pending() {
+        let mut game = chasing_its_tail();
+        game.snakes[0].pending_growth = 1;
+        assert_eq!(reason(&mut game), GameOverReason::HitSelf);
+    }
+
+    /// Player one about to move into the tail of player two, who's moving
+    /// away from it.
+    fn chasing_the_other_tail() -> Game {
+        let mut game = Game::with_seed(2, 1);
+        lay(
+            &mut game,
+            0,
+            &[(5, 5), (4, 5)],
+            Direction::Right,
+            Direction::Right,
+        );
+        lay(
+            &mut game,
+            1,
+            &[(6, 7), (6, 6), (6, 5)],
+            Direction::Down,
+            Direction::Down,
+        );
+        game
+    }
+
+    #[test]
+    fn head_follows_into_another_snakes_tail_as_it_moves_away() {
+        let mut game = chasing_the_other_tail();
+        game.tick().unwrap();
+        assert_eq!(game.snakes()[0].body().head(), &Vector::new(6, 5));
+        assert_eq!(game.snakes()[1].body().head(), &Vector::new(6, 8));
+    }
+
+    #[test]
+    fn another_snakes_tail_stays_put_while_it_grows() {
+        let mut game = chasing_the_other_tail();
+        game.snakes[1].pending_growth = 1;
+        let over = game.tick().unwrap_err();
+        assert_eq!(over.player, 0);
+        assert_eq!(over.reason, GameOverReason::HitSnake);
+    }
+
+    /// A game with a piece of normal food right in front of the snake.
+    fn food_ahead() -> Game {
+        let mut game = Game::with_seed(1, 1);
+        game.food = vec![Food {
+            at: Vector::new(6, 5),
+            kind: FoodKind::Normal,
+        }];
+        game
+    }
+
+    #[test]
+    fn food_comes_back_once_the_delay_is_up() {
+        let mut game = food_ahead();
+        game.set_food_delay(3);
+        for _ in 0..3 {
+            game.tick().unwrap();
+            assert!(game.food().is_empty(), "tick {}", game.ticks());
+        }
+        game.tick().unwrap();
+        assert_eq!(game.food().len(), 1);
+    }
+
+    #[test]
+    fn food_comes_back_straight_away_without_a_delay() {
+        let mut game = food_ahead();
+        game.set_food_delay(0);
+        game.tick().unwrap();
+        assert_eq!(game.food().len(), 1);
+    }
+
+    /// A snake `length` long about to eat rotten food, shrinking no shorter
+    /// than `min_length`.
+    fn about_to_eat_rotten(length: u16, min_length: u32) -> Game {
+        let mut game = Game::with_seed(1, 1);
+        game.food = vec![Food {
+            at: Vector::new(6, 5),
+            kind: FoodKind::Rotten,
+        }];
+        game.set_min_length(min_length);
+        let body: Vec<(u16, u16)> = (0..length).map(|i| (5 - i, 5)).collect();
+        lay(&mut game, 0, &body, Direction::Right, Direction::Right);
+        game
+    }
+
+    #[test]
+    fn rotten_food_shrinks_down_to_the_floor() {
+        let mut game = about_to_eat_rotten(4, 1);
+        game.tick().unwrap();
+        assert_eq!(game.snakes()[0].body().len(), 2);
+    }
+
+    #[test]
+    fn a_higher_floor_clamps_the_shrinking() {
+        let mut game = about_to_eat_rotten(4, 3);
+        game.tick().unwrap();
+        assert_eq!(game.snakes()[0].body().len(), 3);
+
+        let mut game = about_to_eat_rotten(2, 2);
+        game.tick().unwrap();
+        assert_eq!(game.snakes()[0].body().len(), 2);
+    }
+
+    #[test]
+    fn shrinking_below_one_segment_is_starving() {
+        for length in [1, 2] {
+            let mut game = about_to_eat_rotten(length, 1);
+            assert_eq!(reason(&mut game), GameOverReason::Starved, "{}", length);
+        }
+    }
+
+    #[test]
+    fn a_floor_of_zero_is_a_floor_of_one() {
+        for length in [1, 2, 3, 4] {
+            let mut zero = about_to_eat_rotten(length, 0);
+            let mut one = about_to_eat_rotten(length, 1);
+            assert_eq!(zero.tick().map(|_| ()), one.tick().map(|_| ()));
+            assert_eq!(zero.snakes()[0].body(), one.snakes()[0].body());
+        }
+    }
+
+    /// A game with `kinds` of food laid one after another in the snake's
+    /// path and none to follow once it's eaten.
+    fn a_row_of(kinds: &[FoodKind]) -> Game {
+        let mut game = Game::with_seed(1, 1);
+        game.set_food_delay(1000);
+        for (x, kind) in (6..).zip(kinds) {
+            game.food.push(Food {
+                at: Vector::new(x, 5),
+                kind: *kind,
+            });
+        }
+        game
+    }
+
+    #[test]
+    fn without_growth_food_scores_but_the_snake_stays_as_long() {
+        let mut game = a_row_of(&[FoodKind::Normal, FoodKind::Golden]);
+        game.disable_growth(true);
+        lay(
+            &mut game,
+            0,
+            &[(5, 5), (4, 5), (3, 5)],
+            Direction::Right,
+            Direction::Right,
+        );
+        for _ in 0..4 {
+            game.tick().unwrap();
+            assert_eq!(game.snakes()[0].body().len(), 3);
+            assert_eq!(game.snakes[0].pending_growth, 0);
+        }
+        assert_eq!(game.snakes()[0].score(), 4);
+    }
+
+    #[test]
+    fn food_never_lands_in_a_walled_off_pocket() {
+        let pocket = Vector::new(15, 8);
+        let walls = [(14, 8), (16, 8), (15, 7), (15, 9)];
+        for seed in 0..50 {
+            let mut game = Game::with_seed(1, seed);
+            game.obstacles = walls.iter().map(|&(x, y)| Vector::new(x, y)).collect();
+            game.set_food_table(FoodTable::new(vec![(FoodKind::Normal, 1.0)]).unwrap());
+            game.set_food_count(20);
+            for _ in 0..10 {
+                game.tick().unwrap();
+                assert!(game.food().iter().all(|food| food.at != pocket));
+            }
+        }
+    }
+
+    #[test]
+    fn food_keeps_clear_of_the_cells_ahead_of_and_behind_the_head() {
+        let excluded = [Vector::new(6, 5), Vector::new(4, 5)];
+        let mut landed = HashSet::new();
+        for seed in 0..20 {
+            let mut game = Game::with_seed(1, seed);
+            game.keep_food_clear_of_heads(true);
+            game.set_food_count(150);
+            let full = game.with_food();
+            assert_eq!(full.food().len(), 150);
+            assert!(full.food().iter().all(|food| !excluded.contains(&food.at)));
+
+            game.keep_food_clear_of_heads(false);
+            landed.extend(game.with_food().food().iter().map(|food| food.at.clone()));
+        }
+        // Where food goes when they aren't kept clear.
+        assert!(excluded.iter().all(|cell| landed.contains(cell)));
+    }
+
+    /// A 5x5 board split down the middle column by obstacles.
+    fn split_board(wrap: bool) -> Game {
+        let mut game = Game::with_dimensions(1, 1, Dimensions::new(5, 5)).unwrap();
+        game.set_wrap(wrap, wrap);
+        game.set_layout((1..=5).map(|y| Vector::new(3, y)).collect())
+            .unwrap();
+        game
+    }
+
+    #[test]
+    fn every_cell_of_an_open_board_is_reachable() {
+        let game = Game::with_dimensions(1, 1, Dimensions::new(5, 5)).unwrap();
+        assert_eq!(game.reachable_cells(), 25);
+    }
+
+    #[test]
+    fn walls_cut_off_what_wrapping_reaches_round() {
+        assert_eq!(split_board(true).reachable_cells(), 20);
+        assert_eq!(split_board(false).reachable_cells(), 10);
+    }
+
+    #[test]
+    fn the_body_is_in_the_way_too() {
+        let mut game = split_board(false);
+        lay(
+            &mut game,
+            0,
+            &[(5, 5), (5, 4), (4, 4), (4, 5)],
+            Direction::Up,
+            Direction::Up,
+        );
+        // The head itself, and whatever isn't boxed in by the body.
+        let reachable = game.reachable_set();
+        assert_eq!(reachable.len(), 1);
+        assert!(reachable.contains(&Vector::new(5, 5)));
+
+        lay(
+            &mut game,
+            0,
+            &[(5, 5), (5, 4)],
+            Direction::Down,
+            Direction::Down,
+        );
+        assert_eq!(game.reachable_cells(), 9);
+    }
+
+    /// A game about to run onto a cell of `kind` terrain.
+    fn onto(kind: TerrainKind) -> Game {
+        let mut game = Game::with_seed(1, 1);
+        game.set_terrain(HashMap::from([(Vector::new(6, 5), kind)]))
+            .unwrap();
+        game
+    }
+
+    #[test]
+    fn fast_floor_shortens_the_tick_for_a_while() {
+        let mut game = onto(TerrainKind::Fast);
+        assert_eq!(game.interval_scale(), 1.0);
+        game.tick().unwrap();
+        for _ in 0..TERRAIN_TICKS {
+            assert_eq!(game.interval_scale(), 0.5, "tick {}", game.ticks());
+            game.tick().unwrap();
+        }
+        assert_eq!(game.interval_scale(), 1.0);
+    }
+
+    #[test]
+    fn slow_floor_lengthens_it() {
+        let mut game = onto(TerrainKind::Slow);
+        game.tick().unwrap();
+        assert_eq!(game.interval_scale(), 2.0);
+    }
+
+    #[test]
+    fn terrain_keeps_off_obstacles() {
+        let mut game = Game::with_seed(1, 1);
+        game.set_layout(HashSet::from([Vector::new(6, 6)])).unwrap();
+        let terrain = HashMap::from([(Vector::new(6, 6), TerrainKind::Fast)]);
+        assert!(game.set_terrain(terrain).is_err());
+    }
+
+    #[test]
+    fn scripted_food_appears_in_order_one_at_a_time() {
+        let mut game = Game::with_seed(1, 1);
+        let script = cells(&[(7, 5), (9, 5), (11, 5)]);
+        game.set_food_source(FoodSource::Scripted(script.clone()))
+            .unwrap();
+
+        let mut spawned = Vec::new();
+        while !game.is_cleared() {
+            for event in game.tick().unwrap() {
+                if let GameEvent::FoodSpawned(food) = event {
+                    assert_eq!(game.food(), std::slice::from_ref(&food));
+                    spawned.push(food.at);
+                }
+            }
+            assert!(game.food().len() <= 1);
+        }
+        assert_eq!(spawned, script);
+        assert_eq!(game.snakes()[0].body().head(), &Vector::new(11, 5));
+        assert_eq!(game.snakes()[0].score(), 3);
+    }
+
+    #[test]
+    fn scripted_food_is_never_cleared_before_the_last() {
+        let mut game = Game::with_seed(1, 1);
+        game.set_food_source(FoodSource::Scripted(cells(&[(7, 5), (9, 5)])))
+            .unwrap();
+        for _ in 0..3 {
+            game.tick().unwrap();
+            assert!(!game.is_cleared());
+        }
+        game.tick().unwrap();
+        assert!(game.is_cleared());
+    }
+
+    #[test]
+    fn scripted_food_must_be_clear_of_the_level() {
+        let mut game = Game::with_seed(1, 1);
+        game.set_layout(HashSet::from([Vector::new(9, 9)])).unwrap();
+        let on = |x, y| FoodSource::Scripted(cells(&[(2, 2), (x, y)]));
+        assert!(game.set_food_source(on(9, 9)).is_err());
+        assert!(game.set_food_source(on(21, 9)).is_err());
+        assert!(game.set_food_source(on(8, 9)).is_ok());
+    }
+
+    const HEADINGS: [(Direction, (i8, i8)); 8] = [
+        (Direction::Left, (-1, 0)),
+        (Direction::Up, (0, -1)),
+        (Direction::Right, (1, 0)),
+        (Direction::Down, (0, 1)),
+        (Direction::UpLeft, (-1, -1)),
+        (Direction::UpRight, (1, -1)),
+        (Direction::DownLeft, (-1, 1)),
+        (Direction::DownRight, (1, 1)),
+    ];
+
+    /// Where a lone head at `at` ends up after a tick heading `direction`,
+    /// with diagonals allowed.
+    fn moved_from(at: (u16, u16), direction: Direction, wrap: bool) -> Result<Vector, GameOver> {
+        let mut game = Game::with_seed(1, 1);
+        game.allow_diagonals(true);
+        game.set_wrap(wrap, wrap);
+        lay(&mut game, 0, &[at], direction, direction);
+        game.tick()?;
+        Ok(game.snakes()[0].body().head().clone())
+    }
+
+    #[test]
+    fn every_heading_moves_one_cell_from_the_middle() {
+        for (direction, (dx, dy)) in HEADINGS {
+            let to = Vector::new((10 + dx as i16) as u16, (5 + dy as i16) as u16);
+            assert_eq!(
+                moved_from((10, 5), direction, false).unwrap(),
+                to,
+                "{:?}",
+                direction
+            );
+        }
+    }
+
+    #[test]
+    fn every_heading_wraps_around_from_the_corner() {
+        let expected = [
+            (20, 1),
+            (1, 10),
+            (2, 1),
+            (1, 2),
+            (20, 10),
+            (2, 10),
+            (20, 2),
+            (2, 2),
+        ];
+        for ((direction, _), (x, y)) in HEADINGS.into_iter().zip(expected) {
+            assert_eq!(
+                moved_from((1, 1), direction, true).unwrap(),
+                Vector::new(x, y),
+                "{:?}",
+                direction
+            );
+        }
+    }
+
+    #[test]
+    fn headings_off_the_corner_hit_the_wall_without_wrapping() {
+        for (direction, (dx, dy)) in HEADINGS {
+            match moved_from((1, 1), direction, false) {
+                Ok(head) => {
+                    assert!(dx >= 0 && dy >= 0, "{:?}", direction);
+                    assert_eq!(head, Vector::new((1 + dx) as u16, (1 + dy) as u16));
+                }
+                Err(over) => {
+                    assert!(dx < 0 || dy < 0, "{:?}", direction);
+                    assert_eq!(over.reason, GameOverReason::HitWall);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn diagonals_cant_reverse_either() {
+        let mut game = Game::with_seed(1, 1);
+        game.allow_diagonals(true);
+        lay(
+            &mut game,
+            0,
+            &[(5, 5), (4, 4)],
+            Direction::DownRight,
+            Direction::DownRight,
+        );
+        assert!(!game.apply_direction(0, Direction::UpLeft));
+        assert!(game.apply_direction(0, Direction::UpRight));
+    }
+
+    #[test]
+    fn diagonals_are_refused_unless_allowed() {
+        let mut game = Game::with_seed(1, 1);
+        assert!(!game.apply_direction(0, Direction::DownRight));
+        game.allow_diagonals(true);
+        assert!(game.apply_direction(0, Direction::DownRight));
+    }
+
+    /// A board with one of everything on it, and food under the body.
+    fn one_of_everything() -> Game {
+        let mut game = Game::with_dimensions(1, 1, Dimensions::new(6, 6)).unwrap();
+        lay(
+            &mut game,
+            0,
+            &[(3, 2), (2, 2), (1, 2)],
+            Direction::Right,
+            Direction::Right,
+        );
+        game.set_layout(HashSet::from([Vector::new(5, 1)])).unwrap();
+        game.set_terrain(HashMap::from([(Vector::new(1, 6), TerrainKind::Fast)]))
+            .unwrap();
+        for (at, kind) in [((6, 6), FoodKind::Golden), ((2, 2), FoodKind::Normal)] {
+            game.food.push(Food {
+                at: Vector::new(at.0, at.1),
+                kind,
+            });
+        }
+        game
+    }
+
+    #[test]
+    fn grid_snapshot_classifies_every_cell() {
+        let game = one_of_everything();
+        let expected = HashMap::from([
+            (Vector::new(3, 2), CellKind::Head(0)),
+            (Vector::new(2, 2), CellKind::Body(0)),
+            (Vector::new(1, 2), CellKind::Body(0)),
+            (Vector::new(5, 1), CellKind::Obstacle),
+            (Vector::new(1, 6), CellKind::Terrain(TerrainKind::Fast)),
+            (Vector::new(6, 6), CellKind::Food(FoodKind::Golden)),
+        ]);
+
+        let grid = game.grid_snapshot();
+        assert_eq!((grid.width(), grid.height()), (6, 6));
+        for y in 1..=6 {
+            for x in 1..=6 {
+                let at = Vector::new(x, y);
+                let kind = expected.get(&at).copied().unwrap_or(CellKind::Empty);
+                assert_eq!(grid.get(&at), Some(kind), "({}, {})", x, y);
+            }
+        }
+        assert_eq!(grid.get(&Vector::new(7, 1)), None);
+    }
+
+    #[test]
+    fn the_board_prints_a_character_per_cell() {
+        let board = one_of_everything().grid_snapshot().to_string();
+        assert_eq!(
+            board,
+            "....#.\n\
+             oo@...\n\
+             ......\n\
+             ......\n\
+             ......\n\
+             >....$\n"
+        );
+    }
+
+    #[test]
+    fn a_printed_board_reads_back_in_as_a_level() {
+        let game = one_of_everything();
+        let board = game.grid_snapshot().to_string();
+        let layout = crate::read_layout(board.as_bytes(), game.dimensions()).unwrap();
+        assert_eq!(layout.obstacles, game.layout);
+        assert_eq!(layout.terrain, game.terrain);
+    }
+
+    #[test]
+    fn grid_snapshot_shows_closed_rings_as_wall() {
+        let mut game = Game::with_seed(1, 1);
+        game.shrink_every(1);
+        game.tick().unwrap();
+        let grid = game.grid_snapshot();
+        assert_eq!(grid.get(&Vector::new(1, 1)), Some(CellKind::Wall));
+        assert_eq!(grid.get(&Vector::new(20, 7)), Some(CellKind::Wall));
+        assert_ne!(grid.get(&Vector::new(2, 2)), Some(CellKind::Wall));
+    }
+
+    /// The non-empty cells of `game`'s grid snapshot, a row at a time.
+    fn occupied(game: &Game) -> Vec<(Vector, CellKind)> {
+        let grid = game.grid_snapshot();
+        let (x, y) = (game.dimensions.x, game.dimensions.y);
+        (y.0..=y.1)
+            .flat_map(|row| (x.0..=x.1).map(move |column| Vector::new(column, row)))
+            .filter_map(|at| match grid.get(&at) {
+                Some(CellKind::Empty) | None => None,
+                Some(kind) => Some((at, kind)),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn cells_yields_just_the_occupied_cells_a_row_at_a_time() {
+        let cells: Vec<_> = one_of_everything().cells().collect();
+        let expected = [
+            ((5, 1), CellKind::Obstacle),
+            ((1, 2), CellKind::Body(0)),
+            ((2, 2), CellKind::Body(0)),
+            ((3, 2), CellKind::Head(0)),
+            ((1, 6), CellKind::Terrain(TerrainKind::Fast)),
+            ((6, 6), CellKind::Food(FoodKind::Golden)),
+        ];
+        let expected: Vec<_> = expected
+            .into_iter()
+            .map(|((x, y), kind)| (Vector::new(x, y), kind))
+            .collect();
+        assert_eq!(cells, expected);
+    }
+
+    #[test]
+    fn cells_matches_the_grid_snapshot() {
+        let mut game = Game::with_seed(2, 1);
+        game.shrink_every(1);
+        game.place_obstacles(10);
+        for _ in 0..3 {
+            let _ = game.tick();
+            assert_eq!(game.cells().collect::<Vec<_>>(), occupied(&game));
+        }
+        assert_eq!(
+            one_of_everything().cells().collect::<Vec<_>>(),
+            occupied(&one_of_everything())
+        );
+    }
+
+    /// A snake `length` long heading right, turning straight back with or
+    /// without reversing allowed.
+    fn reversing(length: u16, allow: bool) -> (Game, bool) {
+        let mut game = Game::with_seed(1, 1);
+        game.allow_reverse(allow);
+        let body: Vec<(u16, u16)> = (0..length).map(|i| (5 - i, 5)).collect();
+        lay(&mut game, 0, &body, Direction::Right, Direction::Right);
+        let taken = game.change_direction(0, Direction::Left);
+        (game, taken)
+    }
+
+    #[test]
+    fn a_lone_head_reverses_either_way() {
+        for allow in [false, true] {
+            let (mut game, taken) = reversing(1, allow);
+            assert!(taken);
+            game.tick().unwrap();
+            assert_eq!(game.snakes()[0].body().head(), &Vector::new(4, 5));
+        }
+    }
+
+    #[test]
+    fn reversing_into_the_body_dies_when_allowed() {
+        for length in [2, 3] {
+            let (mut game, taken) = reversing(length, true);
+            assert!(taken);
+            assert_eq!(reason(&mut game), GameOverReason::HitSelf, "{}", length);
+        }
+    }
+
+    #[test]
+    fn reversing_is_ignored_otherwise() {
+        let (mut game, taken) = reversing(3, false);
+        assert!(!taken);
+        game.tick().unwrap();
+        assert_eq!(game.snakes()[0].body().head(), &Vector::new(6, 5));
+    }
+
+    #[test]
+    fn without_self_collision_the_snake_runs_over_itself() {
+        let mut game = chasing_its_tail();
+        game.disable_self_collision(true);
+        game.snakes[0].pending_growth = 1;
+        game.tick().unwrap();
+        let body = game.snakes()[0].body();
+        assert_eq!(body.head(), &Vector::new(6, 5));
+        assert_eq!(body.len(), 5);
+        assert_eq!(
+            body.iter().filter(|at| **at == Vector::new(6, 5)).count(),
+            2
+        );
+
+        // It still scores and moves on as usual.
+        game.food.push(Food {
+            at: Vector::new(7, 5),
+            kind: FoodKind::Normal,
+        });
+        game.tick().unwrap();
+        assert_eq!(game.snakes()[0].score(), 1);
+    }
+
+    #[test]
+    fn without_self_collision_walls_still_count() {
+        let mut game = Game::with_seed(1, 1);
+        game.disable_self_collision(true);
+        game.set_wrap(false, false);
+        lay(
+            &mut game,
+            0,
+            &[(20, 5), (19, 5)],
+            Direction::Right,
+            Direction::Right,
+        );
+        assert_eq!(reason(&mut game), GameOverReason::HitWall);
+
+        let mut game = facing_an_obstacle(0);
+        game.disable_self_collision(true);
+        assert_eq!(reason(&mut game), GameOverReason::HitObstacle);
+    }
+
+    #[test]
+    fn running_out_of_ticks_is_time_up() {
+        let mut game = Game::with_seed(1, 1);
+        game.set_time_limit(2);
+        game.tick().unwrap();
+        game.tick().unwrap();
+        assert_eq!(reason(&mut game), GameOverReason::TimeUp);
+    }
+
+    #[test]
+    fn heads_meeting_is_head_on() {
+        let mut game = Game::with_seed(2, 1);
+        lay(&mut game, 0, &[(5, 5)], Direction::Right, Direction::Right);
+        lay(&mut game, 1, &[(7, 5)], Direction::Left, Direction::Left);
+        assert_eq!(reason(&mut game), GameOverReason::HeadOn);
+    }
+
+    #[test]
+    fn undo_puts_back_the_body_from_before_the_last_tick() {
+        let mut game = Game::with_seed(1, 1);
+        game.allow_undos(2);
+        let before = game.snakes()[0].body().clone();
+        game.tick().unwrap();
+        assert_ne!(game.snakes()[0].body(), &before);
+
+        assert!(game.undo());
+        assert_eq!(game.snakes()[0].body(), &before);
+        assert_eq!(game.ticks(), 0);
+        assert_eq!(game.undos_left(), 1);
+        // Nothing more to go back to.
+        assert!(!game.undo());
+        assert_eq!(game.undos_left(), 1);
+    }
+
+    #[test]
+    fn undo_history_stays_bounded() {
+        let mut game = Game::with_seed(1, 1);
+        game.allow_undos(1000);
+        for _ in 0..MAX_HISTORY * 2 {
+            game.tick().unwrap();
+        }
+        assert_eq!(game.history.len(), MAX_HISTORY);
+    }
+
+    #[test]
+    fn no_undos_without_allowing_them() {
+        let mut game = Game::with_seed(1, 1);
+        game.tick().unwrap();
+        assert!(!game.undo());
+        assert_eq!(game.ticks(), 1);
+    }
+
+    #[test]
+    fn a_grace_period_goes_through_without_spending_a_charge() {
+        let mut game = facing_an_obstacle(1);
+        game.set_grace_period(5);
+        game.tick().unwrap();
+        assert_eq!(game.wall_break_charges(), 1);
+        assert!(game.obstacles().contains(&Vector::new(6, 5)));
+    }
+
+    #[test]
+    fn crashes_count_again_once_the_grace_period_is_over() {
+        let mut game = Game::with_seed(1, 1);
+        game.obstacles = HashSet::from([Vector::new(6, 5), Vector::new(8, 5)]);
+        game.set_grace_period(2);
+        assert!(game.in_grace());
+        game.tick().unwrap();
+        game.tick().unwrap();
+        assert!(!game.in_grace());
+        assert_eq!(reason(&mut game), GameOverReason::HitObstacle);
+    }
+
+    #[test]
+    fn a_grace_period_doesnt_get_through_walls() {
+        let mut game = Game::with_seed(1, 1);
+        game.set_wrap(false, false);
+        game.set_grace_period(5);
+        lay(&mut game, 0, &[(20, 5)], Direction::Right, Direction::Right);
+        assert_eq!(reason(&mut game), GameOverReason::HitWall);
+    }
+
+    #[test]
+    fn there_is_no_grace_period_by_default() {
+        let game = Game::with_seed(1, 1);
+        assert!(!game.in_grace());
+    }
+
+    fn json(game: &Game) -> String {
+        serde_json::to_string(game).unwrap()
+    }
+
+    #[test]
+    fn step_leaves_the_game_as_it_was() {
+        let mut game = Game::with_seed(1, 9);
+        game.set_food_count(3);
+        game.tick().unwrap();
+        let before = json(&game);
+
+        let (first, events) = game.step(Some(Direction::Down)).unwrap();
+        let (second, again) = game.step(Some(Direction::Down)).unwrap();
+        assert_eq!(json(&game), before);
+        assert_eq!(json(&first), json(&second));
+        assert_eq!(events, again);
+    }
+
+    #[test]
+    fn step_matches_turning_and_ticking() {
+        let mut game = Game::with_seed(1, 9);
+        let (stepped, events) = game.step(Some(Direction::Up)).unwrap();
+        game.change_direction(0, Direction::Up);
+        assert_eq!(game.tick().unwrap(), events);
+        assert_eq!(json(&game), json(&stepped));
+        assert_eq!(stepped.snakes()[0].body().head(), &Vector::new(5, 4));
+    }
+
+    #[test]
+    fn step_reports_the_crash_without_ending_the_game() {
+        let mut game = Game::with_seed(1, 1);
+        game.set_wrap(false, false);
+        lay(&mut game, 0, &[(1, 5)], Direction::Right, Direction::Right);
+        let over = game.step(Some(Direction::Left)).err().unwrap();
+        assert_eq!(over.reason, GameOverReason::HitWall);
+        assert!(game.step(None).is_ok());
+        assert!(game.tick().is_ok());
+    }
+
+    #[test]
+    fn stepping_with_an_rng_leaves_the_game_as_it_was() {
+        let mut game = Game::with_seed(1, 9);
+        game.set_food_count(3);
+        game.tick().unwrap();
+        let before = json(&game);
+
+        let (first, events) = step(&game, Some(Direction::Down), &mut StdRng::seed_from_u64(7));
+        let (second, again) = step(&game, Some(Direction::Down), &mut StdRng::seed_from_u64(7));
+        assert_eq!(json(&game), before);
+        assert_eq!(json(&first), json(&second));
+        assert_eq!(events, again);
+        assert_eq!(first.snakes()[0].body().head(), &Vector::new(6, 6));
+    }
+
+    #[test]
+    fn stepping_with_different_rngs_spawns_food_in_different_places() {
+        let game = food_ahead();
+        let spawned: HashSet<Vector> = (0..8)
+            .map(|seed| {
+                let (next, _) = step(&game, None, &mut StdRng::seed_from_u64(seed));
+                assert_eq!(next.snakes()[0].body().len(), 2);
+                next.food()[0].at.clone()
+            })
+            .collect();
+        assert!(spawned.len() > 1, "always spawned at {:?}", spawned);
+    }
+
+    #[test]
+    fn stepping_with_an_rng_into_a_wall_comes_back_over() {
+        let mut game = Game::with_seed(1, 1);
+        game.set_wrap(false, false);
+        lay(&mut game, 0, &[(1, 5)], Direction::Right, Direction::Right);
+        let (next, events) = step(&game, Some(Direction::Left), &mut StdRng::seed_from_u64(1));
+        assert!(events.is_empty());
+        assert!(matches!(
+            next.state(),
+            GameState::Over(GameOver {
+                reason: GameOverReason::HitWall,
+                ..
+            })
+        ));
+        assert_eq!(game.state(), GameState::Playing);
+    }
+
+    #[test]
+    fn boards_too_small_to_play_on_are_rejected() {
+        for (width, height) in [(4, 10), (20, 4), (1, 1), (0, 0)] {
+            let err = Game::with_dimensions(1, 1, Dimensions::new(width, height))
+                .err()
+                .unwrap()
+                .to_string();
+            assert!(err.contains("has to be at least 5x5"), "{}", err);
+        }
+        let backwards = Dimensions {
+            x: (20, 1),
+            y: (1, 10),
+        };
+        assert!(Game::with_dimensions(1, 1, backwards).is_err());
+    }
+
+    #[test]
+    fn the_smallest_board_spawns_food_and_obstacles() {
+        for seed in 0..50 {
+            for players in 1..=2 {
+                let mut game = Game::with_dimensions(players, seed, Dimensions::new(5, 5)).unwrap();
+                game.place_obstacles(3);
+                assert_eq!(game.obstacles().len(), 3);
+                let game = game.with_food();
+                assert_eq!(game.food().len(), 1);
+                let at = &game.food()[0].at;
+                assert!((1..=5).contains(&at.0) && (1..=5).contains(&at.1));
+            }
+        }
+    }
+
+    /// Player one's score after going right from `at` with `score` points
+    /// and a wrap penalty of `penalty` points.
+    fn score_after_wrapping(at: (u16, u16), score: u32, penalty: u32) -> u32 {
+        let mut game = Game::with_seed(1, 1);
+        game.set_wrap_penalty(penalty);
+        lay(&mut game, 0, &[at], Direction::Right, Direction::Right);
+        game.snakes[0].score = score;
+        game.tick().unwrap();
+        assert_eq!(game.snakes()[0].body().len(), 1, "ate something");
+        game.snakes()[0].score()
+    }
+
+    #[test]
+    fn wrapping_costs_the_penalty() {
+        assert_eq!(score_after_wrapping((20, 5), 5, 2), 3);
+        assert_eq!(score_after_wrapping((20, 5), 5, 0), 5);
+        // A move that doesn't wrap costs nothing.
+        assert_eq!(score_after_wrapping((10, 5), 5, 2), 5);
+    }
+
+    #[test]
+    fn the_wrap_penalty_stops_at_zero() {
+        assert_eq!(score_after_wrapping((20, 5), 1, 2), 0);
+        assert_eq!(score_after_wrapping((20, 5), 0, 2), 0);
+    }
+
+    /// Player one five segments long along row 5, going hungry every
+    /// `interval` ticks, with food at `food` and none spawning.
+    fn hungry(interval: u64, food: (u16, u16)) -> Game {
+        let mut game = Game::with_seed(1, 1);
+        game.set_food_delay(1000);
+        game.food.push(Food {
+            at: Vector::new(food.0, food.1),
+            kind: FoodKind::Normal,
+        });
+        lay(
+            &mut game,
+            0,
+            &[(5, 5), (4, 5), (3, 5), (2, 5), (1, 5)],
+            Direction::Right,
+            Direction::Right,
+        );
+        game.set_hunger_interval(interval);
+        game
+    }
+
+    #[test]
+    fn hunger_costs_a_segment_every_interval() {
+        let mut game = hungry(3, (1, 1));
+        let mut lengths = Vec::new();
+        for _ in 0..6 {
+            game.tick().unwrap();
+            lengths.push(game.snakes()[0].body().len());
+        }
+        assert_eq!(lengths, [5, 5, 4, 4, 4, 3]);
+        assert_eq!(game.ticks_until_hungry(), Some(3));
+    }
+
+    #[test]
+    fn eating_resets_hunger() {
+        let mut game = hungry(3, (7, 5));
+        game.tick().unwrap();
+        assert_eq!(game.ticks_until_hungry(), Some(2));
+        game.tick().unwrap();
+        assert_eq!(game.ticks_until_hungry(), Some(3));
+        let mut lengths = Vec::new();
+        for _ in 0..3 {
+            game.tick().unwrap();
+            lengths.push(game.snakes()[0].body().len());
+        }
+        // One more from eating, then a segment lost three ticks after.
+        assert_eq!(lengths, [6, 6, 5]);
+    }
+
+    #[test]
+    fn going_hungry_at_the_floor_starves() {
+        let mut game = hungry(1, (1, 1));
+        game.set_min_length(3);
+        game.tick().unwrap();
+        game.tick().unwrap();
+        assert_eq!(game.snakes()[0].body().len(), 3);
+        assert_eq!(reason(&mut game), GameOverReason::Starved);
+    }
+
+    #[test]
+    fn turns_count_the_ticks_the_snake_changed_heading_on() {
+        let mut game = Game::with_seed(1, 1);
+        game.set_food_delay(1000);
+        lay(
+            &mut game,
+            0,
+            &[(5, 5), (4, 5), (3, 5)],
+            Direction::Right,
+            Direction::Right,
+        );
+        let mut counted = Vec::new();
+        let script = [
+            &[][..],
+            &[Direction::Down],
+            &[],
+            &[Direction::Right, Direction::Up],
+            &[],
+            // Already heading up, and back down would be reversing.
+            &[Direction::Up, Direction::Down],
+            &[],
+        ];
+        for turns in script {
+            for &direction in turns {
+                game.apply_direction(0, direction);
+            }
+            game.tick().unwrap();
+            counted.push(game.snakes()[0].turns());
+        }
+        assert_eq!(counted, [0, 1, 1, 2, 3, 3, 3]);
+    }
+
+    #[test]
+    fn bouncing_off_a_wall_isnt_a_turn() {
+        let mut game = Game::with_seed(1, 1);
+        game.set_wrap(false, false);
+        game.set_bounce(true);
+        lay(&mut game, 0, &[(20, 5)], Direction::Right, Direction::Right);
+        game.tick().unwrap();
+        assert_eq!(game.snakes()[0].body().head(), &Vector::new(19, 5));
+        assert_eq!(game.snakes()[0].turns(), 0);
+    }
+
+    /// Pinned to what ChaCha12 gives seed 42, so that a change to the RNG,
+    /// or to the order it's drawn from, shows up here before it breaks old
+    /// replays.
+    #[test]
+    fn a_seed_always_lays_the_board_out_the_same() {
+        let mut game = Game::with_seed(1, 42);
+        game.place_obstacles(3);
+        game.set_food_count(5);
+        let game = game.with_food();
+        let mut obstacles: Vec<_> = game.obstacles().iter().map(|at| (at.0, at.1)).collect();
+        obstacles.sort();
+        let food: Vec<_> = game
+            .food()
+            .iter()
+            .map(|food| ((food.at.0, food.at.1), food.kind))
+            .collect();
+        assert_eq!(obstacles, [(3, 6), (5, 6), (18, 7)]);
+        assert_eq!(
+            food,
+            [
+                ((20, 4), FoodKind::Normal),
+                ((10, 9), FoodKind::Normal),
+                ((1, 1), FoodKind::Rotten),
+                ((19, 5), FoodKind::Normal),
+                ((8, 2), FoodKind::Normal),
+            ]
+        );
+    }
+
+    /// Player one three segments long along row 5, heading right.
+    fn heading_right() -> Game {
+        let mut game = Game::with_seed(1, 1);
+        lay(
+            &mut game,
+            0,
+            &[(5, 5), (4, 5), (3, 5)],
+            Direction::Right,
+            Direction::Right,
+        );
+        game
+    }
+
+    #[test]
+    fn applied_turns_are_queued_without_moving_the_snake() {
+        let mut game = heading_right();
+        assert!(game.apply_direction(0, Direction::Up));
+        assert!(game.apply_direction(0, Direction::Left));
+        assert_eq!(
+            Vec::from(game.snakes[0].queued.clone()),
+            [Direction::Up, Direction::Left]
+        );
+        assert_eq!(game.snakes()[0].body().head(), &Vector::new(5, 5));
+        assert_eq!(game.snakes[0].heading, Direction::Right);
+        assert!(!game.apply_direction(1, Direction::Up));
+    }
+
+    #[test]
+    fn turns_back_the_way_the_snake_will_be_going_are_refused() {
+        let mut game = heading_right();
+        assert!(!game.apply_direction(0, Direction::Left));
+        assert!(game.apply_direction(0, Direction::Down));
+        // Checked against the turn queued before it, not the heading.
+        assert!(!game.apply_direction(0, Direction::Up));
+        assert_eq!(Vec::from(game.snakes[0].queued.clone()), [Direction::Down]);
+    }
+
+    #[test]
+    fn the_queue_holds_only_so_many_turns() {
+        let mut game = heading_right();
+        assert!(game.apply_direction(0, Direction::Up));
+        assert!(game.apply_direction(0, Direction::Left));
+        assert!(!game.apply_direction(0, Direction::Down));
+        // Carrying on the way it will be going needs no room.
+        assert!(game.apply_direction(0, Direction::Left));
+        assert_eq!(game.snakes[0].queued.len(), QUEUED_TURNS);
+
+        // Each tick takes one, making room for another.
+        game.tick().unwrap();
+        assert_eq!(game.snakes()[0].body().head(), &Vector::new(5, 4));
+        assert!(game.apply_direction(0, Direction::Down));
+        assert_eq!(
+            Vec::from(game.snakes[0].queued.clone()),
+            [Direction::Left, Direction::Down]
+        );
+    }
+
+    #[test]
+    fn respawned_food_never_lands_on_anything() {
+        for seed in 0..10 {
+            let mut game = Game::with_seed(1, seed);
+            game.set_food_count(8);
+            game.place_obstacles(10);
+            for _ in 0..150 {
+                if let Some(direction) = crate::ai::next_move(&game) {
+                    game.change_direction(0, direction);
+                }
+                if game.tick().is_err() {
+                    break;
+                }
+                let food: HashSet<_> = game.food().iter().map(|food| &food.at).collect();
+                assert_eq!(food.len(), game.food().len(), "seed {}", seed);
+                assert!(food
+                    .iter()
+                    .all(|at| !game.obstacles().contains(at)
+                        && !game.snakes()[0].body().contains(at)));
+            }
+        }
+    }
+
+    #[test]
+    fn food_fills_only_what_it_can_reach() {
+        let mut game = split_board(false);
+        game.set_food_count(100);
+        let game = game.with_food();
+        // The head's half of the board, less the head.
+        assert_eq!(game.food().len(), 9);
+        assert!(game.food().iter().all(|food| food.at.0 > 3));
+
+        let mut game = Game::with_dimensions(1, 1, Dimensions::new(5, 5)).unwrap();
+        game.set_food_count(100);
+        assert_eq!(game.with_food().food().len(), 24);
+    }
+
+    /// Where a lone head at `at` heading `direction` on a walled, bouncing
+    /// board is after a tick, and which way it's heading.
+    fn bounce_from(at: (u16, u16), direction: Direction) -> (Vector, Direction) {
+        let mut game = Game::with_seed(1, 1);
+        game.set_wrap(false, false);
+        game.set_bounce(true);
+        game.allow_diagonals(true);
+        lay(&mut game, 0, &[at], direction, direction);
+        game.tick().unwrap();
+        let snake = &game.snakes()[0];
+        (snake.body().head().clone(), snake.heading)
+    }
+
+    #[test]
+    fn bouncing_off_each_wall_turns_the_snake_back() {
+        let bounces = [
+            ((20, 5), Direction::Right, (19, 5), Direction::Left),
+            ((1, 5), Direction::Left, (2, 5), Direction::Right),
+            ((5, 1), Direction::Up, (5, 2), Direction::Down),
+            ((5, 10), Direction::Down, (5, 9), Direction::Up),
+        ];
+        for (at, heading, (x, y), back) in bounces {
+            assert_eq!(bounce_from(at, heading), (Vector::new(x, y), back));
+        }
+    }
+
+    #[test]
+    fn bouncing_flips_only_the_axes_that_hit_a_wall() {
+        assert_eq!(
+            bounce_from((20, 5), Direction::UpRight),
+            (Vector::new(19, 4), Direction::UpLeft)
+        );
+        assert_eq!(
+            bounce_from((20, 10), Direction::DownRight),
+            (Vector::new(19, 9), Direction::UpLeft)
+        );
+    }
+
+    #[test]
+    fn edges_that_wrap_dont_bounce() {
+        let mut game = Game::with_seed(1, 1);
+        game.set_wrap(false, true);
+        game.set_bounce(true);
+        lay(&mut game, 0, &[(5, 1)], Direction::Up, Direction::Up);
+        game.tick().unwrap();
+        assert_eq!(game.snakes()[0].body().head(), &Vector::new(5, 10));
+    }
+
+    #[test]
+    fn bouncing_back_into_the_body_is_biting_it() {
+        let mut game = Game::with_seed(1, 1);
+        game.set_wrap(false, false);
+        game.set_bounce(true);
+        lay(
+            &mut game,
+            0,
+            &[(20, 5), (19, 5), (19, 4)],
+            Direction::Right,
+            Direction::Right,
+        );
+        assert_eq!(reason(&mut game), GameOverReason::HitSelf);
+    }
+
+    mod properties {
+        use super::*;
+        use proptest::prelude::*;
+
+        fn direction() -> impl Strategy<Value = Direction> {
+            prop_oneof![
+                Just(Direction::Left),
+                Just(Direction::Up),
+                Just(Direction::Right),
+                Just(Direction::Down),
+            ]
+        }
+
+        /// Up to a few hundred ticks, each with a key pressed or not.
+        fn inputs() -> impl Strategy<Value = Vec<Option<Direction>>> {
+            prop::collection::vec(prop::option::of(direction()), 0..300)
+        }
+
+        fn game(seed: u64, obstacles: u16, food: u32, walls: bool) -> Game {
+            let mut game = Game::with_seed(1, seed);
+            game.set_food_table(
+                FoodTable::new(vec![
+                    (FoodKind::Normal, 6.0),
+                    (FoodKind::Rotten, 2.0),
+                    (FoodKind::Golden, 2.0),
+                    (FoodKind::Magnet, 1.0),
+                    (FoodKind::Pickaxe, 1.0),
+                    (FoodKind::Speed, 1.0),
+                ])
+                .unwrap(),
+            );
+            game.place_obstacles(obstacles);
+            game.set_food_count(food);
+            game.set_wrap(!walls, !walls);
+            game
+        }
+
+        /// How long the snake is, counting the segments it has still to grow.
+        fn length(game: &Game) -> usize {
+            let snake = &game.snakes()[0];
+            snake.body().len() + snake.pending_growth as usize
+        }
+
+        proptest! {
+            #[test]
+            fn invariants_hold_every_tick(
+                seed in any::<u64>(),
+                obstacles in 0u16..20,
+                food in 1u32..5,
+                walls in any::<bool>(),
+                min_length in 1u32..4,
+                inputs in inputs(),
+            ) {
+                let mut game = game(seed, obstacles, food, walls);
+                game.set_min_length(min_length);
+                let dimensions = game.dimensions().clone();
+
+                for input in inputs {
+                    let Ok((next, events)) = game.step(input) else {
+                        break;
+                    };
+
+                    let body = next.snakes()[0].body();
+                    let head = body.head();
+                    prop_assert!(
+                        (dimensions.x.0..=dimensions.x.1).contains(&head.0)
+                            && (dimensions.y.0..=dimensions.y.1).contains(&head.1),
+                        "head off the board at {:?}",
+                        head
+                    );
+
+                    let cells: HashSet<&Vector> = body.iter().collect();
+                    prop_assert_eq!(cells.len(), body.len(), "body overlaps itself");
+
+                    let grown: i32 = events
+                        .iter()
+                        .map(|event| match event {
+                            GameEvent::AteFood { kind, .. } => kind.growth(),
+                            _ => 0,
+                        })
+                        .sum();
+                    // Rotten food shrinks the snake no further than the
+                    // floor, and one that's still shorter than the floor
+                    // isn't cut down at all.
+                    let floor = min_length.min(game.snakes()[0].body().len() as u32) as i32;
+                    prop_assert_eq!(
+                        length(&next) as i32,
+                        (length(&game) as i32 + grown).max(floor),
+                        "length changed other than by eating"
+                    );
+
+                    for piece in next.food() {
+                        prop_assert!(!body.contains(&piece.at), "food on the snake at {:?}", piece.at);
+                        prop_assert!(
+                            !next.obstacles().contains(&piece.at),
+                            "food on an obstacle at {:?}",
+                            piece.at
+                        );
+                    }
+
+                    game = next;
+                }
+            }
+
+            #[test]
+            fn stepping_with_an_rng_moves_the_snakes_as_a_tick_does(
+                seed in any::<u64>(),
+                draws in any::<u64>(),
+                obstacles in 0u16..20,
+                walls in any::<bool>(),
+                inputs in inputs(),
+            ) {
+                let mut game = game(seed, obstacles, 2, walls);
+                let mut rng = StdRng::seed_from_u64(draws);
+
+                for input in inputs {
+                    let (ticks, before) = (game.ticks(), game.snakes()[0].body().clone());
+                    let (next, events) = step(&game, input, &mut rng);
+                    prop_assert_eq!(game.ticks(), ticks, "stepping changed the game");
+                    prop_assert_eq!(game.snakes()[0].body(), &before, "stepping changed the game");
+
+                    // Only where food spawns should come down to the rng.
+                    match game.step(input) {
+                        Ok((ticked, expected)) => {
+                            prop_assert_eq!(next.state(), ticked.state());
+                            prop_assert_eq!(next.snakes()[0].body(), ticked.snakes()[0].body());
+                            let unspawned = |events: &[GameEvent]| -> Vec<GameEvent> {
+                                events
+                                    .iter()
+                                    .filter(|event| !matches!(event, GameEvent::FoodSpawned(_)))
+                                    .cloned()
+                                    .collect()
+                            };
+                            prop_assert_eq!(unspawned(&events), unspawned(&expected));
+                        }
+                        Err(over) => {
+                            prop_assert_eq!(next.state(), GameState::Over(over));
+                            prop_assert!(events.is_empty());
+                            break;
+                        }
+                    }
+
+                    let body = next.snakes()[0].body();
+                    for piece in next.food() {
+                        prop_assert!(!body.contains(&piece.at), "food on the snake at {:?}", piece.at);
+                        prop_assert!(
+                            !next.obstacles().contains(&piece.at),
+                            "food on an obstacle at {:?}",
+                            piece.at
+                        );
+                    }
+
+                    game = next;
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn with_food_stocks_an_unplayed_board() {
+        let mut game = Game::with_seed(1, 4);
+        game.set_food_count(3);
+        assert!(game.food().is_empty());
+
+        let stocked = game.with_food();
+        assert_eq!(stocked.food().len(), 3);
+        assert!(stocked
+            .food()
+            .iter()
+            .all(|food| !stocked.snakes()[0].body().contains(&food.at)));
+        assert!(game.food().is_empty());
+    }
+
+    #[test]
+    fn food_lands_on_the_last_row_and_column() {
+        let mut game = Game::with_dimensions(1, 1, Dimensions::new(6, 6)).unwrap();
+        let open = [Vector::new(5, 5), Vector::new(6, 5), Vector::new(6, 6)];
+        let layout = (1..=6)
+            .flat_map(|y| (1..=6).map(move |x| Vector::new(x, y)))
+            .filter(|cell| !open.contains(cell))
+            .collect();
+        game.set_layout(layout).unwrap();
+        game.set_food_count(2);
+
+        let stocked = game.with_food();
+        let mut food: Vec<Vector> = stocked.food().iter().map(|food| food.at.clone()).collect();
+        food.sort_by_key(|at| at.1);
+        assert_eq!(food, [Vector::new(6, 5), Vector::new(6, 6)]);
+    }
+
+    #[test]
+    fn food_never_lands_on_a_snake_or_other_food_on_a_crowded_board() {
+        for seed in 0..50 {
+            let mut game = Game::with_dimensions(1, seed, Dimensions::new(5, 5)).unwrap();
+            game.set_food_count(24);
+            let stocked = game.with_food();
+            let mut cells: Vec<&Vector> = stocked.food().iter().map(|food| &food.at).collect();
+            assert_eq!(cells.len(), 24);
+            cells.extend(stocked.snakes()[0].body().iter());
+            let unique: HashSet<&Vector> = cells.iter().copied().collect();
+            assert_eq!(unique.len(), 25);
+        }
+    }
+
+    #[test]
+    fn state_follows_the_game_to_its_end() {
+        let mut game = Game::with_seed(1, 1);
+        game.set_time_limit(1);
+        game.allow_undos(1);
+        assert_eq!(game.state(), GameState::Playing);
+
+        game.tick().unwrap();
+        let over = game.tick().unwrap_err();
+        assert_eq!(over.reason, GameOverReason::TimeUp);
+        assert_eq!(game.state(), GameState::Over(over));
+        assert_eq!(game.tick().unwrap_err(), over);
+
+        assert!(game.undo());
+        assert_eq!(game.state(), GameState::Playing);
+    }
+
+    #[test]
+    fn state_is_cleared_once_the_scripted_food_is_eaten() {
+        let mut game = Game::with_seed(1, 1);
+        game.set_food_source(FoodSource::Scripted(vec![Vector::new(8, 5)]))
+            .unwrap();
+        game.tick().unwrap();
+        game.tick().unwrap();
+        assert_eq!(game.state(), GameState::Playing);
+        game.tick().unwrap();
+        assert_eq!(game.state(), GameState::Cleared);
+    }
+}
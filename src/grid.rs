@@ -0,0 +1,109 @@
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+use crate::{Dimensions, FoodKind, TerrainKind, Vector};
+
+/// What fills a cell of the board, going by whatever is on top.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CellKind {
+    Empty,
+    /// Part of the board that has closed in.
+    Wall,
+    Obstacle,
+    /// Fast or slow floor with nothing on it.
+    Terrain(TerrainKind),
+    Food(FoodKind),
+    /// The head of this player's snake.
+    Head(usize),
+    /// Any other segment of this player's snake.
+    Body(usize),
+}
+
+impl CellKind {
+    /// A character standing for the cell in plain text. Obstacles and
+    /// terrain are drawn as in a level file, and everything other than those
+    /// reads back in as plain floor.
+    pub fn ascii(&self) -> char {
+        match self {
+            CellKind::Empty => '.',
+            CellKind::Wall => '=',
+            CellKind::Obstacle => '#',
+            CellKind::Terrain(TerrainKind::Fast) => '>',
+            CellKind::Terrain(TerrainKind::Slow) => '<',
+            CellKind::Food(FoodKind::Normal) => '*',
+            CellKind::Food(FoodKind::Golden) => '$',
+            CellKind::Food(FoodKind::Rotten) => '%',
+            CellKind::Food(FoodKind::Magnet) => 'M',
+            CellKind::Food(FoodKind::Pickaxe) => 'P',
+            CellKind::Food(FoodKind::Speed) => '!',
+            CellKind::Head(0) => '@',
+            CellKind::Head(_) => '&',
+            CellKind::Body(0) => 'o',
+            CellKind::Body(_) => '+',
+        }
+    }
+}
+
+/// Every cell of the board at one moment, a row at a time from the top left.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Grid {
+    dimensions: Dimensions,
+    cells: Vec<CellKind>,
+}
+
+impl Grid {
+    pub(crate) fn new(dimensions: &Dimensions) -> Grid {
+        let width = (dimensions.x.1 - dimensions.x.0 + 1) as usize;
+        let height = (dimensions.y.1 - dimensions.y.0 + 1) as usize;
+
+        Grid {
+            dimensions: dimensions.clone(),
+            cells: vec![CellKind::Empty; width * height],
+        }
+    }
+
+    pub fn width(&self) -> usize {
+        (self.dimensions.x.1 - self.dimensions.x.0 + 1) as usize
+    }
+
+    pub fn height(&self) -> usize {
+        (self.dimensions.y.1 - self.dimensions.y.0 + 1) as usize
+    }
+
+    fn index(&self, at: &Vector) -> Option<usize> {
+        let (x, y) = (self.dimensions.x, self.dimensions.y);
+        if !(x.0..=x.1).contains(&at.0) || !(y.0..=y.1).contains(&at.1) {
+            return None;
+        }
+
+        Some((at.1 - y.0) as usize * self.width() + (at.0 - x.0) as usize)
+    }
+
+    /// `None` for cells off the board.
+    pub fn get(&self, at: &Vector) -> Option<CellKind> {
+        self.index(at).map(|i| self.cells[i])
+    }
+
+    pub(crate) fn set(&mut self, at: &Vector, kind: CellKind) {
+        if let Some(i) = self.index(at) {
+            self.cells[i] = kind;
+        }
+    }
+
+    pub fn rows(&self) -> impl Iterator<Item = &[CellKind]> {
+        self.cells.chunks(self.width())
+    }
+}
+
+/// The board as text, a line per row, with each cell as its `ascii`
+/// character.
+impl fmt::Display for Grid {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for row in self.rows() {
+            let line: String = row.iter().map(CellKind::ascii).collect();
+            writeln!(f, "{}", line)?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,116 @@
+use anyhow::{anyhow, Result};
+use std::{
+    collections::{HashMap, HashSet},
+    io::BufRead,
+};
+
+use crate::{Dimensions, TerrainKind, Vector};
+
+/// What a level puts on the board before any obstacles are scattered.
+#[derive(Debug, Clone, Default)]
+pub struct Layout {
+    pub obstacles: HashSet<Vector>,
+    pub terrain: HashMap<Vector, TerrainKind>,
+}
+
+/// Reads a level drawn as text, a line per row from the top and a character
+/// per cell from the left: `#` is an obstacle, `>` fast floor, `<` slow floor
+/// and anything else plain floor. Rows and columns left off the end are
+/// plain too.
+pub fn read_layout(reader: impl BufRead, dimensions: &Dimensions) -> Result<Layout> {
+    let (x, y) = (dimensions.x, dimensions.y);
+    let width = (x.1 - x.0 + 1) as usize;
+    let height = (y.1 - y.0 + 1) as usize;
+
+    let mut layout = Layout::default();
+    let mut rows = 0;
+
+    for (row, line) in reader.lines().enumerate() {
+        let line = line?;
+        rows += 1;
+        if row >= height {
+            if line.trim().is_empty() {
+                continue;
+            }
+            return Err(anyhow!(
+                "the level is taller than the board's {} rows",
+                height
+            ));
+        }
+
+        let line = line.trim_end();
+        if line.chars().count() > width {
+            return Err(anyhow!(
+                "row {} of the level is wider than the board's {} columns",
+                row + 1,
+                width
+            ));
+        }
+
+        for (column, c) in line.chars().enumerate() {
+            let at = Vector::new(x.0 + column as u16, y.0 + row as u16);
+            match c {
+                '#' => {
+                    layout.obstacles.insert(at);
+                }
+                '>' => {
+                    layout.terrain.insert(at, TerrainKind::Fast);
+                }
+                '<' => {
+                    layout.terrain.insert(at, TerrainKind::Slow);
+                }
+                _ => (),
+            }
+        }
+    }
+
+    if rows == 0 {
+        return Err(anyhow!("the level is empty"));
+    }
+
+    Ok(layout)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn read(text: &str) -> Result<Layout> {
+        let dimensions = Dimensions {
+            x: (1, 5),
+            y: (1, 5),
+        };
+        read_layout(text.as_bytes(), &dimensions)
+    }
+
+    #[test]
+    fn reads_obstacles_and_terrain_from_a_grid() {
+        let layout = read("#...\n.>.<\n\n....#\n").unwrap();
+        assert_eq!(
+            layout.obstacles,
+            HashSet::from([Vector::new(1, 1), Vector::new(5, 4)])
+        );
+        assert_eq!(
+            layout.terrain,
+            HashMap::from([
+                (Vector::new(2, 2), TerrainKind::Fast),
+                (Vector::new(4, 2), TerrainKind::Slow),
+            ])
+        );
+    }
+
+    #[test]
+    fn empty_input_is_an_error() {
+        let err = read("").unwrap_err();
+        assert_eq!(err.to_string(), "the level is empty");
+    }
+
+    #[test]
+    fn a_level_bigger_than_the_board_is_an_error() {
+        let err = read(".\n.\n.\n.\n.\n#\n").unwrap_err();
+        assert!(err.to_string().contains("taller"), "{}", err);
+        let err = read("......\n").unwrap_err();
+        assert!(err.to_string().contains("row 1"), "{}", err);
+        assert!(read(".....   \n.\n.\n.\n.\n\n").is_ok());
+    }
+}
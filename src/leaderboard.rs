@@ -0,0 +1,162 @@
+//! The saved scores as a leaderboard, ordered by score, length or date and
+//! narrowed down to one mode or shown for all of them.
+
+use anyhow::Result;
+use crossterm::event::KeyCode;
+use tui::{
+    layout::{Alignment, Rect},
+    style::{Color, Style},
+    widgets::{Block, Borders, Paragraph},
+};
+
+use crate::{
+    best::{self, ScoreEntry, ScoreSort},
+    centered_rect,
+    config::Config,
+    fit_hints, handle_event, is_quit,
+    menu::{Mode, MODES},
+    AppState, EventSource, Input, Term,
+};
+
+/// The mode after `mode` to narrow the scores down to, going through each of
+/// `MODES` and then back round to all of them.
+fn next_mode(mode: Option<Mode>) -> Option<Mode> {
+    match mode {
+        None => MODES.first().copied(),
+        Some(mode) => MODES.iter().skip_while(|m| **m != mode).nth(1).copied(),
+    }
+}
+
+/// The day `at`, in seconds since the Unix epoch, falls on, as
+/// year-month-day.
+fn date(at: u64) -> String {
+    // Howard Hinnant's days-to-civil, counting from the 1st of March in the
+    // year 0 so leap days fall at the end of the year.
+    let days = (at / 86_400) as i64 + 719_468;
+    let era = days.div_euclid(146_097);
+    let day_of_era = days.rem_euclid(146_097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let march_month = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * march_month + 2) / 5 + 1;
+    let month = if march_month < 10 {
+        march_month + 3
+    } else {
+        march_month - 9
+    };
+    let year = era * 400 + year_of_era + i64::from(month <= 2);
+    format!("{:04}-{:02}-{:02}", year, month, day)
+}
+
+fn row(rank: &str, score: &str, length: &str, mode: &str, date: &str) -> String {
+    format!(
+        " {:>3}  {:>5}  {:>6}  {:<7}  {:<10} ",
+        rank, score, length, mode, date
+    )
+}
+
+fn entry_row(rank: usize, entry: &ScoreEntry) -> String {
+    row(
+        &rank.to_string(),
+        &entry.score.to_string(),
+        &entry.length.to_string(),
+        entry.mode.name(),
+        &date(entry.at),
+    )
+}
+
+/// Shows the saved scores until a quit key is pressed, `s` changing what
+/// they're ordered by and `f` which mode they're from.
+pub fn run(terminal: &mut Term, events: &mut dyn EventSource, config: &Config) -> Result<()> {
+    let entries = best::load_scores();
+    let mut sort = ScoreSort::Score;
+    let mut mode: Option<Mode> = None;
+
+    loop {
+        let board = best::leaderboard(&entries, sort, mode);
+
+        terminal.draw(|f| {
+            let title = format!(
+                "scores by {}, {}",
+                sort.name(),
+                mode.map_or("every mode", |mode| mode.name())
+            );
+            let hints = u16::from(config.show_hints);
+            // Beneath the frame and the heading.
+            let room = f.size().height.saturating_sub(3 + hints) as usize;
+            let mut lines = vec![row("#", "score", "length", "mode", "date")];
+            if board.is_empty() {
+                lines.push(String::from(" no scores yet"));
+            }
+            lines.extend(
+                board
+                    .iter()
+                    .take(room)
+                    .enumerate()
+                    .map(|(i, entry)| entry_row(i + 1, entry)),
+            );
+
+            let width = lines
+                .iter()
+                .chain([&title])
+                .map(|line| line.chars().count())
+                .max()
+                .unwrap_or(0) as u16
+                + 2;
+            let height = lines.len() as u16 + 2;
+            let area = centered_rect(f.size(), width, height);
+            f.render_widget(
+                Paragraph::new(lines.join("\n"))
+                    .block(Block::default().title(title).borders(Borders::ALL)),
+                area,
+            );
+
+            if config.show_hints {
+                let below = area.bottom().min(f.size().bottom().saturating_sub(1));
+                let line = Rect::new(0, below, f.size().width, 1);
+                let hints = fit_hints(&AppState::Leaderboard.key_hints(), line.width);
+                f.render_widget(
+                    Paragraph::new(hints)
+                        .alignment(Alignment::Center)
+                        .style(Style::default().fg(Color::DarkGray)),
+                    line,
+                );
+            }
+        })?;
+
+        match handle_event(events.read()?) {
+            Input::Key(key) if is_quit(&key) => return Ok(()),
+            Input::Key(key) => match key.code {
+                KeyCode::Char('s') => sort = sort.next(),
+                KeyCode::Char('f') => mode = next_mode(mode),
+                _ => (),
+            },
+            Input::Resize | Input::FocusLost | Input::Ignored => (),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn dates_count_from_the_epoch() {
+        assert_eq!(date(0), "1970-01-01");
+        assert_eq!(date(951_782_400), "2000-02-29");
+        assert_eq!(date(1_772_323_199), "2026-02-28");
+    }
+
+    #[test]
+    fn modes_cycle_back_round_to_all() {
+        let mut mode = None;
+        let mut seen = Vec::new();
+        for _ in 0..=MODES.len() {
+            mode = next_mode(mode);
+            seen.push(mode);
+        }
+        let expected: Vec<Option<Mode>> = MODES.iter().copied().map(Some).chain([None]).collect();
+        assert_eq!(seen, expected);
+    }
+}
@@ -0,0 +1,26 @@
+//! The snake game itself, free of any terminal handling so it can be driven
+//! by something other than the bundled front-end.
+
+pub mod ai;
+mod campaign;
+mod difficulty;
+mod food;
+mod game;
+mod grid;
+mod layout;
+mod puzzle;
+mod replay;
+mod terrain;
+
+pub use campaign::{Campaign, LevelDef};
+pub use difficulty::Difficulty;
+pub use food::{pick_food_kind, Food, FoodKind, FoodSource, FoodTable};
+pub use game::{
+    step, Body, Dimensions, Direction, Game, GameEvent, GameOver, GameOverReason, GameState, Snake,
+    Vector,
+};
+pub use grid::{CellKind, Grid};
+pub use layout::{read_layout, Layout};
+pub use puzzle::PuzzleDef;
+pub use replay::Replay;
+pub use terrain::TerrainKind;
@@ -1,234 +1,3860 @@
-use anyhow::{anyhow, Result};
-use std::{collections::LinkedList, io, time::Duration};
+use anyhow::{anyhow, Context, Result};
+use clap::{Parser, Subcommand};
+use std::{
+    collections::{HashSet, VecDeque},
+    env,
+    fs::{self, File, OpenOptions},
+    io::{self, BufReader, LineWriter, Write},
+    mem,
+    path::{Path, PathBuf},
+    sync::mpsc::Receiver,
+    time::{Duration, Instant},
+};
 
 use crossterm::{
-    event::{self, Event, KeyCode, KeyModifiers},
+    cursor::MoveTo,
+    event::{self, DisableFocusChange, EnableFocusChange, Event, KeyCode, KeyEvent, KeyModifiers},
     execute,
-    terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
+    terminal::{
+        self, disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen,
+    },
 };
+use log::warn;
 
-use rand::Rng;
+use config::{BodyStyle, BorderDanger, Config};
+use menu::Mode;
+use rsnake::{
+    ai, read_layout, Campaign, CellKind, Difficulty, Dimensions, Direction, FoodKind, FoodSource,
+    Game, GameEvent, GameOver, GameState, Grid, Layout, PuzzleDef, Snake, TerrainKind, Vector,
+};
+use sound::{Sound, Speaker};
+use theme::Theme;
 use tui::{
-    backend::CrosstermBackend,
-    layout::Rect,
-    style::Color,
-    widgets::{Block, Borders, Widget},
+    backend::{Backend, CrosstermBackend},
+    buffer::Buffer,
+    layout::{Alignment, Margin, Rect},
+    style::{Color, Style},
+    widgets::{Block, Borders, Clear, Paragraph, Widget},
     Terminal,
 };
 
-#[derive(Debug, Clone, PartialEq, Eq)]
-struct Vector(u16, u16);
+mod bench;
+mod best;
+mod config;
+mod leaderboard;
+mod menu;
+mod net;
+mod screenshot;
+mod solve;
+mod sound;
+mod theme;
+mod versus;
+mod watch;
+mod ws;
+
+/// A terminal drawn by writing escape codes to `W`. Everything but drawing a
+/// frame assumes it's stdout.
+type Term<W = io::Stdout> = Terminal<CrosstermBackend<W>>;
+
+const GHOST_COLOR: Color = Color::DarkGray;
+/// The border of a board with an edge that ends the game to run into.
+const WALLED_BORDER_COLOR: Color = Color::Red;
+
+/// Cells the tail has just left, from most to least recently, before they
+/// fade out completely.
+const TRAIL_COLORS: [Color; 3] = [Color::Indexed(34), Color::Indexed(28), Color::Indexed(22)];
+
+/// Alternated between ticks on the ring of the board that's about to close.
+const WARNING_COLORS: [Color; 2] = [Color::Red, Color::LightRed];
+
+/// The characters across and down the minimap, inside its border.
+const MINIMAP_SIZE: (u16, u16) = (10, 5);
+
+/// How many ticks pulsing food stays on each of its shades.
+const PULSE_TICKS: u64 = 3;
+
+/// How many ticks player one's head stays lit up after eating.
+const EAT_FLASH_TICKS: u64 = 2;
+
+/// How long the snake takes to fall apart with `death_animation`, however
+/// long it is.
+const DEATH_DURATION: Duration = Duration::from_secs(1);
+
+/// How long a `Notice` stays beneath the board.
+const NOTICE_DURATION: Duration = Duration::from_secs(2);
+
+/// How often player one's score is sampled for the sparkline shown when the
+/// game is over, and how many of the latest samples it shows.
+const SCORE_SAMPLE_EVERY: Duration = Duration::from_secs(1);
+const SCORE_SAMPLES: usize = 20;
+
+/// How long each tick of a local game lasts.
+const TICK: Duration = Duration::from_millis(100);
+
+/// The most ticks a game loop takes in a row without drawing when it's fallen
+/// behind, before giving up on the time lost.
+const MAX_CATCH_UP: u32 = 4;
+
+/// How often `--smooth` redraws between ticks.
+const FRAME: Duration = Duration::from_millis(16);
+
+/// The tick intervals shown as speed 10 and speed 1.
+const FASTEST_TICK: Duration = Duration::from_millis(40);
+const SLOWEST_TICK: Duration = Duration::from_millis(250);
+
+/// `--version` along with the commit and day it was built from.
+const LONG_VERSION: &str = concat!(
+    env!("CARGO_PKG_VERSION"),
+    " (",
+    env!("RSNAKE_GIT_HASH"),
+    ", built ",
+    env!("RSNAKE_BUILD_DATE"),
+    ")"
+);
+
+#[derive(Clone, Parser)]
+#[command(
+    version,
+    long_version = LONG_VERSION,
+    about = "Snake in the terminal",
+    args_conflicts_with_subcommands = true
+)]
+struct Cli {
+    #[command(subcommand)]
+    command: Option<Command>,
+
+    /// Deprecated: use `rsnake host --port PORT`
+    #[arg(long, value_name = "PORT", conflicts_with = "join", hide = true)]
+    host: Option<u16>,
+
+    /// Deprecated: use `rsnake join ADDR`
+    #[arg(long, value_name = "ADDR", conflicts_with = "spectate", hide = true)]
+    join: Option<String>,
+
+    /// Deprecated: use `rsnake spectate ADDR`
+    #[arg(long, value_name = "ADDR", conflicts_with = "host", hide = true)]
+    spectate: Option<String>,
+
+    /// Allow taking back up to this many moves per game with `u`
+    #[arg(long, value_name = "N", default_value_t = 0)]
+    undo: u32,
+
+    /// How many times the snake can crash and start over before the game ends
+    #[arg(long, value_name = "N", default_value_t = 1, value_parser = clap::value_parser!(u32).range(1..))]
+    lives: u32,
+
+    /// Play a two-player game at this terminal, player 1 with the arrow keys
+    /// and player 2 with WASD
+    #[arg(long, conflicts_with_all = ["host", "join", "spectate", "serve_ws", "campaign", "solve", "puzzle", "watch", "trainer", "print_board", "bench_scenario", "difficulty", "diagonal"])]
+    versus: bool,
+
+    /// Play through a series of levels, each cleared by reaching a target score
+    #[arg(long, conflicts_with_all = ["host", "join", "spectate"])]
+    campaign: bool,
+
+    /// Start from this seed, so food and obstacles land in the same places
+    /// every time
+    #[arg(long, value_name = "N")]
+    seed: Option<u64>,
+
+    /// Make every edge of the board a wall that ends the game, whatever
+    /// `wrap_x` and `wrap_y` say
+    #[arg(long, conflicts_with_all = ["host", "join", "spectate", "serve_ws", "puzzle", "watch"])]
+    walls: bool,
+
+    /// Speed the snake up as it grows: easy, normal or hard, over the config's
+    /// `difficulty`
+    #[arg(long, value_name = "NAME", conflicts_with_all = ["host", "join", "spectate", "serve_ws", "puzzle", "watch"])]
+    difficulty: Option<Difficulty>,
+
+    /// End the game after this many seconds, however it's going
+    #[arg(long, value_name = "SECS", value_parser = clap::value_parser!(u64).range(1..), conflicts_with_all = ["host", "join", "spectate", "serve_ws", "campaign", "puzzle", "watch", "trainer"])]
+    time_limit: Option<u64>,
+
+    /// How many cells across the board is, 20 if left out
+    #[arg(long, value_name = "CELLS", value_parser = clap::value_parser!(u16).range(5..=1000), conflicts_with_all = ["host", "join", "spectate", "campaign", "puzzle", "watch"])]
+    width: Option<u16>,
+
+    /// How many cells down the board is, 10 if left out
+    #[arg(long, value_name = "CELLS", value_parser = clap::value_parser!(u16).range(5..=1000), conflicts_with_all = ["host", "join", "spectate", "campaign", "puzzle", "watch"])]
+    height: Option<u16>,
+
+    /// How many milliseconds each tick lasts, 100 if left out
+    #[arg(long, value_name = "MS", value_parser = clap::value_parser!(u64).range(1..), conflicts_with_all = ["host", "join", "spectate", "campaign"])]
+    tick_ms: Option<u64>,
+
+    /// Let the computer play the seeded board without a terminal, printing
+    /// the score it reaches
+    #[arg(long, requires = "seed", conflicts_with_all = ["host", "join", "spectate", "campaign"])]
+    solve: bool,
+
+    /// Let the snake move diagonally too, with Home, PgUp, End and PgDn
+    #[arg(long, conflicts_with_all = ["host", "join", "spectate", "serve_ws"])]
+    diagonal: bool,
+
+    /// Lay out obstacles from this text file, `#` for each one, or from stdin
+    /// with `-`
+    #[arg(long, value_name = "PATH", conflicts_with_all = ["host", "join", "spectate", "serve_ws"])]
+    level: Option<PathBuf>,
+
+    /// Point out which way the nearest food is, beside the score
+    #[arg(long, conflicts_with_all = ["host", "join", "spectate", "serve_ws", "solve"])]
+    assist: bool,
+
+    /// Only show the board within this many cells of the snake's head
+    #[arg(long, value_name = "RADIUS", conflicts_with_all = ["host", "join", "spectate", "serve_ws", "solve"])]
+    fog: Option<u16>,
+
+    /// Debug aid: let the snake run through itself, to steer it into a
+    /// particular shape for a screenshot or bug report
+    #[arg(long, hide = true, conflicts_with_all = ["host", "join", "spectate", "serve_ws"])]
+    ghost: bool,
+
+    /// Play the puzzle in this TOML file, cleared by eating its food in as
+    /// few ticks as possible
+    #[arg(long, value_name = "PATH", conflicts_with_all = ["host", "join", "spectate", "serve_ws", "campaign", "solve", "level", "bench_scenario"])]
+    puzzle: Option<PathBuf>,
+
+    /// Drill tight turns on a course of food laid out in a zigzag, counting
+    /// the turns made before the snake dies
+    #[arg(long, conflicts_with_all = ["host", "join", "spectate", "serve_ws", "campaign", "solve", "level", "puzzle", "watch", "bench_scenario"])]
+    trainer: bool,
+
+    /// Redraw between ticks so the snake glides from cell to cell, for
+    /// terminals that keep up
+    #[arg(long, conflicts_with_all = ["host", "join", "spectate", "serve_ws", "solve"])]
+    smooth: bool,
+
+    /// Print the board a game would start on as text and exit, to check a
+    /// level or share a layout
+    #[arg(long, conflicts_with_all = ["host", "join", "spectate", "serve_ws", "campaign", "solve", "watch", "trainer", "bench_scenario"])]
+    print_board: bool,
+
+    /// Time the computer player through a fixed seeded scenario without a
+    /// terminal, printing the timings as JSON
+    #[arg(long, conflicts_with_all = ["host", "join", "spectate", "serve_ws", "campaign", "solve"])]
+    bench_scenario: bool,
+
+    /// Stop a `--solve` run after this many ticks
+    #[arg(long, value_name = "N", default_value_t = 10_000, requires = "solve")]
+    tick_limit: u64,
+
+    /// Save the replay of a `--solve` run to this file, in a compact binary
+    /// format if it ends in `.bin` and as JSON otherwise
+    #[arg(long, value_name = "PATH", requires = "solve")]
+    record: Option<PathBuf>,
+
+    /// Watch a run saved with `--record` back, in either format, pausing,
+    /// stepping and speeding it up as it goes
+    #[arg(long, value_name = "PATH", conflicts_with_all = ["host", "join", "spectate", "serve_ws", "campaign", "solve", "puzzle", "bench_scenario"])]
+    watch: Option<PathBuf>,
+
+    /// Show the saved scores, which can be ordered by score, length or date
+    /// and narrowed down to one mode
+    #[arg(long, conflicts_with_all = ["host", "join", "spectate", "serve_ws", "versus", "campaign", "solve", "puzzle", "watch", "trainer", "print_board", "bench_scenario"])]
+    scores: bool,
+
+    /// Run a headless game steered over WebSocket on this port
+    #[arg(long, value_name = "PORT", conflicts_with_all = ["host", "join", "spectate"])]
+    serve_ws: Option<u16>,
+
+    /// Draw over the terminal's main screen rather than a separate one, so the
+    /// last frame is left in the scrollback on exit
+    #[arg(long)]
+    no_alt_screen: bool,
+
+    /// Print the names of the themes the config's `theme` can be, one per
+    /// line, and exit
+    #[arg(long, conflicts_with = "list_modes")]
+    list_themes: bool,
+
+    /// Print the names of the modes the menu offers, one per line, and exit
+    #[arg(long)]
+    list_modes: bool,
+
+    /// Lay this profile from the config file over its other settings
+    #[arg(long, value_name = "NAME", global = true)]
+    profile: Option<String>,
+
+    /// Where to write logs when RUST_LOG is set, since the board owns stdout
+    #[arg(long, value_name = "PATH", default_value = "rsnake.log", global = true)]
+    log_file: PathBuf,
+}
+
+/// The two-player games over TCP, which take none of the single-player
+/// options.
+#[derive(Clone, Subcommand)]
+enum Command {
+    /// Host a two-player game, listening for the second player
+    Host {
+        /// The port to listen on
+        #[arg(long, default_value_t = 4000)]
+        port: u16,
+    },
+    /// Join a two-player game hosted at this address (e.g. 192.168.0.2:4000)
+    Join { addr: String },
+    /// Watch a two-player game hosted at this address without playing
+    Spectate { addr: String },
+}
+
+impl Cli {
+    /// Moves a subcommand's address or port into the flag it replaced, so
+    /// the rest of `main` only looks at one, and warns about any of those
+    /// flags used the old way.
+    fn adopt_command(&mut self) {
+        match self.command.take() {
+            Some(Command::Host { port }) => self.host = Some(port),
+            Some(Command::Join { addr }) => self.join = Some(addr),
+            Some(Command::Spectate { addr }) => self.spectate = Some(addr),
+            None => {
+                let deprecated = [
+                    (self.host.is_some(), "--host PORT", "host --port PORT"),
+                    (self.join.is_some(), "--join ADDR", "join ADDR"),
+                    (self.spectate.is_some(), "--spectate ADDR", "spectate ADDR"),
+                ];
+                for (_, flag, command) in deprecated.iter().filter(|(used, ..)| *used) {
+                    eprintln!(
+                        "warning: {} is deprecated, use `rsnake {}` instead",
+                        flag, command
+                    );
+                }
+            }
+        }
+    }
+
+    /// The board asked for, the default one along any side left out.
+    fn dimensions(&self) -> Dimensions {
+        let default = Dimensions::default();
+        Dimensions::new(
+            self.width.unwrap_or(default.x.1 - default.x.0 + 1),
+            self.height.unwrap_or(default.y.1 - default.y.0 + 1),
+        )
+    }
+
+    fn tick(&self) -> Duration {
+        self.tick_ms.map_or(TICK, Duration::from_millis)
+    }
 
-impl Vector {
-    fn new(x: u16, y: u16) -> Vector {
-        Vector(x, y)
+    /// The names `--list-themes` or `--list-modes` ask to be printed, if
+    /// either was given.
+    fn listed(&self) -> Option<Vec<&'static str>> {
+        match (self.list_themes, self.list_modes) {
+            (true, _) => Some(theme::available_themes()),
+            (_, true) => Some(menu::available_modes()),
+            _ => None,
+        }
     }
 }
 
-struct Dimensions {
-    x: (u16, u16),
-    y: (u16, u16),
+/// Playing time, which stands still while the game is paused or held after
+/// a crash. The timers kept between ticks, for notices, held keys and the
+/// snake falling apart, read it rather than the wall clock so a pause doesn't
+/// run them down.
+struct GameClock {
+    /// Where the wall-clock time comes from.
+    wall: fn() -> Instant,
+    started: Instant,
+    /// How long the clock has been stopped for, not counting a pause that's
+    /// still going on.
+    paused_for: Duration,
+    paused_at: Option<Instant>,
 }
 
-#[derive(PartialEq, Eq)]
-enum Direction {
-    Left,
-    Up,
-    Right,
-    Down,
+impl GameClock {
+    fn new() -> GameClock {
+        GameClock::with_wall(Instant::now)
+    }
+
+    fn with_wall(wall: fn() -> Instant) -> GameClock {
+        GameClock {
+            wall,
+            started: wall(),
+            paused_for: Duration::ZERO,
+            paused_at: None,
+        }
+    }
+
+    /// How much playing time has gone by since the clock started.
+    fn now(&self) -> Duration {
+        let wall = self.paused_at.unwrap_or_else(self.wall);
+        wall.saturating_duration_since(self.started)
+            .saturating_sub(self.paused_for)
+    }
+
+    /// Stops the clock, if it isn't already.
+    fn pause(&mut self) {
+        self.paused_at = self.paused_at.or_else(|| Some((self.wall)()));
+    }
+
+    /// Starts the clock again from where it was stopped.
+    fn resume(&mut self) {
+        if let Some(at) = self.paused_at.take() {
+            self.paused_for += (self.wall)().saturating_duration_since(at);
+        }
+    }
 }
 
-struct Cube {
-    x: u16,
-    y: u16,
+/// A short message shown beneath the board, for `NOTICE_DURATION` if it was
+/// put up with the time.
+struct Notice {
+    text: String,
+    /// The playing time it was put up at, if it goes away by itself.
+    shown_at: Option<Duration>,
 }
 
-impl Cube {
-    fn new(x: u16, y: u16) -> Cube {
-        Cube { x, y }
+impl Notice {
+    /// A notice that stays up as long as the screen showing it.
+    fn new(text: String) -> Notice {
+        Notice {
+            text,
+            shown_at: None,
+        }
+    }
+
+    /// A notice that goes away after `NOTICE_DURATION` of playing time.
+    fn timed(text: String, clock: &GameClock) -> Notice {
+        Notice {
+            text,
+            shown_at: Some(clock.now()),
+        }
+    }
+
+    fn expired(&self, clock: &GameClock) -> bool {
+        self.shown_at
+            .is_some_and(|at| clock.now().saturating_sub(at) >= NOTICE_DURATION)
     }
 }
 
-impl Widget for Cube {
-    fn render(self, area: Rect, buf: &mut tui::buffer::Buffer) {
-        if area.area() == 0 {
-            return;
+/// Fast floor is a deep blue and slow floor a muddy brown.
+fn terrain_color(kind: TerrainKind) -> Color {
+    match kind {
+        TerrainKind::Fast => Color::Indexed(24),
+        TerrainKind::Slow => Color::Indexed(94),
+    }
+}
+
+/// How much danger `metric` says player one is in, from 0 for none to 1,
+/// or `None` if it doesn't apply to this game.
+fn danger(metric: BorderDanger, game: &Game) -> Option<f64> {
+    let left = |remaining: u64, every: u64| 1.0 - remaining as f64 / every as f64;
+    match metric {
+        BorderDanger::None => None,
+        BorderDanger::Space => {
+            let board = game.dimensions();
+            let cells = (board.x.1 - board.x.0 + 1) as f64 * (board.y.1 - board.y.0 + 1) as f64;
+            Some(1.0 - game.reachable_cells() as f64 / cells)
         }
+        BorderDanger::Hunger => game
+            .ticks_until_hungry()
+            .map(|ticks| left(ticks, game.hunger_interval())),
+        BorderDanger::Shrink => game
+            .ticks_until_shrink()
+            .map(|ticks| left(ticks, game.shrink_interval())),
+    }
+}
 
-        buf.get_mut(self.x, self.y).set_bg(Color::Green);
-        buf.get_mut(self.x + 1, self.y).set_bg(Color::Green);
+/// Green below a third, yellow below two thirds and red from there up.
+fn danger_color(danger: f64) -> Color {
+    if danger < 1.0 / 3.0 {
+        Color::Green
+    } else if danger < 2.0 / 3.0 {
+        Color::Yellow
+    } else {
+        Color::Red
     }
 }
 
-struct Game {
-    body: LinkedList<Vector>,
-    direction: Direction,
-    dimensions: Dimensions,
-    block: Option<Vector>,
+/// What color to draw the border of `game` in, which is whatever
+/// `border_danger` warns about, or otherwise the terminal's own unless there's
+/// an edge that ends the game to run into.
+fn border_color_for(config: &Config, game: &Game) -> Color {
+    let walled = game.wrap() != (true, true) && !game.bounces();
+    danger(config.border_danger, game).map_or(
+        if walled {
+            WALLED_BORDER_COLOR
+        } else {
+            Color::Reset
+        },
+        danger_color,
+    )
 }
 
-impl Game {
-    fn go_forward(&mut self) -> Result<()> {
-        if let Some(head) = self.body.front() {
-            let mut new_head = head.clone();
+fn food_color(kind: FoodKind) -> Color {
+    match kind {
+        FoodKind::Normal => Color::Green,
+        FoodKind::Golden => Color::Yellow,
+        FoodKind::Rotten => Color::Magenta,
+        FoodKind::Magnet => Color::Cyan,
+        FoodKind::Pickaxe => Color::LightRed,
+        FoodKind::Speed => Color::White,
+    }
+}
 
-            match &self.direction {
-                Direction::Right => self.move_vec_right(&mut new_head),
-                Direction::Left => self.move_vec_left(&mut new_head),
-                Direction::Up => self.move_vec_up(&mut new_head),
-                Direction::Down => self.move_vec_down(&mut new_head),
-            }
+/// Food's colour on `tick` when it pulses, spending `PULSE_TICKS` on its
+/// usual colour and then as long on a shade of it.
+fn pulsing_food_color(kind: FoodKind, tick: u64) -> Color {
+    if (tick / PULSE_TICKS).is_multiple_of(2) {
+        return food_color(kind);
+    }
 
-            if self.vec_intersects_with_body(&new_head) {
-                return Err(anyhow!("game over :("));
-            }
+    match kind {
+        FoodKind::Normal => Color::LightGreen,
+        FoodKind::Golden => Color::LightYellow,
+        FoodKind::Rotten => Color::LightMagenta,
+        FoodKind::Magnet => Color::LightCyan,
+        FoodKind::Pickaxe => Color::Red,
+        FoodKind::Speed => Color::Gray,
+    }
+}
 
-            let mut pop_back = true;
+/// What the player is doing, which decides the keys hinted beneath the board.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum AppState {
+    Playing {
+        undo: bool,
+        screenshot: bool,
+        diagonal: bool,
+        minimap: bool,
+        /// The key that cycles the theme, if this game has one.
+        theme_key: Option<char>,
+    },
+    /// Frozen after a crash, waiting to see if the player undoes it.
+    Crashed,
+    /// Frozen after an undo, waiting for a direction to carry on in.
+    Undone,
+    /// Waiting for a key once the game is over, which may start another if
+    /// `retry` allows.
+    GameOver {
+        retry: bool,
+    },
+    Paused,
+    /// Watching the snake fall apart after it died.
+    Dying,
+    LevelComplete,
+    Spectating,
+    /// Playing a saved run back, which may be `paused`.
+    Watching {
+        paused: bool,
+    },
+    /// Picking what to play before the first game.
+    Menu,
+    /// Two players sharing the keyboard.
+    Versus,
+    Leaderboard,
+}
 
-            if let Some(block) = &self.block {
-                if *block == new_head {
-                    self.block = None;
-                    pop_back = false;
+impl AppState {
+    fn key_hints(&self) -> Vec<String> {
+        let hints = match *self {
+            AppState::Playing {
+                undo,
+                screenshot,
+                diagonal,
+                minimap,
+                theme_key,
+            } => {
+                let mut hints = vec![String::from("←↑↓→ move")];
+                if diagonal {
+                    hints.push(String::from("Home PgUp End PgDn diagonal"));
+                }
+                if undo {
+                    hints.push(String::from("u undo"));
+                }
+                hints.push(String::from("p pause"));
+                if minimap {
+                    hints.push(String::from("m map"));
                 }
+                if let Some(key) = theme_key {
+                    hints.push(format!("{} theme", key));
+                }
+                if screenshot {
+                    hints.push(String::from("F12 screenshot"));
+                }
+                hints.push(String::from("q quit"));
+                return hints;
             }
-
-            self.body.push_front(new_head);
-
-            if pop_back {
-                self.body.pop_back();
+            AppState::Crashed => vec!["u undo", "q quit"],
+            AppState::Undone => vec!["←↑↓→ resume", "u undo", "q quit"],
+            AppState::GameOver { retry: true } => {
+                vec!["r new board", "t same board", "any other key quit"]
             }
+            AppState::GameOver { retry: false } => vec!["any key quit"],
+            AppState::Paused => vec!["any key resume", "q quit"],
+            AppState::Dying => vec!["any key skip", "q quit"],
+            AppState::LevelComplete => vec!["any key continue", "q quit"],
+            AppState::Spectating => vec!["q quit"],
+            AppState::Watching { paused } => vec![
+                if paused { "space play" } else { "space pause" },
+                "←→ step",
+                "+- speed",
+                "q quit",
+            ],
+            AppState::Menu => vec!["↑↓ choose", "←→ change", "enter play", "q quit"],
+            AppState::Versus => vec!["←↑↓→ player 1", "wasd player 2", "q quit"],
+            AppState::Leaderboard => vec!["s sort", "f mode", "q quit"],
+        };
+        hints.into_iter().map(String::from).collect()
+    }
+}
 
-            Ok(())
+/// Joins as many hints as fit in `width` columns, dropping whole hints from
+/// the end rather than cutting one off part way.
+fn fit_hints(hints: &[String], width: u16) -> String {
+    let mut line = String::new();
+
+    for hint in hints {
+        let candidate = if line.is_empty() {
+            hint.to_string()
         } else {
-            Err(anyhow!("empty body"))
+            format!("{} · {}", line, hint)
+        };
+
+        if candidate.chars().count() > width as usize {
+            break;
         }
+        line = candidate;
     }
 
-    fn vec_intersects_with_body(&self, vec: &Vector) -> bool {
-        for block in &self.body {
-            if *block == *vec {
-                return true;
-            }
+    line
+}
+
+/// Player one's score every `SCORE_SAMPLE_EVERY`, the latest `SCORE_SAMPLES`
+/// of it.
+struct ScoreHistory {
+    /// Ticks between samples.
+    every: u64,
+    samples: VecDeque<u32>,
+}
+
+impl ScoreHistory {
+    fn new(tick: Duration) -> ScoreHistory {
+        ScoreHistory {
+            every: ticks(SCORE_SAMPLE_EVERY, tick).max(1),
+            samples: VecDeque::with_capacity(SCORE_SAMPLES),
         }
-        return false;
     }
 
-    fn move_vec_left(&mut self, vec: &mut Vector) {
-        if vec.0 > self.dimensions.x.0 {
-            vec.0 -= 1;
-        } else {
-            vec.0 = self.dimensions.x.1;
+    /// Takes a sample if one is due on the tick `game` is on.
+    fn update(&mut self, game: &Game) {
+        if !game.ticks().is_multiple_of(self.every) {
+            return;
         }
+        if self.samples.len() == SCORE_SAMPLES {
+            self.samples.pop_front();
+        }
+        self.samples.push_back(game.snakes()[0].score());
     }
 
-    fn move_vec_right(&mut self, vec: &mut Vector) {
-        if vec.0 < self.dimensions.x.1 {
-            vec.0 += 1;
-        } else {
-            vec.0 = self.dimensions.x.0;
-        }
+    fn samples(&self) -> Vec<u32> {
+        self.samples.iter().copied().collect()
     }
+}
 
-    fn move_vec_up(&mut self, vec: &mut Vector) {
-        if vec.1 > self.dimensions.y.0 {
-            vec.1 -= 1;
-        } else {
-            vec.1 = self.dimensions.y.1;
+/// A bar per sample, each as tall as its share of the highest. All zeros
+/// come out as the lowest bar, and no samples as nothing.
+fn sparkline(samples: &[u32]) -> String {
+    const BARS: [char; 8] = ['▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+
+    let highest = samples.iter().copied().max().unwrap_or(0).max(1) as u64;
+    samples
+        .iter()
+        .map(|&sample| BARS[(sample as u64 * (BARS.len() as u64 - 1) / highest) as usize])
+        .collect()
+}
+
+/// Cells player one's tail recently moved off, each with the tick it did,
+/// so they can be drawn fading out behind the snake.
+#[derive(Default)]
+struct Trail {
+    cells: VecDeque<(Vector, u64)>,
+}
+
+impl Trail {
+    /// Adds the cells of `before`, the body ahead of the latest tick, that the
+    /// snake no longer covers, and forgets those too old to be drawn or left
+    /// on ticks since undone.
+    fn update(&mut self, before: &[Vector], game: &Game) {
+        let body = game.snakes()[0].body();
+        let now = game.ticks();
+
+        for cell in before.iter().rev() {
+            if !body.contains(cell) {
+                self.cells.push_front((cell.clone(), now));
+            }
         }
+
+        self.cells
+            .retain(|(_, left)| *left <= now && now - left < TRAIL_COLORS.len() as u64);
     }
 
-    fn move_vec_down(&mut self, vec: &mut Vector) {
-        if vec.1 < self.dimensions.y.1 {
-            vec.1 += 1;
-        } else {
-            vec.1 = self.dimensions.y.0;
+    /// Each cell still glowing and how many ticks ago it was left.
+    fn cells(&self, now: u64) -> impl Iterator<Item = (&Vector, usize)> {
+        self.cells
+            .iter()
+            .map(move |(cell, left)| (cell, now.saturating_sub(*left) as usize))
+            .filter(|(_, age)| *age < TRAIL_COLORS.len())
+    }
+}
+
+/// Extras drawn along with the game itself.
+#[derive(Default)]
+struct Overlay<'a> {
+    /// A previous run's snake, drawn beneath the live one.
+    ghost: Option<&'a [Vector]>,
+    /// Drawn beneath the snakes and food, never over them.
+    trail: Option<&'a Trail>,
+    notice: Option<&'a Notice>,
+    /// Shown on the line beneath the board, when hints are enabled.
+    hints: Option<AppState>,
+    /// Text shown in the middle of the board, over everything else.
+    banner: Option<&'a str>,
+    /// How long each tick lasts, shown in the title as a `speed_level`.
+    tick: Option<Duration>,
+    /// The score to reach, shown alongside the score.
+    target: Option<u32>,
+    /// Whether to show the minimap beside the board, where there's room.
+    minimap: bool,
+    /// How far through the tick it is, from 0 to 1, to draw each head that far
+    /// into the cell it's moving to.
+    glide: Option<f64>,
+    /// Whether player one's head is lit up from eating.
+    flash: bool,
+    /// Whether to point out the nearest food in the title.
+    compass: bool,
+    /// How far from player one's head the board can be seen, if not all of
+    /// it.
+    fog: Option<u16>,
+    /// The most turns made on the trainer course, to show player one's own
+    /// turns against.
+    best_turns: Option<u32>,
+    /// The best score so far, shown alongside the score.
+    high_score: Option<u32>,
+    /// How many of player one's segments have fallen away from the tail.
+    collapsed: usize,
+}
+
+/// An arrow pointing `direction`.
+fn arrow(direction: Direction) -> &'static str {
+    match direction {
+        Direction::Left => "←",
+        Direction::Up => "↑",
+        Direction::Right => "→",
+        Direction::Down => "↓",
+        Direction::UpLeft => "↖",
+        Direction::UpRight => "↗",
+        Direction::DownLeft => "↙",
+        Direction::DownRight => "↘",
+    }
+}
+
+/// Whether `at` is within `radius` cells of player one's head as the crow
+/// flies, going the short way round through edges that wrap.
+fn in_sight(game: &Game, at: &Vector, radius: u16) -> bool {
+    let (dx, dy) = ai::offset(game, game.snakes()[0].body().head(), at);
+    let radius = radius as i32;
+    dx * dx + dy * dy <= radius * radius
+}
+
+/// Ranks a tick interval from 1 (`max`, the slowest) to 10 (`min`, the
+/// fastest), so speeds can be compared without thinking in milliseconds.
+/// Intervals outside the range are clamped to it.
+fn speed_level(interval: Duration, min: Duration, max: Duration) -> u8 {
+    if max <= min {
+        return 10;
+    }
+
+    let interval = interval.clamp(min, max);
+    let fraction = (max - interval).as_secs_f64() / (max - min).as_secs_f64();
+    1 + (fraction * 9.0).round() as u8
+}
+
+/// A solid cell of the board, `width` columns wide from `x`.
+struct Cube {
+    x: u16,
+    y: u16,
+    width: u16,
+    color: Color,
+}
+
+impl Cube {
+    fn new(at: &Vector, dimensions: &Dimensions, layout: &BoardLayout, color: Color) -> Cube {
+        let (x, y) = layout.cell_origin(at, dimensions);
+        Cube {
+            x,
+            y,
+            width: layout.cell_width,
+            color,
         }
     }
 }
 
-fn main() -> Result<(), io::Error> {
-    let mut game = Game {
-        body: LinkedList::from([Vector::new(5, 5)]),
-        dimensions: Dimensions {
-            x: (1, 20),
-            y: (1, 10),
-        },
-        direction: Direction::Right,
-        block: None,
-    };
-    let mut rng = rand::thread_rng();
+impl Widget for Cube {
+    fn render(self, area: Rect, buf: &mut tui::buffer::Buffer) {
+        if area.area() == 0 {
+            return;
+        }
 
-    enable_raw_mode()?;
-    let mut stdout = io::stdout();
-    execute!(stdout, EnterAlternateScreen)?;
-    let backend = CrosstermBackend::new(stdout);
-    let mut terminal = Terminal::new(backend)?;
+        for x in self.x..self.x + self.width {
+            buf.get_mut(x, self.y).set_bg(self.color);
+        }
+    }
+}
 
-    loop {
-        let mut cubes: Vec<Cube> = game
-            .body
-            .iter()
-            .map(|Vector(x, y)| Cube::new(*x, *y))
-            .collect();
+/// The whole board shrunk down to fit `MINIMAP_SIZE`, each character showing
+/// the most important thing in the cells it stands for.
+struct Minimap<'a> {
+    grid: &'a Grid,
+    theme: &'a Theme,
+}
 
-        if let Some(block) = &game.block {
-            cubes.push(Cube::new(block.0, block.1));
+impl Minimap<'_> {
+    /// What a character of the map shows when its cells hold several things.
+    fn rank(cell: CellKind) -> u8 {
+        match cell {
+            CellKind::Empty => 0,
+            CellKind::Terrain(_) => 1,
+            CellKind::Wall => 2,
+            CellKind::Obstacle => 3,
+            CellKind::Body(_) => 4,
+            CellKind::Food(_) => 5,
+            CellKind::Head(_) => 6,
         }
+    }
+}
 
-        let score = game.body.len();
+impl Widget for Minimap<'_> {
+    fn render(self, area: Rect, buf: &mut tui::buffer::Buffer) {
+        if area.area() == 0 {
+            return;
+        }
 
-        let size = Rect::new(0, 0, game.dimensions.x.1 + 3, game.dimensions.y.1 + 2);
+        let across = self.grid.width().div_ceil(area.width as usize);
+        let down = self.grid.height().div_ceil(area.height as usize);
 
-        terminal.draw(|f| {
-            let block = Block::default()
-                .borders(Borders::empty())
-                .title(format!("score: {}", score))
-                .borders(Borders::ALL);
+        for (y, rows) in
+            (area.y..area.bottom()).zip(self.grid.rows().collect::<Vec<_>>().chunks(down))
+        {
+            for x in area.x..area.right() {
+                let start = (x - area.x) as usize * across;
+                let cell = rows
+                    .iter()
+                    .flat_map(|row| row.iter().skip(start).take(across))
+                    .copied()
+                    .max_by_key(|cell| Minimap::rank(*cell))
+                    .unwrap_or(CellKind::Empty);
 
-            f.render_widget(block, size);
-            for cube in cubes {
-                f.render_widget(cube, size);
+                let (symbol, color) = match cell {
+                    CellKind::Empty => continue,
+                    CellKind::Wall => ("█", self.theme.wall),
+                    CellKind::Obstacle => ("█", self.theme.obstacle),
+                    CellKind::Terrain(kind) => ("░", terrain_color(kind)),
+                    CellKind::Food(kind) => ("•", food_color(kind)),
+                    CellKind::Body(player) => ("█", self.theme.players[player]),
+                    CellKind::Head(player) => ("●", self.theme.players[player]),
+                };
+                buf.get_mut(x, y).set_symbol(symbol).set_fg(color);
             }
-        })?;
+        }
+    }
+}
 
-        if let Err(_) = game.go_forward() {
-            break;
+/// Arrows on each side of the border that the snakes can pass through to the
+/// opposite side. The left and right ones sit halfway down, the top and
+/// bottom ones by the right-hand corners, clear of the title.
+struct WrapMarkers {
+    wrap: (bool, bool),
+}
+
+impl Widget for WrapMarkers {
+    fn render(self, area: Rect, buf: &mut tui::buffer::Buffer) {
+        if area.width < 3 || area.height < 3 {
+            return;
         }
 
-        if game.block.is_none() {
-            game.block = Some(Vector::new(
-                rng.gen_range(game.dimensions.x.0..game.dimensions.x.1),
-                rng.gen_range(game.dimensions.y.0..game.dimensions.y.1),
-            ));
+        if self.wrap.0 {
+            let y = area.y + area.height / 2;
+            buf.get_mut(area.x, y).set_symbol("↔");
+            buf.get_mut(area.right() - 1, y).set_symbol("↔");
         }
+        if self.wrap.1 {
+            let x = area.right() - 2;
+            buf.get_mut(x, area.y).set_symbol("↕");
+            buf.get_mut(x, area.bottom() - 1).set_symbol("↕");
+        }
+    }
+}
 
-        if event::poll(Duration::from_millis(100))? {
-            if let Event::Key(key) = event::read()? {
-                match (key.code, key.modifiers) {
-                    (KeyCode::Char(code), KeyModifiers::CONTROL) if code == 'c' || code == 'd' => {
-                        break
-                    }
-                    (KeyCode::Char('q'), _) => break,
-                    (KeyCode::Up, _) if game.direction != Direction::Down => {
-                        game.direction = Direction::Up
-                    }
-                    (KeyCode::Down, _) if game.direction != Direction::Up => {
-                        game.direction = Direction::Down
-                    }
-                    (KeyCode::Left, _) if game.direction != Direction::Right => {
-                        game.direction = Direction::Left
-                    }
-                    (KeyCode::Right, _) if game.direction != Direction::Left => {
-                        game.direction = Direction::Right
-                    }
-                    _ => (),
-                }
+/// What to draw in each column, from the left, of the cell a head moving
+/// `direction` is entering, `fraction` of the way through a tick. Cells fill
+/// half a column or half a row at a time; an empty string leaves a column
+/// as it is. Diagonal moves don't glide.
+fn glide_glyphs(direction: Direction, fraction: f64, width: u16) -> Vec<&'static str> {
+    let width = width as usize;
+    let across = (fraction.clamp(0.0, 1.0) * 2.0 * width as f64).round() as usize;
+    let column = |halves: usize, half: &'static str| match halves {
+        0 => "",
+        1 => half,
+        _ => "█",
+    };
+
+    match direction {
+        Direction::Right => (0..width)
+            .map(|i| column(across.saturating_sub(2 * i), "▌"))
+            .collect(),
+        Direction::Left => (0..width)
+            .rev()
+            .map(|i| column(across.saturating_sub(2 * i), "▐"))
+            .collect(),
+        Direction::Down | Direction::Up => {
+            let down = (fraction.clamp(0.0, 1.0) * 2.0).round() as usize;
+            let half = if direction == Direction::Down {
+                "▀"
+            } else {
+                "▄"
+            };
+            vec![column(down, half); width]
+        }
+        _ => Vec::new(),
+    }
+}
+
+/// A head part of the way into the next cell, as worked out by
+/// `glide_glyphs`.
+struct Glide {
+    x: u16,
+    y: u16,
+    glyphs: Vec<&'static str>,
+    color: Color,
+}
+
+impl Widget for Glide {
+    fn render(self, area: Rect, buf: &mut tui::buffer::Buffer) {
+        if area.area() == 0 {
+            return;
+        }
+
+        for (x, glyph) in (self.x..).zip(self.glyphs) {
+            if !glyph.is_empty() {
+                buf.get_mut(x, self.y).set_symbol(glyph).set_fg(self.color);
+            }
+        }
+    }
+}
+
+/// One cell of a snake drawn as a line. The glyph goes in the cell's first
+/// column and the rest carry the line on when it joins the cell to the right.
+struct Segment {
+    x: u16,
+    y: u16,
+    width: u16,
+    symbol: &'static str,
+    joins_right: bool,
+    color: Color,
+}
+
+impl Widget for Segment {
+    fn render(self, area: Rect, buf: &mut tui::buffer::Buffer) {
+        if area.area() == 0 {
+            return;
+        }
+
+        buf.get_mut(self.x, self.y)
+            .set_symbol(self.symbol)
+            .set_fg(self.color);
+        if self.joins_right {
+            for x in self.x + 1..self.x + self.width {
+                buf.get_mut(x, self.y).set_symbol("─").set_fg(self.color);
             }
         }
     }
+}
+
+/// Which way `to` lies from the neighbouring cell `from`, taking wrapping
+/// around the edge of the board into account.
+fn neighbour_direction(from: &Vector, to: &Vector, dimensions: &Dimensions) -> Direction {
+    let next = |value: u16, (low, high): (u16, u16)| if value == high { low } else { value + 1 };
+    let right = next(from.0, dimensions.x) == to.0;
+    let down = next(from.1, dimensions.y) == to.1;
+
+    match (from.0 == to.0, from.1 == to.1) {
+        (_, true) if right => Direction::Right,
+        (_, true) => Direction::Left,
+        (true, _) if down => Direction::Down,
+        (true, _) => Direction::Up,
+        _ => match (right, down) {
+            (true, true) => Direction::DownRight,
+            (true, false) => Direction::UpRight,
+            (false, true) => Direction::DownLeft,
+            (false, false) => Direction::UpLeft,
+        },
+    }
+}
+
+/// Whether segment `i` of `body` runs on into the cell to its right.
+fn joins_right(body: &[&Vector], i: usize, dimensions: &Dimensions) -> bool {
+    [i.checked_sub(1).and_then(|i| body.get(i)), body.get(i + 1)]
+        .into_iter()
+        .flatten()
+        .any(|neighbour| neighbour_direction(body[i], neighbour, dimensions) == Direction::Right)
+}
+
+/// The box-drawing character joining segment `i` of `body` to the segments
+/// before and after it. The head is a dot and the tail just continues the
+/// line.
+fn body_glyph(body: &[&Vector], i: usize, dimensions: &Dimensions) -> &'static str {
+    if i == 0 {
+        return "●";
+    }
 
+    let at = body[i];
+    let neighbours: Vec<Direction> = [body.get(i - 1), body.get(i + 1)]
+        .into_iter()
+        .flatten()
+        .map(|neighbour| neighbour_direction(at, neighbour, dimensions))
+        .collect();
+
+    use Direction::*;
+    match neighbours.as_slice() {
+        [Left | Right] | [Left, Right] | [Right, Left] => "─",
+        [Up | Down] | [Up, Down] | [Down, Up] => "│",
+        [Down, Right] | [Right, Down] => "┌",
+        [Down, Left] | [Left, Down] => "┐",
+        [Up, Right] | [Right, Up] => "└",
+        [Up, Left] | [Left, Up] => "┘",
+        [UpLeft | DownRight] | [UpLeft, DownRight] | [DownRight, UpLeft] => "╲",
+        [UpRight | DownLeft] | [UpRight, DownLeft] | [DownLeft, UpRight] => "╱",
+        _ => "●",
+    }
+}
+
+/// Without `alt_screen` the game is drawn over the terminal's main screen,
+/// cleared first, so the last frame stays in the scrollback afterwards.
+fn setup_terminal<W: Write>(config: &Config, alt_screen: bool, mut out: W) -> Result<Term<W>> {
+    enable_raw_mode()?;
+    enter_screen(&mut out, config, alt_screen)?;
+    let backend = CrosstermBackend::new(out);
+    let mut terminal = Terminal::new(backend)?;
+    if !alt_screen {
+        terminal.clear()?;
+    }
+    Ok(terminal)
+}
+
+/// Switches to the alternate screen if asked to, and turns on focus
+/// reporting if it's wanted.
+fn enter_screen<W: Write>(out: &mut W, config: &Config, alt_screen: bool) -> io::Result<()> {
+    if alt_screen {
+        execute!(out, EnterAlternateScreen)?;
+    }
+    if config.pause_on_focus_loss {
+        execute!(out, EnableFocusChange)?;
+    }
+    Ok(())
+}
+
+/// Leaves the alternate screen, or on the main screen puts the cursor
+/// beneath the last frame so whatever's printed next doesn't overwrite it.
+fn restore_terminal<W: Write>(terminal: &mut Term<W>, alt_screen: bool) -> Result<()> {
     disable_raw_mode()?;
-    execute!(terminal.backend_mut(), LeaveAlternateScreen)?;
+    let bottom = if alt_screen {
+        0
+    } else {
+        terminal.size()?.bottom()
+    };
+    leave_screen(terminal.backend_mut(), alt_screen, bottom)?;
+    terminal.show_cursor()?;
+    Ok(())
+}
 
-    return Ok(());
+/// The other half of `enter_screen`, for a screen `bottom` rows tall.
+fn leave_screen<W: Write>(out: &mut W, alt_screen: bool, bottom: u16) -> io::Result<()> {
+    execute!(out, DisableFocusChange)?;
+    if alt_screen {
+        execute!(out, LeaveAlternateScreen)?;
+    } else {
+        execute!(out, MoveTo(0, bottom.saturating_sub(1)))?;
+        writeln!(out)?;
+    }
+    Ok(())
+}
+
+/// How many ticks of length `tick` make up `duration`, rounded down. Game
+/// timers are set up in ticks with this so that pausing freezes them, rather
+/// than being checked against `Instant::now()`.
+fn ticks(duration: Duration, tick: Duration) -> u64 {
+    (duration.as_millis() / tick.as_millis()) as u64
+}
+
+/// Whether the next ring of a shrinking board closes within `warning`, with
+/// ticks of length `tick`.
+fn about_to_shrink(game: &Game, tick: Duration, warning: Duration) -> bool {
+    game.ticks_until_shrink()
+        .is_some_and(|ticks| tick * ticks as u32 <= warning)
+}
+
+/// How the board is laid out on screen.
+#[derive(Clone, Copy)]
+struct BoardLayout {
+    /// Columns each cell is drawn across.
+    cell_width: u16,
+    /// Frames drawn one inside the other around the board. Never zero, since
+    /// the title sits in the outermost.
+    border: u16,
+    /// Blank cells between the innermost frame and the board on every side.
+    padding: u16,
+}
+
+impl BoardLayout {
+    fn new(config: &Config) -> BoardLayout {
+        BoardLayout {
+            cell_width: config.cell_width,
+            border: config.border_thickness,
+            padding: config.padding,
+        }
+    }
+
+    /// The columns and rows the board takes up, frames and padding
+    /// included. Fails for a board with more cells on screen than a `Rect`
+    /// can hold, which would otherwise be quietly shrunk to fit.
+    fn board_size(&self, dimensions: &Dimensions) -> Result<(u16, u16)> {
+        let columns = dimensions.x.1 - dimensions.x.0 + 1;
+        let rows = dimensions.y.1 - dimensions.y.0 + 1;
+        let size = || {
+            let edges = self.border.checked_add(self.padding)?.checked_mul(2)?;
+            let width = columns.checked_mul(self.cell_width)?.checked_add(edges)?;
+            let height = rows.checked_add(edges)?;
+            width.checked_mul(height)?;
+            Some((width, height))
+        };
+        size().ok_or_else(|| {
+            anyhow!(
+                "a {}x{} board with cells {} wide, {} frames and {} padding is too big to draw",
+                columns,
+                rows,
+                self.cell_width,
+                self.border,
+                self.padding
+            )
+        })
+    }
+
+    /// Where the board is drawn, frames and padding included. A board too
+    /// big for `board_size`, which is turned away before a game starts,
+    /// takes up as much as a `Rect` can.
+    fn board_rect(&self, dimensions: &Dimensions) -> Rect {
+        let (width, height) = self.board_size(dimensions).unwrap_or((u16::MAX, u16::MAX));
+        Rect::new(0, 0, width, height)
+    }
+
+    /// Where frame number `frame` is drawn, counting in from the outermost
+    /// at zero.
+    fn frame_rect(&self, dimensions: &Dimensions, frame: u16) -> Rect {
+        inset(self.board_rect(dimensions), frame)
+    }
+
+    /// The part of the board the cells are drawn in.
+    fn play_rect(&self, dimensions: &Dimensions) -> Rect {
+        inset(self.board_rect(dimensions), self.border + self.padding)
+    }
+
+    /// The screen position of the first column of the cell `at`.
+    fn cell_origin(&self, at: &Vector, dimensions: &Dimensions) -> (u16, u16) {
+        let play = self.play_rect(dimensions);
+        (
+            play.x + (at.0 - dimensions.x.0) * self.cell_width,
+            play.y + at.1 - dimensions.y.0,
+        )
+    }
+}
+
+/// Rows drawn beneath the board: the key hints, if they're shown, and the
+/// line notices go on.
+fn footer_rows(config: &Config) -> u16 {
+    u16::from(config.show_hints) + 1
+}
+
+/// `area` shrunk by `by` on every side.
+fn inset(area: Rect, by: u16) -> Rect {
+    area.inner(&Margin {
+        horizontal: by,
+        vertical: by,
+    })
+}
+
+/// A `width` by `height` rectangle in the middle of `area`, shrunk if it
+/// doesn't fit.
+fn centered_rect(area: Rect, width: u16, height: u16) -> Rect {
+    let width = width.min(area.width);
+    let height = height.min(area.height);
+
+    Rect::new(
+        area.x + (area.width - width) / 2,
+        area.y + (area.height - height) / 2,
+        width,
+        height,
+    )
+}
+
+/// The width of the longest line in `text` and how many lines it has.
+fn text_size(text: &str) -> (u16, u16) {
+    let width = text
+        .lines()
+        .map(|line| line.chars().count())
+        .max()
+        .unwrap_or(0);
+    (width as u16, text.lines().count() as u16)
+}
+
+/// `text` cut down to fit in `width` by `height`. Lines too long are wrapped
+/// at spaces, or cut off where there are none, then lines are dropped from the
+/// top so any message under art is kept.
+fn fit_text(text: &str, width: u16, height: u16) -> String {
+    let width = usize::from(width);
+    let mut lines: Vec<String> = Vec::new();
+    for line in text.lines() {
+        if line.chars().count() <= width || !line.contains(' ') {
+            lines.push(line.chars().take(width).collect());
+            continue;
+        }
+        let mut wrapped = String::new();
+        for word in line.split(' ') {
+            let word: String = word.chars().take(width).collect();
+            if !wrapped.is_empty() && wrapped.chars().count() + 1 + word.chars().count() > width {
+                lines.push(mem::take(&mut wrapped));
+            } else if !wrapped.is_empty() {
+                wrapped.push(' ');
+            }
+            wrapped += &word;
+        }
+        lines.push(wrapped);
+    }
+    let cut = lines.len().saturating_sub(usize::from(height));
+    lines[cut..].join("\n")
+}
+
+/// Draws `text` centered line by line in the middle of `area`, blanking out
+/// whatever is behind it.
+fn render_centered<B: Backend>(f: &mut tui::Frame<B>, area: Rect, text: &str) {
+    let (width, height) = text_size(text);
+    let rect = centered_rect(area, width, height);
+
+    f.render_widget(Clear, rect);
+    f.render_widget(Paragraph::new(text).alignment(Alignment::Center), rect);
+}
+
+/// Renders `game` and returns the frame that was drawn, so callers can capture
+/// it.
+fn draw<'a, B: Backend>(
+    terminal: &'a mut Terminal<B>,
+    config: &Config,
+    game: &Game,
+    overlay: &Overlay,
+) -> Result<&'a Buffer> {
+    let mut cubes: Vec<Cube> = Vec::new();
+
+    let grid = game.grid_snapshot();
+    let dimensions = game.dimensions();
+    let theme = config.theme();
+    let layout = BoardLayout::new(config);
+    let width = layout.cell_width;
+    let cube = |at: &Vector, color: Color| Cube::new(at, dimensions, &layout, color);
+    // Snakes blink through a grace period.
+    let hidden = game.in_grace() && game.ticks() % 2 == 1;
+    // Segments that have fallen away are left out.
+    let body = game.snakes()[0].body();
+    let fallen: HashSet<&Vector> = body
+        .iter()
+        .skip(body.len().saturating_sub(overlay.collapsed))
+        .collect();
+    // Out in the fog nothing shows but the snakes, faded.
+    let seen = |at: &Vector| overlay.fog.is_none_or(|radius| in_sight(game, at, radius));
+    let warning = overlay
+        .tick
+        .is_some_and(|tick| about_to_shrink(game, tick, config.shrink_warning));
+    if warning {
+        for y in dimensions.y.0..=dimensions.y.1 {
+            for x in dimensions.x.0..=dimensions.x.1 {
+                let at = Vector(x, y);
+                if game.ring(&at) == game.closed_rings() && seen(&at) {
+                    cubes.push(cube(&at, WARNING_COLORS[game.ticks() as usize % 2]));
+                }
+            }
+        }
+    }
+
+    // Terrain goes beneath everything else so snakes are drawn on top of it.
+    cubes.extend(
+        game.terrain()
+            .iter()
+            .filter(|(at, _)| seen(at))
+            .map(|(at, kind)| cube(at, terrain_color(*kind))),
+    );
+
+    if let Some(ghost) = overlay.ghost {
+        cubes.extend(
+            ghost
+                .iter()
+                .filter(|at| seen(at))
+                .map(|at| cube(at, GHOST_COLOR)),
+        );
+    }
+
+    if let Some(trail) = overlay.trail {
+        cubes.extend(
+            trail
+                .cells(game.ticks())
+                .filter(|(cell, _)| grid.get(cell) == Some(CellKind::Empty) && seen(cell))
+                .map(|(at, age)| cube(at, TRAIL_COLORS[age])),
+        );
+    }
+
+    for (row, cells) in (dimensions.y.0..).zip(grid.rows()) {
+        for (column, cell) in (dimensions.x.0..).zip(cells) {
+            let at = Vector(column, row);
+            let seen = seen(&at);
+            let color = match cell {
+                CellKind::Empty | CellKind::Terrain(_) => continue,
+                CellKind::Wall | CellKind::Obstacle | CellKind::Food(_) if !seen => continue,
+                CellKind::Wall => theme.wall,
+                CellKind::Obstacle => theme.obstacle,
+                CellKind::Food(kind) if config.pulse_food => {
+                    pulsing_food_color(*kind, game.ticks())
+                }
+                CellKind::Food(kind) => food_color(*kind),
+                CellKind::Head(_) | CellKind::Body(_) if hidden => continue,
+                CellKind::Head(0) | CellKind::Body(0) if fallen.contains(&at) => continue,
+                CellKind::Head(0) if overlay.flash && config.body_style == BodyStyle::Blocks => {
+                    config.eat_flash_color.into()
+                }
+                CellKind::Head(player) | CellKind::Body(player) => match config.body_style {
+                    BodyStyle::Blocks if seen => theme.players[*player],
+                    BodyStyle::Blocks => theme.faded_players[*player],
+                    BodyStyle::Lines => continue,
+                },
+            };
+            cubes.push(cube(&at, color));
+        }
+    }
+
+    let mut segments: Vec<Segment> = Vec::new();
+
+    if config.body_style == BodyStyle::Lines && !hidden {
+        for (player, (snake, color)) in game.snakes().iter().zip(theme.players).enumerate() {
+            let body: Vec<&Vector> = snake.body().iter().collect();
+            let standing = match player {
+                0 => body.len().saturating_sub(overlay.collapsed),
+                _ => body.len(),
+            };
+            segments.extend(body.iter().enumerate().take(standing).map(|(i, at)| {
+                let (x, y) = layout.cell_origin(at, dimensions);
+                Segment {
+                    x,
+                    y,
+                    width,
+                    symbol: body_glyph(&body, i, dimensions),
+                    joins_right: joins_right(&body, i, dimensions),
+                    color: match (i, player) {
+                        (0, 0) if overlay.flash => config.eat_flash_color.into(),
+                        _ if !seen(at) => theme.faded_players[player],
+                        _ => color,
+                    },
+                }
+            }));
+        }
+    }
+
+    let mut glides: Vec<Glide> = Vec::new();
+
+    if let Some(fraction) = overlay
+        .glide
+        .filter(|_| config.body_style == BodyStyle::Blocks)
+    {
+        let play = layout.play_rect(dimensions);
+        for (snake, color) in game.snakes().iter().zip(theme.players) {
+            let (x, y) = layout.cell_origin(snake.body().head(), dimensions);
+            // Heads about to wrap around the edge don't glide off it.
+            let (x, y) = match snake.direction() {
+                Direction::Right => (x + width, y),
+                Direction::Left if x >= play.x + width => (x - width, y),
+                Direction::Down => (x, y + 1),
+                Direction::Up if y > play.y => (x, y - 1),
+                _ => continue,
+            };
+            if x < play.right() && y < play.bottom() && !hidden {
+                glides.push(Glide {
+                    x,
+                    y,
+                    glyphs: glide_glyphs(snake.direction(), fraction, width),
+                    color,
+                });
+            }
+        }
+    }
+
+    let title = if game.snakes().len() == 1 {
+        let snake = &game.snakes()[0];
+        let mut title = match overlay.target {
+            Some(target) => format!("score: {}/{}", snake.score(), target),
+            None => format!("score: {}", snake.score()),
+        };
+        if snake.combo() > 1 {
+            title += &format!("  x{} combo", snake.combo());
+        }
+        if let Some(high) = overlay.high_score {
+            title += &format!("  high: {}", high.max(snake.score()));
+        }
+        if let Some(best) = overlay.best_turns {
+            title += &format!("  turns: {} (best {})", snake.turns(), best);
+        }
+        if game.undos_left() > 0 {
+            title += &format!("  undos: {}", game.undos_left());
+        }
+        if game.growth_disabled() {
+            title += "  no growth";
+        }
+        if game.self_collision_disabled() {
+            title += "  ghost";
+        }
+        if let Some(ticks) = game.ticks_until_hungry() {
+            title += &format!("  hunger: {}", ticks);
+        }
+        if snake.magnet() > 0 {
+            title += &format!("  magnet: {}", snake.magnet());
+        }
+        if game.wall_break_charges() > 0 {
+            title += &format!("  pickaxe: {}", game.wall_break_charges());
+        }
+        if let Some(direction) = ai::food_direction(game).filter(|_| overlay.compass) {
+            title += &format!("  food: {}", arrow(direction));
+        }
+        if let Some(tick) = overlay.tick {
+            let current = tick.mul_f64(game.interval_scale());
+            title += &format!(
+                "  speed: {}",
+                speed_level(current, FASTEST_TICK, SLOWEST_TICK)
+            );
+            // The limit was set in ticks of the usual length, so that's what
+            // it counts down in.
+            if let Some(left) = game.ticks_left() {
+                let secs = (tick * left as u32).as_secs_f64().ceil();
+                title += &format!("  time: {}s", secs);
+            }
+        }
+        title
+    } else {
+        game.snakes()
+            .iter()
+            .enumerate()
+            .map(|(i, snake)| format!("p{}: {}", i + 1, snake.score()))
+            .collect::<Vec<String>>()
+            .join("  ")
+    };
+
+    let size = layout.board_rect(dimensions);
+    let play = layout.play_rect(dimensions);
+    let border = Style::default().fg(border_color_for(config, game));
+
+    let frame = terminal.draw(|f| {
+        let block = Block::default()
+            .borders(Borders::empty())
+            .title(title)
+            .borders(Borders::ALL)
+            .border_style(border);
+
+        f.render_widget(block, size);
+        for frame in 1..layout.border {
+            f.render_widget(
+                Block::default().borders(Borders::ALL).border_style(border),
+                layout.frame_rect(dimensions, frame),
+            );
+        }
+        if config.wrap_markers {
+            f.render_widget(
+                WrapMarkers { wrap: game.wrap() },
+                layout.frame_rect(dimensions, layout.border - 1),
+            );
+        }
+        for cube in cubes {
+            f.render_widget(cube, size);
+        }
+        for segment in segments {
+            f.render_widget(segment, size);
+        }
+        for glide in glides {
+            f.render_widget(glide, size);
+        }
+
+        if let Some(banner) = overlay.banner {
+            render_centered(f, play, banner);
+        }
+
+        let map = Rect::new(size.right() + 1, 0, MINIMAP_SIZE.0 + 2, MINIMAP_SIZE.1 + 2);
+        // The minimap would give away everything the fog hides.
+        if overlay.minimap && overlay.fog.is_none() && map.intersection(f.size()) == map {
+            let block = Block::default().title("map").borders(Borders::ALL);
+            let inner = block.inner(map);
+            f.render_widget(block, map);
+            f.render_widget(Minimap { grid: &grid, theme }, inner);
+        }
+
+        let mut below = size.bottom();
+
+        if let Some(state) = overlay.hints {
+            let line = Rect::new(0, below, f.size().width, 1).intersection(f.size());
+            let hints = fit_hints(&state.key_hints(), line.width);
+            f.render_widget(
+                Paragraph::new(hints).style(Style::default().fg(Color::DarkGray)),
+                line,
+            );
+            below += 1;
+        }
+
+        if let Some(notice) = overlay.notice {
+            let line = Rect::new(0, below, f.size().width, 1).intersection(f.size());
+            f.render_widget(Paragraph::new(notice.text.as_str()), line);
+        }
+    })?;
+
+    Ok(frame.buffer)
+}
+
+/// Where the screens get terminal events from, so that tests can script
+/// them.
+trait EventSource {
+    /// Whether an event comes within `timeout`.
+    fn poll(&mut self, timeout: Duration) -> Result<bool>;
+
+    /// The next event, waiting for it if need be.
+    fn read(&mut self) -> Result<Event>;
+}
+
+/// The events of the terminal the game is running in.
+struct TerminalEvents;
+
+impl EventSource for TerminalEvents {
+    fn poll(&mut self, timeout: Duration) -> Result<bool> {
+        Ok(event::poll(timeout)?)
+    }
+
+    fn read(&mut self) -> Result<Event> {
+        Ok(event::read()?)
+    }
+}
+
+/// A terminal event, boiled down to what the screens respond to.
+enum Input {
+    Key(KeyEvent),
+    /// The terminal changed size, so whatever's showing needs drawing again.
+    Resize,
+    FocusLost,
+    /// Focus coming back, the mouse and pasted text, none of which do
+    /// anything. A paste isn't typing, so pasted arrows don't steer.
+    Ignored,
+}
+
+/// Sorts every kind of event crossterm delivers, so each screen only has to
+/// match on `Input`.
+fn handle_event(event: Event) -> Input {
+    match event {
+        Event::Key(key) => Input::Key(key),
+        Event::Resize(..) => Input::Resize,
+        Event::FocusLost => Input::FocusLost,
+        Event::FocusGained | Event::Mouse(_) | Event::Paste(_) => Input::Ignored,
+    }
+}
+
+fn is_quit(key: &KeyEvent) -> bool {
+    match (key.code, key.modifiers) {
+        (KeyCode::Char(code), KeyModifiers::CONTROL) => code == 'c' || code == 'd',
+        (KeyCode::Char('q'), _) => true,
+        _ => false,
+    }
+}
+
+/// Drops a direction key pressed again within `window` of the last, so a
+/// held key's flood of repeats comes out as the one turn.
+struct Debounce {
+    window: Duration,
+    /// The last direction taken and the playing time it was pressed at.
+    last: Option<(Direction, Duration)>,
+}
+
+impl Debounce {
+    fn new(window: Duration) -> Debounce {
+        Debounce { window, last: None }
+    }
+
+    /// Whether `direction`, pressed at playing time `now`, should be taken.
+    fn accept(&mut self, direction: Direction, now: Duration) -> bool {
+        let repeat = self
+            .last
+            .is_some_and(|(last, at)| last == direction && now.saturating_sub(at) < self.window);
+        self.last = Some((direction, now));
+        !repeat
+    }
+}
+
+fn key_direction(key: &KeyEvent) -> Option<Direction> {
+    match key.code {
+        KeyCode::Up => Some(Direction::Up),
+        KeyCode::Down => Some(Direction::Down),
+        KeyCode::Left => Some(Direction::Left),
+        KeyCode::Right => Some(Direction::Right),
+        KeyCode::Home => Some(Direction::UpLeft),
+        KeyCode::PageUp => Some(Direction::UpRight),
+        KeyCode::End => Some(Direction::DownLeft),
+        KeyCode::PageDown => Some(Direction::DownRight),
+        _ => None,
+    }
+}
+
+/// Freezes the game, and `clock`, after a crash or an undo until the player
+/// undoes (again) or, if they're still alive, picks a direction. Returns
+/// whether to carry on playing.
+fn hold<B: Backend + Write>(
+    terminal: &mut Terminal<B>,
+    events: &mut dyn EventSource,
+    config: &Config,
+    game: &mut Game,
+    clock: &mut GameClock,
+    speaker: &mut Speaker,
+    crashed: bool,
+) -> Result<bool> {
+    clock.pause();
+    let carry_on = held(terminal, events, config, game, speaker, crashed);
+    clock.resume();
+    carry_on
+}
+
+fn held<B: Backend + Write>(
+    terminal: &mut Terminal<B>,
+    events: &mut dyn EventSource,
+    config: &Config,
+    game: &mut Game,
+    speaker: &mut Speaker,
+    mut crashed: bool,
+) -> Result<bool> {
+    loop {
+        let text = if crashed {
+            format!("crashed! u to undo ({} left)", game.undos_left())
+        } else {
+            String::from("pick a direction")
+        };
+        let notice = Notice::new(text);
+        draw(
+            terminal,
+            config,
+            game,
+            &Overlay {
+                notice: Some(&notice),
+                hints: config.show_hints.then_some(if crashed {
+                    AppState::Crashed
+                } else {
+                    AppState::Undone
+                }),
+                ..Overlay::default()
+            },
+        )?;
+        speaker.ring(terminal.backend_mut())?;
+        if let Some(ring) = speaker.next_ring() {
+            if !events.poll(ring)? {
+                continue;
+            }
+        }
+
+        if let Input::Key(key) = handle_event(events.read()?) {
+            if is_quit(&key) {
+                return Ok(false);
+            }
+            if key.code == KeyCode::Char('u') && game.undo() {
+                crashed = false;
+            } else if let (false, Some(direction)) = (crashed, key_direction(&key)) {
+                game.change_direction(0, direction);
+                return Ok(true);
+            }
+        }
+    }
+}
+
+/// Freezes the game, and `clock`, under a "PAUSED" banner, when `p` or Esc
+/// is pressed or the terminal is out of focus, until a key is pressed.
+/// Returns whether to carry on playing.
+fn pause<B: Backend + Write>(
+    terminal: &mut Terminal<B>,
+    events: &mut dyn EventSource,
+    config: &Config,
+    game: &Game,
+    clock: &mut GameClock,
+) -> Result<bool> {
+    clock.pause();
+    let carry_on = paused(terminal, events, config, game);
+    clock.resume();
+    carry_on
+}
+
+fn paused<B: Backend + Write>(
+    terminal: &mut Terminal<B>,
+    events: &mut dyn EventSource,
+    config: &Config,
+    game: &Game,
+) -> Result<bool> {
+    let notice = Notice::new(String::from("paused"));
+
+    loop {
+        draw(
+            terminal,
+            config,
+            game,
+            &Overlay {
+                notice: Some(&notice),
+                banner: Some("PAUSED"),
+                hints: config.show_hints.then_some(AppState::Paused),
+                ..Overlay::default()
+            },
+        )?;
+
+        if let Input::Key(key) = handle_event(events.read()?) {
+            return Ok(!is_quit(&key));
+        }
+    }
+}
+
+/// The snake falling apart from the tail once it's died, a segment at a
+/// time over `DEATH_DURATION`.
+struct Collapse {
+    length: usize,
+    /// How long each segment stands after the one behind it falls.
+    every: Duration,
+    /// The playing time the snake died at.
+    started: Duration,
+}
+
+impl Collapse {
+    fn new(length: usize, started: Duration) -> Collapse {
+        Collapse {
+            length,
+            every: DEATH_DURATION / length.max(1) as u32,
+            started,
+        }
+    }
+
+    /// How many segments have fallen by `now`, never more than there are.
+    fn fallen(&self, now: Duration) -> usize {
+        let elapsed = now.saturating_sub(self.started);
+        ((elapsed.as_nanos() / self.every.as_nanos()) as usize).min(self.length)
+    }
+
+    /// Whether the whole snake is gone by `now`, ending the animation.
+    fn is_done(&self, now: Duration) -> bool {
+        self.fallen(now) == self.length
+    }
+
+    /// When the next segment falls.
+    fn next_fall(&self, now: Duration) -> Duration {
+        self.started + self.every * (self.fallen(now) + 1) as u32
+    }
+}
+
+/// Plays out the snake falling apart, which any key skips. Returns whether
+/// to go on to the game over screen rather than quit.
+fn collapse<B: Backend + Write>(
+    terminal: &mut Terminal<B>,
+    events: &mut dyn EventSource,
+    config: &Config,
+    game: &Game,
+    clock: &GameClock,
+    speaker: &mut Speaker,
+) -> Result<bool> {
+    let dying = Collapse::new(game.snakes()[0].body().len(), clock.now());
+
+    loop {
+        let now = clock.now();
+        draw(
+            terminal,
+            config,
+            game,
+            &Overlay {
+                collapsed: dying.fallen(now),
+                hints: config.show_hints.then_some(AppState::Dying),
+                ..Overlay::default()
+            },
+        )?;
+        speaker.ring(terminal.backend_mut())?;
+        if dying.is_done(now) {
+            return Ok(true);
+        }
+
+        let fall = dying.next_fall(now).saturating_sub(now);
+        let timeout = speaker.next_ring().map_or(fall, |ring| ring.min(fall));
+        if events.poll(timeout)? {
+            if let Input::Key(key) = handle_event(events.read()?) {
+                return Ok(!is_quit(&key));
+            }
+        }
+    }
+}
+
+/// Which board to start on after a game, when there's another.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Retry {
+    /// A fresh seed, and with it fresh food and obstacles.
+    NewBoard,
+    /// The seed just played, so food and obstacles land where they did.
+    SameBoard,
+}
+
+impl Retry {
+    /// The seed to start the next game on after `game`, `None` for a fresh
+    /// one.
+    fn seed(self, game: &Game) -> Option<u64> {
+        match self {
+            Retry::NewBoard => None,
+            Retry::SameBoard => Some(game.replay().seed()),
+        }
+    }
+}
+
+/// Shows the final board with the configured game-over message, and what the
+/// snake ran into beneath it, until a key is pressed, returning which board
+/// to start on next if the round allows another game and one was picked.
+/// The final score, against the round's high score, and the score's
+/// `history` are drawn beneath the message where there's room.
+#[allow(clippy::too_many_arguments)]
+fn game_over<B: Backend + Write>(
+    terminal: &mut Terminal<B>,
+    events: &mut dyn EventSource,
+    config: &Config,
+    game: &Game,
+    speaker: &mut Speaker,
+    over: GameOver,
+    round: &Round,
+    history: &[u32],
+) -> Result<Option<Retry>> {
+    let score = game.snakes()[0].score();
+    let score_line = match round.high_score {
+        Some(high) if score > high => format!("score: {}, a new high score!", score),
+        Some(high) => format!("score: {}  high score: {}", score, high),
+        None => format!("score: {}", score),
+    };
+    let mut extras = vec![score_line];
+    if !history.is_empty() {
+        extras.push(sparkline(history));
+    }
+
+    let play = BoardLayout::new(config).play_rect(game.dimensions());
+    let mut banner = fit_text(&config.game_over_banner(), play.width, play.height);
+    for extra in extras {
+        let longer = format!("{}\n{}", banner, extra);
+        let (width, height) = text_size(&longer);
+        if width > play.width || height > play.height {
+            break;
+        }
+        banner = longer;
+    }
+    let retry = round.retry;
+    let notice = Notice::new(format!(
+        "your snake {}, seed {}",
+        over.reason,
+        game.replay().seed()
+    ));
+
+    loop {
+        draw(
+            terminal,
+            config,
+            game,
+            &Overlay {
+                banner: Some(&banner),
+                notice: Some(&notice),
+                hints: config.show_hints.then_some(AppState::GameOver { retry }),
+                ..Overlay::default()
+            },
+        )?;
+        speaker.ring(terminal.backend_mut())?;
+        // Redrawn for each ring of the death sound still to come.
+        if let Some(ring) = speaker.next_ring() {
+            if !events.poll(ring)? {
+                continue;
+            }
+        }
+
+        if let Input::Key(key) = handle_event(events.read()?) {
+            return Ok(match key.code {
+                KeyCode::Char('r') if retry => Some(Retry::NewBoard),
+                KeyCode::Char('t') if retry => Some(Retry::SameBoard),
+                _ => None,
+            });
+        }
+    }
+}
+
+/// Shows `banner` over the board until a key is pressed, returning whether
+/// it wasn't a quit key.
+fn interlude<B: Backend + Write>(
+    terminal: &mut Terminal<B>,
+    events: &mut dyn EventSource,
+    config: &Config,
+    game: &Game,
+    banner: &str,
+    state: AppState,
+) -> Result<bool> {
+    loop {
+        draw(
+            terminal,
+            config,
+            game,
+            &Overlay {
+                banner: Some(banner),
+                hints: config.show_hints.then_some(state),
+                ..Overlay::default()
+            },
+        )?;
+
+        if let Input::Key(key) = handle_event(events.read()?) {
+            return Ok(!is_quit(&key));
+        }
+    }
+}
+
+/// How a call to `play` ended.
+enum Outcome {
+    Quit,
+    /// The game ended, and the player may have asked for another.
+    Over(Option<Retry>),
+    /// The snake reached the target score or ate the last of the scripted
+    /// food.
+    Won,
+}
+
+/// What a call to `play` plays towards.
+#[derive(Default)]
+struct Round<'a> {
+    tick: Duration,
+    /// A previous run's snake for each tick, to race against.
+    ghost: Option<&'a [Vec<Vector>]>,
+    /// The score that wins the round, if any.
+    target: Option<u32>,
+    /// Whether to keep redrawing through each tick so the heads glide.
+    smooth: bool,
+    /// Whether to point out the nearest food.
+    assist: bool,
+    /// How far from the head the board can be seen, if not all of it.
+    fog: Option<u16>,
+    /// The trainer's record, if this is the trainer.
+    best_turns: Option<u32>,
+    /// The best score so far, if there is one to beat.
+    high_score: Option<u32>,
+    /// Whether the game over screen offers another game.
+    retry: bool,
+}
+
+/// A single-player game set up from `config`.
+fn local_game(
+    config: &Config,
+    cli: &Cli,
+    layout: &Layout,
+    seed: Option<u64>,
+    obstacles: u16,
+    undos: u32,
+    tick: Duration,
+) -> Result<Game> {
+    let seed = seed.unwrap_or_else(rand::random);
+    let mut game = Game::with_dimensions(1, seed, cli.dimensions())?;
+    game.set_food_table(config.food_table.clone());
+    if !layout.obstacles.is_empty() {
+        game.set_layout(layout.obstacles.clone())?;
+    }
+    if !layout.terrain.is_empty() {
+        game.set_terrain(layout.terrain.clone())?;
+    }
+    game.place_obstacles(obstacles);
+    game.set_lives(cli.lives);
+    game.regenerate_obstacles_on_respawn(config.regen_obstacles_on_respawn);
+    game.shrink_every(ticks(config.shrink_interval, tick));
+    game.set_food_delay(ticks(config.food_respawn_delay, tick));
+    game.set_grace_period(ticks(config.grace_period, tick));
+    game.set_hunger_interval(ticks(config.hunger_interval, tick));
+    game.set_difficulty(cli.difficulty.or(config.difficulty));
+    game.set_time_limit(
+        cli.time_limit
+            .map_or(0, |secs| ticks(Duration::from_secs(secs), tick)),
+    );
+    game.set_food_count(config.food_count);
+    game.keep_food_clear_of_heads(config.food_clear_of_head);
+    game.set_min_length(config.min_length);
+    game.allow_reverse(config.allow_reverse);
+    game.disable_growth(config.no_growth);
+    game.disable_self_collision(cli.ghost);
+    game.set_wrap(config.wrap_x && !cli.walls, config.wrap_y && !cli.walls);
+    game.set_bounce(config.bounce);
+    game.enable_combos(config.combos);
+    game.set_wrap_penalty(config.wrap_penalty);
+    game.allow_undos(undos);
+    game.allow_diagonals(cli.diagonal);
+    if !config.food_path.is_empty() {
+        game.set_food_source(FoodSource::Scripted(config.food_path.clone()))
+            .context("food_path")?;
+    }
+    Ok(game)
+}
+
+/// How many ticks in a row a game loop has taken late without drawing them.
+#[derive(Default)]
+struct CatchUp {
+    caught_up: u32,
+}
+
+impl CatchUp {
+    /// Moves `next_tick` on to the tick after, and returns whether that's
+    /// already due by `now` and should be taken straight away. After
+    /// `MAX_CATCH_UP` in a row the time lost is given up on, and the next
+    /// tick is a whole `tick` from now.
+    fn behind(&mut self, next_tick: &mut Instant, now: Instant, tick: Duration) -> bool {
+        *next_tick += tick;
+        if now >= *next_tick {
+            if self.caught_up < MAX_CATCH_UP {
+                self.caught_up += 1;
+                return true;
+            }
+            *next_tick = now + tick;
+        }
+        self.caught_up = 0;
+        false
+    }
+
+    /// Whether the last tick was taken late, so isn't worth drawing.
+    fn catching_up(&self) -> bool {
+        self.caught_up > 0
+    }
+}
+
+/// What `play` draws along with `game`.
+fn play_overlay<'a>(
+    config: &Config,
+    game: &Game,
+    round: &'a Round,
+    trail: &'a Trail,
+    notice: Option<&'a Notice>,
+    flash_until: u64,
+    minimap: bool,
+) -> Overlay<'a> {
+    Overlay {
+        ghost: round
+            .ghost
+            .and_then(|frames| frames.get(game.ticks() as usize))
+            .map(Vec::as_slice),
+        trail: config.trail.then_some(trail),
+        notice,
+        hints: config.show_hints.then_some(AppState::Playing {
+            undo: game.undos_left() > 0,
+            diagonal: game.diagonal(),
+            screenshot: cfg!(feature = "screenshot"),
+            minimap: true,
+            theme_key: Some(config.theme_key),
+        }),
+        tick: Some(round.tick),
+        target: round.target,
+        minimap,
+        flash: config.eat_flash && game.ticks() < flash_until,
+        compass: round.assist,
+        fog: round.fog,
+        best_turns: round.best_turns,
+        high_score: round.high_score,
+        ..Overlay::default()
+    }
+}
+
+/// Runs `game` until the player quits, the game ends or the round's target
+/// is reached. A theme picked with the theme key is kept in `config`, so it
+/// lasts into the next game.
+fn play<B: Backend + Write>(
+    terminal: &mut Terminal<B>,
+    events: &mut dyn EventSource,
+    config: &mut Config,
+    game: &mut Game,
+    round: &Round,
+) -> Result<Outcome> {
+    let mut notice: Option<Notice> = None;
+    let mut capture = false;
+    let mut screenshot: Option<Receiver<Result<PathBuf>>> = None;
+    let mut trail = Trail::default();
+    let mut history = ScoreHistory::new(round.tick);
+    let mut debounce = Debounce::new(config.repeat_debounce);
+    history.update(game);
+    let mut minimap = config.minimap;
+    let mut clock = GameClock::new();
+    let mut flash_until = 0;
+    let mut speaker = Speaker::new(config.sound);
+    // When the next tick is due, and how many ticks in a row have been taken
+    // late without drawing.
+    let mut next_tick = Instant::now();
+    let mut catch_up = CatchUp::default();
+
+    loop {
+        if let Some(result) = screenshot.as_ref().and_then(|rx| rx.try_recv().ok()) {
+            notice = Some(Notice::timed(
+                match result {
+                    Ok(path) => format!("saved {}", path.display()),
+                    Err(err) => format!("screenshot failed: {}", err),
+                },
+                &clock,
+            ));
+            screenshot = None;
+        }
+
+        if notice.as_ref().is_some_and(|notice| notice.expired(&clock)) {
+            notice = None;
+        }
+
+        if !catch_up.catching_up() {
+            let overlay = play_overlay(
+                config,
+                game,
+                round,
+                &trail,
+                notice.as_ref(),
+                flash_until,
+                minimap,
+            );
+            let frame = draw(terminal, config, game, &overlay)?;
+
+            if capture {
+                screenshot = Some(screenshot::capture(frame.clone()));
+                capture = false;
+            }
+            speaker.ring(terminal.backend_mut())?;
+        }
+
+        let before: Vec<Vector> = game.snakes()[0].body().iter().cloned().collect();
+        match game.tick() {
+            Ok(happened) => {
+                trail.update(&before, game);
+                history.update(game);
+                for event in happened {
+                    match event {
+                        GameEvent::LostLife(reason) => {
+                            speaker.play(Sound::Died);
+                            notice = Some(Notice::timed(
+                                format!(
+                                    "your snake {}, lives left: {}",
+                                    reason,
+                                    game.spare_lives() + 1
+                                ),
+                                &clock,
+                            ));
+                        }
+                        GameEvent::AteFood { player: 0, kind } => {
+                            speaker.play(Sound::Ate(kind));
+                            flash_until = game.ticks() + EAT_FLASH_TICKS;
+                        }
+                        _ => (),
+                    }
+                }
+            }
+            Err(over) => {
+                speaker.play(Sound::Died);
+                if game.undos_left() == 0 {
+                    if config.death_animation
+                        && !collapse(terminal, events, config, game, &clock, &mut speaker)?
+                    {
+                        return Ok(Outcome::Quit);
+                    }
+                    let retry = game_over(
+                        terminal,
+                        events,
+                        config,
+                        game,
+                        &mut speaker,
+                        over,
+                        round,
+                        &history.samples(),
+                    )?;
+                    return Ok(Outcome::Over(retry));
+                }
+                if !hold(
+                    terminal,
+                    events,
+                    config,
+                    game,
+                    &mut clock,
+                    &mut speaker,
+                    true,
+                )? {
+                    return Ok(Outcome::Quit);
+                }
+                next_tick = Instant::now();
+            }
+        }
+
+        if round
+            .target
+            .is_some_and(|target| game.snakes()[0].score() >= target)
+            || game.state() == GameState::Cleared
+        {
+            return Ok(Outcome::Won);
+        }
+
+        // Running behind, say because drawing took longer than a tick, the
+        // missed ticks are taken straight away without drawing them. Only a
+        // few though, so a long stall doesn't come out as the snake leaping
+        // across the board. Terrain the snake has run over stretches or
+        // shortens the tick for a while.
+        let tick = round.tick.mul_f64(game.interval_scale());
+        if catch_up.behind(&mut next_tick, Instant::now(), tick) {
+            continue;
+        }
+
+        // Keys pressed during the tick are only read here, so however late in
+        // it they come their turn is taken on the next tick, which starts on
+        // time unless `instant_turns` brings it forward.
+        let started = next_tick - tick;
+        while let Some(timeout) = next_tick.checked_duration_since(Instant::now()) {
+            if !events.poll(if round.smooth {
+                timeout.min(FRAME)
+            } else {
+                timeout
+            })? {
+                if round.smooth {
+                    let fraction = Instant::now()
+                        .saturating_duration_since(started)
+                        .as_secs_f64()
+                        / tick.as_secs_f64();
+                    let overlay = Overlay {
+                        glide: Some(fraction),
+                        ..play_overlay(
+                            config,
+                            game,
+                            round,
+                            &trail,
+                            notice.as_ref(),
+                            flash_until,
+                            minimap,
+                        )
+                    };
+                    draw(terminal, config, game, &overlay)?;
+                    continue;
+                }
+                break;
+            }
+
+            match handle_event(events.read()?) {
+                Input::FocusLost => {
+                    if !pause(terminal, events, config, game, &mut clock)? {
+                        return Ok(Outcome::Quit);
+                    }
+                    next_tick = Instant::now();
+                    break;
+                }
+                Input::Key(key) => {
+                    if is_quit(&key) {
+                        return Ok(Outcome::Quit);
+                    }
+                    if key.code == KeyCode::Char('u') && game.undo() {
+                        if !hold(
+                            terminal,
+                            events,
+                            config,
+                            game,
+                            &mut clock,
+                            &mut speaker,
+                            false,
+                        )? {
+                            return Ok(Outcome::Quit);
+                        }
+                        next_tick = Instant::now();
+                        break;
+                    }
+                    if matches!(key.code, KeyCode::Char('p') | KeyCode::Esc) {
+                        if !pause(terminal, events, config, game, &mut clock)? {
+                            return Ok(Outcome::Quit);
+                        }
+                        next_tick = Instant::now();
+                        break;
+                    }
+                    if key.code == KeyCode::F(12) {
+                        capture = true;
+                    }
+                    if key.code == KeyCode::Char('m') {
+                        minimap = !minimap;
+                    }
+                    if key.code == KeyCode::Char(config.theme_key) {
+                        config.theme = theme::next(config.theme);
+                        let overlay = play_overlay(
+                            config,
+                            game,
+                            round,
+                            &trail,
+                            notice.as_ref(),
+                            flash_until,
+                            minimap,
+                        );
+                        draw(terminal, config, game, &overlay)?;
+                    }
+                    if let Some(direction) =
+                        key_direction(&key).filter(|d| debounce.accept(*d, clock.now()))
+                    {
+                        if game.change_direction(0, direction) && config.instant_turns {
+                            next_tick = Instant::now();
+                            break;
+                        }
+                    }
+                }
+                Input::Resize => {
+                    let overlay = play_overlay(
+                        config,
+                        game,
+                        round,
+                        &trail,
+                        notice.as_ref(),
+                        flash_until,
+                        minimap,
+                    );
+                    draw(terminal, config, game, &overlay)?;
+                }
+                Input::Ignored => (),
+            }
+        }
+    }
+}
+
+/// Plays single-player games until the player stops asking for another
+/// from the game over screen.
+fn run_local(
+    terminal: &mut Term,
+    events: &mut dyn EventSource,
+    config: &mut Config,
+    cli: &Cli,
+    layout: &Layout,
+) -> Result<()> {
+    let mut seed = cli.seed;
+
+    loop {
+        let tick = cli.tick();
+        let mut game = local_game(config, cli, layout, seed, config.obstacles, cli.undo, tick)?;
+        let best = best::load();
+        // A run on a board of another size, or from another version of the
+        // game, can't be raced.
+        let ghost = best
+            .as_ref()
+            .filter(|run| run.replay.is_current() && run.replay.dimensions() == game.dimensions())
+            .map(|run| run.replay.frames());
+
+        let outcome = play(
+            terminal,
+            events,
+            config,
+            &mut game,
+            &Round {
+                tick,
+                ghost: ghost.as_deref(),
+                smooth: cli.smooth,
+                assist: cli.assist,
+                fog: cli.fog,
+                high_score: best.as_ref().map(|run| run.score),
+                retry: true,
+                ..Round::default()
+            },
+        )?;
+        if let Outcome::Won = outcome {
+            interlude(
+                terminal,
+                events,
+                config,
+                &game,
+                "path complete!",
+                AppState::GameOver { retry: false },
+            )?;
+        }
+
+        // Failing to save the score is no reason to stop a retry.
+        let score = game.snakes()[0].score();
+        if let Err(err) = best::save_score(score, game.snakes()[0].body().len(), Mode::of(cli)) {
+            warn!("couldn't save the score: {:#}", err);
+        }
+        if best.is_none_or(|run| score > run.score) {
+            if let Err(err) = best::save(&best::BestRun {
+                score,
+                replay: game.replay().clone(),
+            }) {
+                warn!("couldn't save the best run: {:#}", err);
+            }
+        }
+
+        seed = match outcome {
+            Outcome::Over(Some(retry)) => retry.seed(&game),
+            _ => return Ok(()),
+        };
+    }
+}
+
+/// Food for `--trainer`, each piece a diagonal step on from the last so that
+/// every move is a turn. It zigzags from where the snake starts to the far
+/// side, drops a few rows and zigzags back, for as many rows as the board
+/// has.
+fn serpentine(dimensions: &Dimensions) -> Vec<Vector> {
+    let (x, y) = (dimensions.x, dimensions.y);
+    let (mut at_x, mut at_y) = (x.0 + 4, y.0 + 4);
+    let mut food = Vec::new();
+    // Which way the next piece is along each axis.
+    let (mut across, mut up) = (1i32, true);
+
+    loop {
+        let end = if across > 0 { x.1 - 1 } else { x.0 + 1 };
+        while (at_x as i32 - end as i32) * across < 0 {
+            at_x = (at_x as i32 + across) as u16;
+            at_y = if up { at_y - 1 } else { at_y + 1 };
+            up = !up;
+            food.push(Vector(at_x, at_y));
+        }
+
+        // Down three rows to the next band, weaving between the end column
+        // and the one beyond it.
+        if at_y + 4 > y.1 {
+            return food;
+        }
+        let mut side = across;
+        for _ in 0..3 {
+            at_x = (at_x as i32 + side) as u16;
+            at_y += 1;
+            side = -side;
+            food.push(Vector(at_x, at_y));
+        }
+        // Back the other way along rows clear of the ones just used.
+        across = -across;
+        up = false;
+    }
+}
+
+/// Runs the turn trainer until the snake dies or clears the course, keeping
+/// a record of the most turns made.
+fn run_trainer(
+    terminal: &mut Term,
+    events: &mut dyn EventSource,
+    config: &mut Config,
+    cli: &Cli,
+) -> Result<()> {
+    let mut game = Game::with_dimensions(1, rand::random(), cli.dimensions())?;
+    let course = serpentine(game.dimensions());
+    game.set_food_source(FoodSource::Scripted(course))?;
+    let best = best::load_turns();
+
+    let round = Round {
+        tick: cli.tick(),
+        smooth: cli.smooth,
+        best_turns: Some(best),
+        ..Round::default()
+    };
+    if let Outcome::Won = play(terminal, events, config, &mut game, &round)? {
+        let banner = format!("course complete!\n{} turns", game.snakes()[0].turns());
+        interlude(
+            terminal,
+            events,
+            config,
+            &game,
+            &banner,
+            AppState::GameOver { retry: false },
+        )?;
+    }
+
+    let turns = game.snakes()[0].turns();
+    if turns > best {
+        best::save_turns(turns)?;
+    }
+
+    Ok(())
+}
+
+/// Plays `puzzle` until it's cleared or the snake dies, rating a clear with
+/// up to three stars.
+fn run_puzzle(
+    terminal: &mut Term,
+    events: &mut dyn EventSource,
+    config: &mut Config,
+    cli: &Cli,
+    puzzle: &PuzzleDef,
+) -> Result<()> {
+    let mut game = puzzle.game()?;
+    game.allow_undos(cli.undo);
+
+    let round = Round {
+        tick: cli.tick(),
+        smooth: cli.smooth,
+        assist: cli.assist,
+        fog: cli.fog,
+        ..Round::default()
+    };
+    if let Outcome::Won = play(terminal, events, config, &mut game, &round)? {
+        let stars = puzzle.stars(game.ticks());
+        let banner = format!(
+            "cleared in {} ticks\n{}{}",
+            game.ticks(),
+            "★".repeat(stars as usize),
+            "☆".repeat(3 - stars as usize)
+        );
+        interlude(
+            terminal,
+            events,
+            config,
+            &game,
+            &banner,
+            AppState::GameOver { retry: false },
+        )?;
+    }
+
+    Ok(())
+}
+
+/// Plays through the levels of `campaign` in order, stopping early if the
+/// snake runs out of lives.
+fn run_campaign(
+    terminal: &mut Term,
+    events: &mut dyn EventSource,
+    config: &mut Config,
+    mut campaign: Campaign,
+    cli: &Cli,
+    layout: &Layout,
+) -> Result<()> {
+    let mut previous: Option<Snake> = None;
+
+    loop {
+        let level = campaign.level().clone();
+        let mut game = local_game(config, cli, layout, None, level.obstacles, 0, level.tick)?;
+
+        let mut target = level.target;
+        if let Some(snake) = previous.as_ref().filter(|_| config.campaign_carry_over) {
+            game.carry_over(snake);
+            target += snake.score();
+        }
+
+        let round = Round {
+            tick: level.tick,
+            target: Some(target),
+            smooth: cli.smooth,
+            assist: cli.assist,
+            fog: cli.fog,
+            ..Round::default()
+        };
+        match play(terminal, events, config, &mut game, &round)? {
+            Outcome::Won => (),
+            Outcome::Quit | Outcome::Over(_) => return Ok(()),
+        }
+
+        let number = campaign.number();
+        if !campaign.advance() {
+            interlude(
+                terminal,
+                events,
+                config,
+                &game,
+                "campaign complete!",
+                AppState::GameOver { retry: false },
+            )?;
+            return Ok(());
+        }
+        if !interlude(
+            terminal,
+            events,
+            config,
+            &game,
+            &format!("level {} complete!", number),
+            AppState::LevelComplete,
+        )? {
+            return Ok(());
+        }
+
+        previous = Some(game.snakes()[0].clone());
+    }
+}
+
+fn load_puzzle(path: &Path) -> Result<PuzzleDef> {
+    let text = fs::read_to_string(path)
+        .with_context(|| format!("can't open puzzle {}", path.display()))?;
+    PuzzleDef::from_toml(&text).with_context(|| path.display().to_string())
+}
+
+/// Reads the obstacles for `--level` on a board of `dimensions` from `path`,
+/// or stdin if it's `-`.
+fn load_level(path: &Path, dimensions: &Dimensions) -> Result<Layout> {
+    if path == Path::new("-") {
+        return read_layout(io::stdin().lock(), dimensions).context("reading the level from stdin");
+    }
+
+    let file = File::open(path).with_context(|| format!("can't open level {}", path.display()))?;
+    read_layout(BufReader::new(file), dimensions).with_context(|| path.display().to_string())
+}
+
+/// Sends logs to `path`, but only when `RUST_LOG` asks for them so nothing is
+/// opened or formatted otherwise.
+fn init_logging(path: &Path) -> Result<()> {
+    if env::var_os("RUST_LOG").is_none() {
+        return Ok(());
+    }
+
+    let file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .with_context(|| format!("can't open log file {}", path.display()))?;
+
+    env_logger::Builder::from_default_env()
+        .target(env_logger::Target::Pipe(Box::new(LineWriter::new(file))))
+        .init();
+    Ok(())
+}
+
+fn main() -> Result<()> {
+    let mut cli = Cli::parse();
+    cli.adopt_command();
+    if let Some(names) = cli.listed() {
+        for name in names {
+            println!("{}", name);
+        }
+        return Ok(());
+    }
+    init_logging(&cli.log_file)?;
+    let mut config = Config::load(cli.profile.as_deref())?;
+
+    let dimensions = cli.dimensions();
+
+    // Read in full before the terminal is taken over, since it may come from
+    // stdin.
+    let layout = match &cli.level {
+        Some(path) => load_level(path, &dimensions)?,
+        None => Layout::default(),
+    };
+    let puzzle = match &cli.puzzle {
+        Some(path) => Some(load_puzzle(path)?),
+        None => None,
+    };
+    let watch = match &cli.watch {
+        Some(path) => Some(best::BestRun::import(path)?.replay),
+        None => None,
+    };
+
+    if cli.bench_scenario {
+        return bench::run();
+    }
+
+    if cli.print_board {
+        let game = match &puzzle {
+            Some(puzzle) => puzzle.game()?,
+            None => local_game(
+                &config,
+                &cli,
+                &layout,
+                cli.seed,
+                config.obstacles,
+                0,
+                cli.tick(),
+            )?,
+        };
+        print!("{}", game.with_food().grid_snapshot());
+        return Ok(());
+    }
+
+    if cli.solve {
+        let game = local_game(
+            &config,
+            &cli,
+            &layout,
+            cli.seed,
+            config.obstacles,
+            0,
+            cli.tick(),
+        )?;
+        return solve::run(game, cli.tick_limit, cli.record.as_deref());
+    }
+
+    if let Some(port) = cli.serve_ws {
+        return ws::serve(port, &config, &cli);
+    }
+
+    let host = match cli.host {
+        Some(port) => Some(net::Host::listen(port)?),
+        None => None,
+    };
+
+    let (width, height) = BoardLayout::new(&config).board_size(&dimensions)?;
+    let height = height.saturating_add(footer_rows(&config));
+    let (columns, rows) = terminal::size()?;
+    if width > columns || height > rows {
+        return Err(anyhow!(
+            "the board takes up {}x{} but the terminal is only {}x{}",
+            width,
+            height,
+            columns,
+            rows
+        ));
+    }
+
+    let mut terminal = setup_terminal(&config, !cli.no_alt_screen, io::stdout())?;
+    let mut events = TerminalEvents;
+
+    // To tell whether the player picked another theme along the way.
+    let theme = config.theme;
+    let outcome = match (host, &cli.join, &cli.spectate, &puzzle, &watch) {
+        (Some(host), ..) => host.run(&mut terminal, &mut events, &config).map(Some),
+        (None, Some(addr), ..) => net::join(&mut terminal, &mut events, &config, addr).map(Some),
+        (None, None, Some(addr), ..) => {
+            net::spectate(&mut terminal, &mut events, &config, addr).map(Some)
+        }
+        (None, None, None, Some(puzzle), _) => {
+            run_puzzle(&mut terminal, &mut events, &mut config, &cli, puzzle).map(|_| None)
+        }
+        (None, None, None, None, Some(replay)) => {
+            watch::run(&mut terminal, &mut events, &config, replay, cli.tick()).map(|_| None)
+        }
+        (None, None, None, None, None) if cli.scores => {
+            leaderboard::run(&mut terminal, &mut events, &config).map(|_| None)
+        }
+        (None, None, None, None, None) if cli.versus => {
+            versus::run(&mut terminal, &mut events, &config, &cli, &layout).map(Some)
+        }
+        (None, None, None, None, None) if cli.trainer => {
+            run_trainer(&mut terminal, &mut events, &mut config, &cli).map(|_| None)
+        }
+        (None, None, None, None, None) if cli.campaign => run_campaign(
+            &mut terminal,
+            &mut events,
+            &mut config,
+            Campaign::default(),
+            &cli,
+            &layout,
+        )
+        .map(|_| None),
+        (None, None, None, None, None) if config.start_menu => {
+            menu::run(&mut terminal, &mut events, &config, &cli)
+                .and_then(|picked| match picked {
+                    Some((picked, cli)) => {
+                        config = picked;
+                        run_local(&mut terminal, &mut events, &mut config, &cli, &layout)
+                    }
+                    None => Ok(()),
+                })
+                .map(|_| None)
+        }
+        (None, None, None, None, None) => {
+            run_local(&mut terminal, &mut events, &mut config, &cli, &layout).map(|_| None)
+        }
+    };
+
+    restore_terminal(&mut terminal, !cli.no_alt_screen)?;
+
+    if config.persist_theme && config.theme != theme {
+        if let Err(err) = config.save_theme() {
+            warn!("couldn't save the theme: {:#}", err);
+        }
+    }
+
+    if let Some(message) = outcome? {
+        println!("{}", message);
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crossterm::event::{MouseButton, MouseEvent, MouseEventKind};
+    use std::cell::Cell;
+    use tui::backend::TestBackend;
+
+    thread_local! {
+        static WALL: Cell<Option<Instant>> = const { Cell::new(None) };
+    }
+
+    /// A wall clock that only moves when `wait` moves it.
+    fn wall() -> Instant {
+        WALL.with(|cell| {
+            let now = cell.get().unwrap_or_else(Instant::now);
+            cell.set(Some(now));
+            now
+        })
+    }
+
+    fn wait(duration: Duration) {
+        WALL.with(|cell| cell.set(Some(wall() + duration)));
+    }
+
+    #[test]
+    fn clock_stands_still_while_paused() {
+        let mut clock = GameClock::with_wall(wall);
+        wait(Duration::from_secs(1));
+        clock.pause();
+        wait(Duration::from_secs(5));
+        assert_eq!(clock.now(), Duration::from_secs(1));
+        clock.resume();
+        wait(Duration::from_secs(1));
+        assert_eq!(clock.now(), Duration::from_secs(2));
+    }
+
+    #[test]
+    fn snake_stops_falling_apart_while_paused() {
+        let mut clock = GameClock::with_wall(wall);
+        let dying = Collapse::new(4, clock.now());
+        wait(DEATH_DURATION / 2);
+        clock.pause();
+        wait(DEATH_DURATION * 2);
+        assert_eq!(dying.fallen(clock.now()), 2);
+        assert!(!dying.is_done(clock.now()));
+        clock.resume();
+        wait(DEATH_DURATION / 2);
+        assert!(dying.is_done(clock.now()));
+    }
+
+    #[test]
+    fn snake_falls_apart_a_segment_at_a_time() {
+        let dying = Collapse::new(4, Duration::ZERO);
+        let every = DEATH_DURATION / 4;
+        let fallen: Vec<_> = (0..=5).map(|k| dying.fallen(every * k)).collect();
+        assert_eq!(fallen, [0, 1, 2, 3, 4, 4]);
+        assert_eq!(dying.next_fall(every / 2), every);
+        assert_eq!(dying.next_fall(every * 2), every * 3);
+        assert!(!dying.is_done(DEATH_DURATION - ms(1)));
+        assert!(dying.is_done(DEATH_DURATION));
+    }
+
+    /// Events that never come, each wait for one moving the fake wall clock
+    /// on by as long as it was asked to wait.
+    struct Waiting;
+
+    impl EventSource for Waiting {
+        fn poll(&mut self, timeout: Duration) -> Result<bool> {
+            wait(timeout);
+            Ok(false)
+        }
+
+        fn read(&mut self) -> Result<Event> {
+            Err(anyhow!("read without an event to read"))
+        }
+    }
+
+    #[test]
+    fn dying_goes_on_to_game_over_once_the_snake_has_fallen_apart() {
+        let mut terminal = Terminal::new(Screen(TestBackend::new(60, 20))).unwrap();
+        let clock = GameClock::with_wall(wall);
+        let game = Game::with_seed(1, 1);
+        let mut speaker = Speaker::new(sound::SoundProfile::Silent);
+        let config = Config::default();
+        let over = collapse(
+            &mut terminal,
+            &mut Waiting,
+            &config,
+            &game,
+            &clock,
+            &mut speaker,
+        );
+        assert!(over.unwrap());
+        assert_eq!(clock.now(), DEATH_DURATION);
+    }
+
+    #[test]
+    fn a_key_skips_dying_and_quitting_skips_game_over_too() {
+        let mut terminal = Terminal::new(Screen(TestBackend::new(60, 20))).unwrap();
+        let clock = GameClock::with_wall(wall);
+        let game = Game::with_seed(1, 1);
+        let config = Config::default();
+        let mut speaker = Speaker::new(sound::SoundProfile::Silent);
+        for (pressed, over) in [(KeyCode::Char(' '), true), (KeyCode::Char('q'), false)] {
+            let mut events = VecEventSource::new(vec![None, key(pressed)]);
+            let went_on = collapse(
+                &mut terminal,
+                &mut events,
+                &config,
+                &game,
+                &clock,
+                &mut speaker,
+            );
+            assert_eq!(went_on.unwrap(), over);
+        }
+        assert_eq!(clock.now(), Duration::ZERO);
+    }
+
+    #[test]
+    fn clock_pauses_and_resumes_only_once_each() {
+        let mut clock = GameClock::with_wall(wall);
+        clock.resume();
+        wait(Duration::from_secs(1));
+        clock.pause();
+        wait(Duration::from_secs(1));
+        clock.pause();
+        wait(Duration::from_secs(1));
+        clock.resume();
+        clock.resume();
+        assert_eq!(clock.now(), Duration::from_secs(1));
+    }
+
+    #[test]
+    fn notice_outlasts_a_pause() {
+        let mut clock = GameClock::with_wall(wall);
+        let notice = Notice::timed(String::from("saved"), &clock);
+        clock.pause();
+        wait(NOTICE_DURATION * 2);
+        assert!(!notice.expired(&clock));
+        clock.resume();
+        wait(NOTICE_DURATION);
+        assert!(notice.expired(&clock));
+    }
+    use std::{cell::RefCell, rc::Rc};
+    use tui::{terminal::Viewport, TerminalOptions};
+
+    /// A writer whose output can still be read once it's been handed over.
+    #[derive(Clone, Default)]
+    struct Captured(Rc<RefCell<Vec<u8>>>);
+
+    impl Write for Captured {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.borrow_mut().extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    /// The rows drawn for `game` under `overlay` on a 60x20 screen.
+    fn rows(game: &Game, overlay: &Overlay) -> Vec<String> {
+        let mut terminal = Terminal::with_options(
+            CrosstermBackend::new(Captured::default()),
+            TerminalOptions {
+                viewport: Viewport::fixed(Rect::new(0, 0, 60, 20)),
+            },
+        )
+        .unwrap();
+        let buffer = draw(&mut terminal, &Config::default(), game, overlay).unwrap();
+        (0..20)
+            .map(|y| (0..60).map(|x| buffer.get(x, y).symbol.as_str()).collect())
+            .collect()
+    }
+
+    #[test]
+    fn minimap_marks_the_head_beside_the_board() {
+        let game = Game::with_seed(1, 1);
+        let overlay = Overlay {
+            minimap: true,
+            ..Overlay::default()
+        };
+        let screen = rows(&game, &overlay);
+        let map = |row: usize| screen[row].chars().skip(43).take(12).collect::<String>();
+
+        assert!(map(0).contains("map"), "{:#?}", screen);
+        // The head at (5, 5) falls in the third character across and down,
+        // with two cells to a character each way.
+        assert_eq!(map(3), "│  ●       │", "{:#?}", screen);
+
+        let screen = rows(&game, &Overlay::default());
+        assert!(screen.iter().all(|row| !row.contains('●')));
+    }
+
+    #[test]
+    fn wrap_markers_only_show_on_edges_that_wrap() {
+        for (x, y) in [(false, false), (true, false), (false, true), (true, true)] {
+            let mut game = Game::with_seed(1, 1);
+            game.set_wrap(x, y);
+            let screen = rows(&game, &Overlay::default());
+            // Across the middle of the left and right sides, and the right
+            // hand end of the top and bottom.
+            let sides = [screen[6].chars().next(), screen[6].chars().nth(41)];
+            let ends = [screen[0].chars().nth(40), screen[11].chars().nth(40)];
+
+            let side = if x { '↔' } else { '│' };
+            let end = if y { '↕' } else { '─' };
+            assert_eq!(sides, [Some(side); 2], "{:#?}", screen);
+            assert_eq!(ends, [Some(end); 2], "{:#?}", screen);
+            assert_eq!(screen.iter().any(|row| row.contains('↔')), x);
+            assert_eq!(screen.iter().any(|row| row.contains('↕')), y);
+        }
+    }
+
+    #[test]
+    fn a_cell_paints_as_many_columns_as_it_is_wide() {
+        let game = Game::with_seed(1, 1);
+        for width in 1..=4 {
+            let mut terminal = Terminal::with_options(
+                CrosstermBackend::new(Captured::default()),
+                TerminalOptions {
+                    viewport: Viewport::fixed(Rect::new(0, 0, 100, 20)),
+                },
+            )
+            .unwrap();
+            let config = Config {
+                cell_width: width,
+                ..Config::default()
+            };
+            let buffer = draw(&mut terminal, &config, &game, &Overlay::default()).unwrap();
+            let color = config.theme().players[0];
+
+            // The head at (5, 5) is four cells in from the frame each way.
+            let (x, y) = BoardLayout::new(&config).cell_origin(&Vector(5, 5), game.dimensions());
+            assert_eq!((x, y), (1 + 4 * width, 5));
+            let painted: Vec<u16> = (0..buffer.area.width)
+                .filter(|&column| buffer.get(column, y).bg == color)
+                .collect();
+            assert_eq!(
+                painted,
+                (x..x + width).collect::<Vec<_>>(),
+                "width {}",
+                width
+            );
+        }
+    }
+
+    #[test]
+    fn the_main_screen_is_left_for_the_scrollback() {
+        let screen = |alt_screen| {
+            let config = Config {
+                pause_on_focus_loss: false,
+                ..Config::default()
+            };
+            let (mut entered, mut left) = (Vec::new(), Vec::new());
+            enter_screen(&mut entered, &config, alt_screen).unwrap();
+            leave_screen(&mut left, alt_screen, 24).unwrap();
+            (
+                String::from_utf8(entered).unwrap(),
+                String::from_utf8(left).unwrap(),
+            )
+        };
+
+        let (entered, left) = screen(true);
+        assert_eq!(entered, "\x1b[?1049h");
+        assert!(left.ends_with("\x1b[?1049l"), "{:?}", left);
+
+        let (entered, left) = screen(false);
+        assert_eq!(entered, "");
+        assert!(!left.contains("\x1b[?1049"), "{:?}", left);
+        // Beneath the last row of the frame.
+        assert!(left.ends_with("\x1b[24;1H\n"), "{:?}", left);
+    }
+
+    #[test]
+    fn a_frame_renders_to_any_writer() {
+        let out = Captured::default();
+        let mut terminal = Terminal::with_options(
+            CrosstermBackend::new(out.clone()),
+            TerminalOptions {
+                viewport: Viewport::fixed(Rect::new(0, 0, 60, 20)),
+            },
+        )
+        .unwrap();
+        draw(
+            &mut terminal,
+            &Config::default(),
+            &Game::with_seed(1, 1),
+            &Overlay::default(),
+        )
+        .unwrap();
+
+        let written = String::from_utf8(out.0.borrow().clone()).unwrap();
+        assert!(!written.is_empty());
+        // Moving the cursor to the top left, and filling in the snake.
+        assert!(written.contains("\x1b[1;1H┌score:"), "{:?}", written);
+        assert!(written.contains("\x1b[48;5;2m"), "{:?}", written);
+    }
+
+    #[test]
+    fn lines_turn_the_corner_where_the_snake_does() {
+        let cells = [Vector(7, 6), Vector(7, 5), Vector(6, 5)];
+        let body: Vec<&Vector> = cells.iter().collect();
+        let dimensions = Dimensions::default();
+        let glyphs: Vec<&str> = (0..body.len())
+            .map(|i| body_glyph(&body, i, &dimensions))
+            .collect();
+        assert_eq!(glyphs, ["●", "┐", "─"]);
+    }
+
+    #[test]
+    fn lines_run_straight_through_a_wrapping_edge() {
+        let dimensions = Dimensions::default();
+        let (low, high) = dimensions.x;
+        let cells = [Vector(low, 5), Vector(high, 5), Vector(high - 1, 5)];
+        let body: Vec<&Vector> = cells.iter().collect();
+        assert_eq!(body_glyph(&body, 1, &dimensions), "─");
+    }
+
+    fn ms(ms: u64) -> Duration {
+        Duration::from_millis(ms)
+    }
+
+    #[test]
+    fn speed_levels_run_from_slowest_to_fastest() {
+        assert_eq!(speed_level(ms(250), ms(40), ms(250)), 1);
+        assert_eq!(speed_level(ms(145), ms(40), ms(250)), 6);
+        assert_eq!(speed_level(ms(40), ms(40), ms(250)), 10);
+    }
+
+    #[test]
+    fn speed_levels_clamp_intervals_out_of_range() {
+        assert_eq!(speed_level(ms(1000), ms(40), ms(250)), 1);
+        assert_eq!(speed_level(ms(1), ms(40), ms(250)), 10);
+        assert_eq!(speed_level(ms(100), ms(100), ms(100)), 10);
+    }
+
+    #[test]
+    fn the_ring_warns_for_the_lead_time_before_it_closes() {
+        let mut game = Game::with_seed(1, 1);
+        game.shrink_every(10);
+        let warned = |game: &Game| about_to_shrink(game, ms(100), ms(300));
+
+        for _ in 0..6 {
+            assert!(!warned(&game), "tick {}", game.ticks());
+            game.tick().unwrap();
+        }
+        assert!(!warned(&game));
+        game.tick().unwrap();
+        for _ in 0..3 {
+            assert!(warned(&game), "tick {}", game.ticks());
+            game.tick().unwrap();
+        }
+        assert_eq!(game.closed_rings(), 1);
+        assert!(!warned(&game));
+    }
+
+    #[test]
+    fn a_board_that_never_shrinks_never_warns() {
+        let game = Game::with_seed(1, 1);
+        assert!(!about_to_shrink(&game, ms(100), ms(300)));
+    }
+
+    /// Ticks `game` once, keeping `trail` up to date.
+    fn trail_tick(game: &mut Game, trail: &mut Trail) {
+        let before: Vec<Vector> = game.snakes()[0].body().iter().cloned().collect();
+        game.tick().unwrap();
+        trail.update(&before, game);
+    }
+
+    #[test]
+    fn trail_cells_fade_out_and_expire() {
+        let mut game = Game::with_seed(1, 1);
+        let mut trail = Trail::default();
+        trail_tick(&mut game, &mut trail);
+        let ages: Vec<_> = trail.cells(game.ticks()).collect();
+        assert_eq!(ages, [(&Vector(5, 5), 0)]);
+
+        for _ in 1..TRAIL_COLORS.len() {
+            trail_tick(&mut game, &mut trail);
+        }
+        let ages: Vec<usize> = trail.cells(game.ticks()).map(|(_, age)| age).collect();
+        assert_eq!(ages, (0..TRAIL_COLORS.len()).collect::<Vec<_>>());
+
+        trail_tick(&mut game, &mut trail);
+        assert_eq!(trail.cells.len(), TRAIL_COLORS.len());
+        assert!(trail.cells(game.ticks()).all(|(at, _)| at != &Vector(5, 5)));
+    }
+
+    #[test]
+    fn trail_forgets_cells_left_on_undone_ticks() {
+        let mut game = Game::with_seed(1, 1);
+        game.allow_undos(1);
+        let mut trail = Trail::default();
+        trail_tick(&mut game, &mut trail);
+        trail_tick(&mut game, &mut trail);
+        assert!(game.undo());
+        trail.update(&[], &game);
+        let cells: Vec<_> = trail.cells(game.ticks()).collect();
+        assert_eq!(cells, [(&Vector(5, 5), 0)]);
+    }
+
+    #[test]
+    fn food_pulses_between_its_colour_and_a_shade() {
+        let colors: Vec<Color> = (0..PULSE_TICKS * 4)
+            .map(|tick| pulsing_food_color(FoodKind::Golden, tick))
+            .collect();
+        let on = vec![Color::Yellow; PULSE_TICKS as usize];
+        let off = vec![Color::LightYellow; PULSE_TICKS as usize];
+        assert_eq!(colors, [on.clone(), off.clone(), on, off].concat());
+    }
+
+    #[test]
+    fn every_kind_of_food_pulses() {
+        let kinds = [
+            FoodKind::Normal,
+            FoodKind::Golden,
+            FoodKind::Rotten,
+            FoodKind::Magnet,
+            FoodKind::Pickaxe,
+            FoodKind::Speed,
+        ];
+        for kind in kinds {
+            assert_eq!(pulsing_food_color(kind, 0), food_color(kind));
+            assert_ne!(pulsing_food_color(kind, PULSE_TICKS), food_color(kind));
+        }
+    }
+
+    #[test]
+    fn heads_glide_right_and_left_by_half_columns() {
+        let right = |fraction| glide_glyphs(Direction::Right, fraction, 2);
+        assert_eq!(right(0.0), ["", ""]);
+        assert_eq!(right(0.25), ["▌", ""]);
+        assert_eq!(right(0.5), ["█", ""]);
+        assert_eq!(right(0.75), ["█", "▌"]);
+        assert_eq!(right(1.0), ["█", "█"]);
+        assert_eq!(right(1.5), right(1.0));
+
+        let left = |fraction| glide_glyphs(Direction::Left, fraction, 2);
+        assert_eq!(left(0.25), ["", "▐"]);
+        assert_eq!(left(0.75), ["▐", "█"]);
+    }
+
+    #[test]
+    fn heads_glide_up_and_down_by_half_rows() {
+        assert_eq!(glide_glyphs(Direction::Down, 0.1, 2), ["", ""]);
+        assert_eq!(glide_glyphs(Direction::Down, 0.5, 2), ["▀", "▀"]);
+        assert_eq!(glide_glyphs(Direction::Up, 0.5, 3), ["▄", "▄", "▄"]);
+        assert_eq!(glide_glyphs(Direction::Up, 0.9, 1), ["█"]);
+        assert!(glide_glyphs(Direction::DownLeft, 0.5, 2).is_empty());
+    }
+
+    #[test]
+    fn head_flashes_for_a_couple_of_ticks_after_eating() {
+        let round = Round::default();
+        let trail = Trail::default();
+        let mut game = Game::with_seed(1, 1);
+        game.set_food_source(FoodSource::Scripted(vec![Vector(7, 5)]))
+            .unwrap();
+        // The food shows up at the end of the first tick and is eaten on the
+        // second.
+        game.tick().unwrap();
+        game.tick().unwrap();
+        assert_eq!(game.snakes()[0].score(), 1);
+        let flash_until = game.ticks() + EAT_FLASH_TICKS;
+
+        let flashing = |config: &Config, game: &Game| {
+            play_overlay(config, game, &round, &trail, None, flash_until, false).flash
+        };
+        let config = Config::default();
+        let off = Config {
+            eat_flash: false,
+            ..Config::default()
+        };
+        for _ in 0..EAT_FLASH_TICKS {
+            assert!(flashing(&config, &game), "tick {}", game.ticks());
+            assert!(!flashing(&off, &game));
+            game.tick().unwrap();
+        }
+        assert!(!flashing(&config, &game));
+    }
+
+    #[test]
+    fn a_long_stall_only_catches_up_a_few_ticks() {
+        let start = Instant::now();
+        let now = start + ms(100) * 20;
+        let mut next_tick = start;
+        let mut catch_up = CatchUp::default();
+
+        let mut caught_up = 0;
+        while catch_up.behind(&mut next_tick, now, ms(100)) {
+            assert!(catch_up.catching_up());
+            caught_up += 1;
+        }
+        assert_eq!(caught_up, MAX_CATCH_UP);
+        assert!(!catch_up.catching_up());
+        // The rest of the stall is given up on.
+        assert_eq!(next_tick, now + ms(100));
+    }
+
+    #[test]
+    fn keeping_up_never_catches_up() {
+        let start = Instant::now();
+        let mut next_tick = start;
+        let mut catch_up = CatchUp::default();
+        for tick in 1..=5 {
+            let now = start + ms(100) * tick - ms(10);
+            assert!(!catch_up.behind(&mut next_tick, now, ms(100)));
+            assert_eq!(next_tick, start + ms(100) * tick);
+        }
+    }
+
+    #[test]
+    fn text_that_fits_is_left_alone() {
+        assert_eq!(fit_text("game over :(", 20, 5), "game over :(");
+    }
+
+    #[test]
+    fn text_too_wide_wraps_at_spaces_or_is_cut() {
+        assert_eq!(fit_text("game over :(", 5, 5), "game\nover\n:(");
+        assert_eq!(fit_text("##########", 4, 5), "####");
+    }
+
+    #[test]
+    fn text_too_tall_loses_art_from_the_top_before_the_message() {
+        assert_eq!(fit_text("art 1\nart 2\nart 3\nrip", 10, 2), "art 3\nrip");
+    }
+
+    fn parse(args: &[&str]) -> Cli {
+        let mut cli = Cli::try_parse_from([&["rsnake"], args].concat()).unwrap();
+        cli.adopt_command();
+        cli
+    }
+
+    #[test]
+    fn flags_win_over_the_config_file() {
+        let config = Config {
+            difficulty: Some(Difficulty::Easy),
+            wrap_x: true,
+            wrap_y: true,
+            ..Config::default()
+        };
+        let game = |args: &[&str]| {
+            local_game(
+                &config,
+                &parse(args),
+                &Layout::default(),
+                Some(1),
+                0,
+                0,
+                TICK,
+            )
+            .unwrap()
+        };
+        assert_eq!(game(&[]).difficulty(), Some(Difficulty::Easy));
+        assert_eq!(game(&[]).wrap(), (true, true));
+
+        let flagged = game(&["--difficulty", "hard", "--walls"]);
+        assert_eq!(flagged.difficulty(), Some(Difficulty::Hard));
+        assert_eq!(flagged.wrap(), (false, false));
+    }
+
+    #[test]
+    fn listing_flags_pick_which_names_to_print() {
+        assert_eq!(parse(&[]).listed(), None);
+        assert_eq!(
+            parse(&["--list-themes"]).listed(),
+            Some(theme::available_themes())
+        );
+        assert_eq!(
+            parse(&["--list-modes"]).listed(),
+            Some(menu::available_modes())
+        );
+        assert!(Cli::try_parse_from(["rsnake", "--list-themes", "--list-modes"]).is_err());
+    }
+
+    #[test]
+    fn sparkline_bars_are_each_samples_share_of_the_highest() {
+        assert_eq!(sparkline(&[0, 1, 2, 3, 4, 5, 6, 7]), "▁▂▃▄▅▆▇█");
+        assert_eq!(sparkline(&[1, 14, 7]), "▁█▄");
+        assert_eq!(sparkline(&[3, 3, 3]), "███");
+        assert_eq!(sparkline(&[0, 0]), "▁▁");
+        assert_eq!(sparkline(&[]), "");
+    }
+
+    #[test]
+    fn score_history_keeps_only_the_latest_samples() {
+        let mut game = Game::with_seed(1, 1);
+        game.set_food_delay(1000);
+        let mut history = ScoreHistory::new(SCORE_SAMPLE_EVERY / 2);
+        for _ in 0..(SCORE_SAMPLES * 2 + 6) {
+            game.tick().unwrap();
+            history.update(&game);
+        }
+        assert_eq!(history.samples().len(), SCORE_SAMPLES);
+
+        let mut history = ScoreHistory::new(SCORE_SAMPLE_EVERY / 2);
+        for _ in 0..6 {
+            game.tick().unwrap();
+            history.update(&game);
+        }
+        assert_eq!(history.samples().len(), 3);
+    }
+
+    #[test]
+    fn repeats_within_the_window_are_dropped() {
+        let mut debounce = Debounce::new(ms(50));
+        let presses = [
+            (Direction::Up, 0),
+            (Direction::Up, 10),
+            (Direction::Up, 20),
+            (Direction::Up, 65),
+            (Direction::Left, 70),
+            (Direction::Up, 75),
+            (Direction::Up, 200),
+        ];
+        let taken: Vec<_> = presses
+            .into_iter()
+            .map(|(direction, at)| debounce.accept(direction, ms(at)))
+            .collect();
+        // Each repeat counts from the one before, so a held key stays held.
+        assert_eq!(taken, [true, false, false, false, true, true, true]);
+    }
+
+    #[test]
+    fn without_a_window_every_press_is_taken() {
+        let mut debounce = Debounce::new(Duration::ZERO);
+        assert!((0..5).all(|_| debounce.accept(Direction::Up, ms(0))));
+    }
+
+    #[test]
+    fn danger_shades_green_then_yellow_then_red() {
+        let colors: Vec<_> = [0.0, 0.3, 0.34, 0.6, 0.67, 1.0]
+            .into_iter()
+            .map(danger_color)
+            .collect();
+        assert_eq!(
+            colors,
+            [
+                Color::Green,
+                Color::Green,
+                Color::Yellow,
+                Color::Yellow,
+                Color::Red,
+                Color::Red
+            ]
+        );
+    }
+
+    #[test]
+    fn the_border_shades_as_hunger_sets_in() {
+        let config = Config {
+            border_danger: BorderDanger::Hunger,
+            ..Config::default()
+        };
+        let mut game = Game::with_seed(1, 1);
+        // Nothing to warn about yet, so just the border of a wrapping board.
+        assert_eq!(border_color_for(&config, &game), Color::Reset);
+
+        game.set_hunger_interval(9);
+        let mut colors = vec![border_color_for(&config, &game)];
+        for _ in 0..2 {
+            for _ in 0..3 {
+                game.tick().unwrap();
+            }
+            colors.push(border_color_for(&config, &game));
+        }
+        assert_eq!(colors, [Color::Green, Color::Yellow, Color::Red]);
+    }
+
+    #[test]
+    fn without_a_danger_the_border_shows_whether_there_are_walls() {
+        let config = Config::default();
+        let mut game = Game::with_seed(1, 1);
+        assert_eq!(border_color_for(&config, &game), Color::Reset);
+        game.set_wrap(true, false);
+        assert_eq!(border_color_for(&config, &game), WALLED_BORDER_COLOR);
+        game.set_bounce(true);
+        assert_eq!(border_color_for(&config, &game), Color::Reset);
+    }
+
+    /// Where the first `count` pieces of food land in a game started from
+    /// `seed`, played by the autopilot, or as many as land before it dies.
+    fn first_food(seed: Option<u64>, count: usize) -> Vec<Vector> {
+        let cli = parse(&[]);
+        let config = Config::default();
+        let mut game = local_game(&config, &cli, &Layout::default(), seed, 0, 0, TICK).unwrap();
+        let mut seen = Vec::new();
+        while seen.len() < count {
+            for food in game.food() {
+                if !seen.contains(&food.at) {
+                    seen.push(food.at.clone());
+                }
+            }
+            if let Some(direction) = ai::next_move(&game) {
+                game.change_direction(0, direction);
+            }
+            if game.tick().is_err() {
+                break;
+            }
+        }
+        seen.truncate(count);
+        seen
+    }
+
+    #[test]
+    fn retrying_the_same_board_brings_back_the_same_food() {
+        let first = local_game(
+            &Config::default(),
+            &parse(&[]),
+            &Layout::default(),
+            None,
+            0,
+            0,
+            TICK,
+        )
+        .unwrap();
+        let same = Retry::SameBoard.seed(&first);
+        assert_eq!(same, Some(first.replay().seed()));
+        assert_eq!(first_food(same, 5), first_food(same, 5));
+
+        assert_eq!(Retry::NewBoard.seed(&first), None);
+        let fresh: Vec<_> = (0..3).map(|_| first_food(None, 5)).collect();
+        assert!(fresh.iter().any(|food| *food != first_food(same, 5)));
+    }
+
+    #[test]
+    fn in_sight_is_within_the_radius_of_the_head() {
+        let mut game = Game::with_seed(1, 1);
+        game.set_wrap(false, false);
+        let seen = |game: &Game, x, y, radius| in_sight(game, &Vector::new(x, y), radius);
+        assert!(seen(&game, 5, 5, 0));
+        assert!(seen(&game, 8, 5, 3));
+        assert!(seen(&game, 7, 7, 3));
+        assert!(!seen(&game, 8, 6, 3));
+        assert!(!seen(&game, 5, 1, 3));
+    }
+
+    #[test]
+    fn in_sight_looks_across_edges_that_wrap() {
+        let mut game = Game::with_seed(1, 1);
+        game.set_wrap(true, false);
+        assert!(in_sight(&game, &Vector::new(20, 5), 5));
+        assert!(in_sight(&game, &Vector::new(20, 6), 6));
+        assert!(!in_sight(&game, &Vector::new(18, 5), 5));
+
+        game.set_wrap(false, false);
+        assert!(!in_sight(&game, &Vector::new(20, 5), 5));
+    }
+
+    #[test]
+    fn the_serpentine_steps_diagonally_across_the_board() {
+        for dimensions in [Dimensions::default(), Dimensions::new(12, 16)] {
+            let food = serpentine(&dimensions);
+            let start = Vector::new(5, 5);
+            for (from, to) in std::iter::once(&start).chain(&food).zip(&food) {
+                let step = (to.0.abs_diff(from.0), to.1.abs_diff(from.1));
+                assert_eq!(step, (1, 1), "{:?} to {:?}", from, to);
+            }
+            let mut game = Game::with_dimensions(1, 1, dimensions).unwrap();
+            assert!(game.set_food_source(FoodSource::Scripted(food)).is_ok());
+        }
+    }
+
+    #[test]
+    fn board_rects_follow_the_border_and_padding() {
+        let dimensions = Dimensions::default();
+        for (border, padding, width, height) in [
+            (1, 0, 42, 12),
+            (2, 0, 44, 14),
+            (1, 1, 44, 14),
+            (3, 2, 50, 20),
+        ] {
+            let layout = BoardLayout {
+                cell_width: 2,
+                border,
+                padding,
+            };
+            assert_eq!(
+                layout.board_rect(&dimensions),
+                Rect::new(0, 0, width, height)
+            );
+            let edge = border + padding;
+            assert_eq!(
+                layout.play_rect(&dimensions),
+                Rect::new(edge, edge, 40, 10),
+                "{} frames and {} padding",
+                border,
+                padding
+            );
+            assert_eq!(
+                layout.frame_rect(&dimensions, border - 1),
+                Rect::new(
+                    border - 1,
+                    border - 1,
+                    width - 2 * (border - 1),
+                    height - 2 * (border - 1)
+                )
+            );
+            assert_eq!(layout.cell_origin(&Vector(1, 1), &dimensions), (edge, edge));
+            assert_eq!(
+                layout.cell_origin(&Vector(20, 10), &dimensions),
+                (edge + 38, edge + 9)
+            );
+        }
+    }
+    #[test]
+    fn every_kind_of_event_is_sorted() {
+        let key_event = KeyEvent::new(KeyCode::Up, KeyModifiers::NONE);
+        let mouse = MouseEvent {
+            kind: MouseEventKind::Moved,
+            column: 3,
+            row: 4,
+            modifiers: KeyModifiers::NONE,
+        };
+        assert!(matches!(handle_event(Event::Key(key_event)), Input::Key(key) if key == key_event));
+        assert!(matches!(handle_event(Event::Resize(80, 24)), Input::Resize));
+        assert!(matches!(handle_event(Event::FocusLost), Input::FocusLost));
+        assert!(matches!(handle_event(Event::FocusGained), Input::Ignored));
+        assert!(matches!(handle_event(Event::Mouse(mouse)), Input::Ignored));
+        assert!(matches!(
+            handle_event(Event::Paste(String::from("q"))),
+            Input::Ignored
+        ));
+    }
+
+    #[test]
+    fn board_sides_are_kept_in_range() {
+        for side in ["4", "1001"] {
+            assert!(Cli::try_parse_from(["rsnake", "--width", side]).is_err());
+            assert!(Cli::try_parse_from(["rsnake", "--height", side]).is_err());
+        }
+        let cli = Cli::try_parse_from(["rsnake", "--width", "1000", "--height", "5"]).unwrap();
+        assert_eq!(cli.dimensions(), Dimensions::new(1000, 5));
+    }
+
+    #[test]
+    fn network_subcommands_fill_in_the_old_flags() {
+        assert_eq!(parse(&["host", "--port", "4001"]).host, Some(4001));
+        assert_eq!(parse(&["host"]).host, Some(4000));
+        let join = parse(&["join", "192.168.0.2:4000"]);
+        assert_eq!(join.join.as_deref(), Some("192.168.0.2:4000"));
+        assert_eq!(join.host, None);
+        let spectate = parse(&["spectate", "192.168.0.2:4000"]);
+        assert_eq!(spectate.spectate.as_deref(), Some("192.168.0.2:4000"));
+        assert_eq!(
+            parse(&["host", "--profile", "fast"]).profile.as_deref(),
+            Some("fast")
+        );
+    }
+
+    #[test]
+    fn old_network_flags_still_work() {
+        assert_eq!(parse(&["--host", "4000"]).host, Some(4000));
+        assert_eq!(
+            parse(&["--join", "192.168.0.2:4000"]).join.as_deref(),
+            Some("192.168.0.2:4000")
+        );
+    }
+
+    #[test]
+    fn network_subcommands_take_no_single_player_options() {
+        assert!(Cli::try_parse_from(["rsnake", "--versus", "host"]).is_err());
+        assert!(Cli::try_parse_from(["rsnake", "join", "a:1", "--width", "30"]).is_err());
+        assert!(Cli::try_parse_from(["rsnake", "join"]).is_err());
+    }
+
+    #[test]
+    fn board_too_big_to_draw_is_an_error() {
+        let layout = BoardLayout::new(&Config::default());
+        assert_eq!(
+            layout.board_size(&Dimensions::new(20, 10)).unwrap(),
+            (42, 12)
+        );
+        assert!(layout.board_size(&Dimensions::new(1000, 1000)).is_err());
+
+        let wide = BoardLayout {
+            cell_width: 100,
+            ..layout
+        };
+        assert!(wide.board_size(&Dimensions::new(1000, 5)).is_err());
+    }
+
+    /// Events handed out in order, where `None` is a poll that times out.
+    struct VecEventSource(VecDeque<Option<Event>>);
+
+    impl VecEventSource {
+        fn new(events: Vec<Option<Event>>) -> VecEventSource {
+            VecEventSource(events.into())
+        }
+    }
+
+    impl EventSource for VecEventSource {
+        fn poll(&mut self, _timeout: Duration) -> Result<bool> {
+            match self.0.front() {
+                Some(None) => {
+                    self.0.pop_front();
+                    Ok(false)
+                }
+                Some(Some(_)) => Ok(true),
+                None => Err(anyhow!("the script ran out of events")),
+            }
+        }
+
+        fn read(&mut self) -> Result<Event> {
+            self.0
+                .pop_front()
+                .flatten()
+                .ok_or_else(|| anyhow!("read without an event to read"))
+        }
+    }
+
+    /// A test backend that the bell can be rung on.
+    struct Screen(TestBackend);
+
+    impl Backend for Screen {
+        fn draw<'a, I>(&mut self, content: I) -> io::Result<()>
+        where
+            I: Iterator<Item = (u16, u16, &'a tui::buffer::Cell)>,
+        {
+            self.0.draw(content)
+        }
+
+        fn hide_cursor(&mut self) -> io::Result<()> {
+            self.0.hide_cursor()
+        }
+
+        fn show_cursor(&mut self) -> io::Result<()> {
+            self.0.show_cursor()
+        }
+
+        fn get_cursor(&mut self) -> io::Result<(u16, u16)> {
+            self.0.get_cursor()
+        }
+
+        fn set_cursor(&mut self, x: u16, y: u16) -> io::Result<()> {
+            self.0.set_cursor(x, y)
+        }
+
+        fn clear(&mut self) -> io::Result<()> {
+            self.0.clear()
+        }
+
+        fn size(&self) -> io::Result<Rect> {
+            self.0.size()
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Backend::flush(&mut self.0)
+        }
+    }
+
+    impl Write for Screen {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    fn key(code: KeyCode) -> Option<Event> {
+        Some(Event::Key(KeyEvent::new(code, KeyModifiers::NONE)))
+    }
+
+    fn text(terminal: &Terminal<Screen>) -> String {
+        let buffer = terminal.backend().0.buffer();
+        let width = usize::from(buffer.area.width);
+        buffer
+            .content
+            .chunks(width)
+            .map(|row| {
+                row.iter()
+                    .map(|cell| cell.symbol.as_str())
+                    .collect::<String>()
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    #[test]
+    fn pasting_and_the_mouse_dont_steer_or_quit() {
+        let mut terminal = Terminal::new(Screen(TestBackend::new(60, 20))).unwrap();
+        let round = Round {
+            tick: Duration::from_secs(3600),
+            ..Round::default()
+        };
+        let mouse = MouseEvent {
+            kind: MouseEventKind::Down(MouseButton::Left),
+            column: 3,
+            row: 4,
+            modifiers: KeyModifiers::NONE,
+        };
+        let mut events = VecEventSource::new(vec![
+            Some(Event::Paste(String::from("q\x1b[B"))),
+            Some(Event::Mouse(mouse)),
+            Some(Event::Resize(60, 20)),
+            Some(Event::FocusGained),
+            key(KeyCode::Char('q')),
+        ]);
+        let mut game = Game::with_seed(1, 1);
+        let outcome = play(
+            &mut terminal,
+            &mut events,
+            &mut Config::default(),
+            &mut game,
+            &round,
+        )
+        .unwrap();
+        assert!(matches!(outcome, Outcome::Quit));
+        assert!(events.0.is_empty());
+        // Only the first tick, straight on.
+        assert_eq!(game.snakes()[0].direction(), Direction::Right);
+        assert_eq!(game.snakes()[0].body().head(), &Vector(6, 5));
+    }
+
+    #[test]
+    fn scripted_game_moves_eats_dies_and_restarts() {
+        let mut terminal = Terminal::new(Screen(TestBackend::new(60, 20))).unwrap();
+        let mut config = Config::default();
+        let round = Round {
+            tick: Duration::from_secs(3600),
+            retry: true,
+            ..Round::default()
+        };
+        let new_game = || {
+            let mut game = Game::with_dimensions(1, 7, Dimensions::default()).unwrap();
+            game.set_wrap(false, false);
+            game.set_food_source(FoodSource::Scripted(vec![Vector(6, 8), Vector(15, 2)]))
+                .unwrap();
+            game
+        };
+
+        // One tick right, then down through the food and into the bottom
+        // wall, then another game.
+        let mut events = VecEventSource::new(
+            [key(KeyCode::Down)]
+                .into_iter()
+                .chain(std::iter::repeat_n(None, 6))
+                .chain([key(KeyCode::Char('r'))])
+                .collect(),
+        );
+        let mut game = new_game();
+        let outcome = play(&mut terminal, &mut events, &mut config, &mut game, &round).unwrap();
+        assert!(matches!(outcome, Outcome::Over(Some(Retry::NewBoard))));
+        assert_eq!(game.snakes()[0].score(), 1);
+        let screen = text(&terminal);
+        assert!(screen.contains("game over"), "{}", screen);
+        assert!(events.0.is_empty());
+
+        let mut events = VecEventSource::new(vec![key(KeyCode::Char('q'))]);
+        let mut game = new_game();
+        let outcome = play(&mut terminal, &mut events, &mut config, &mut game, &round).unwrap();
+        assert!(matches!(outcome, Outcome::Quit));
+        assert_eq!(game.snakes()[0].score(), 0);
+    }
+
+    #[test]
+    fn a_theme_cycled_in_one_game_lasts_into_the_retry() {
+        let mut terminal = Terminal::new(Screen(TestBackend::new(60, 20))).unwrap();
+        let mut config = Config {
+            theme_key: 't',
+            ..Config::default()
+        };
+        let round = Round {
+            tick: Duration::from_secs(3600),
+            retry: true,
+            ..Round::default()
+        };
+        let new_game = || {
+            let mut game = Game::with_seed(1, 1);
+            game.set_wrap(false, false);
+            game
+        };
+        // Whether any cell of the board is filled in `color`.
+        let drawn_in = |terminal: &Terminal<Screen>, color: Color| {
+            let buffer = terminal.backend().0.buffer();
+            buffer.content.iter().any(|cell| cell.bg == color)
+        };
+
+        // Straight on into the wall, then the same board again.
+        let mut events = VecEventSource::new(
+            [key(KeyCode::Char('t'))]
+                .into_iter()
+                .chain(std::iter::repeat_n(None, 15))
+                .chain([key(KeyCode::Char('t'))])
+                .collect(),
+        );
+        let mut game = new_game();
+        let outcome = play(&mut terminal, &mut events, &mut config, &mut game, &round).unwrap();
+        assert!(matches!(outcome, Outcome::Over(Some(Retry::SameBoard))));
+        assert!(events.0.is_empty());
+        assert_eq!(config.theme, theme::next(0));
+
+        let mut events = VecEventSource::new(vec![key(KeyCode::Char('q'))]);
+        let mut game = new_game();
+        play(&mut terminal, &mut events, &mut config, &mut game, &round).unwrap();
+        assert!(drawn_in(&terminal, config.theme().players[0]));
+        assert!(!drawn_in(&terminal, Config::default().theme().players[0]));
+        let screen = text(&terminal);
+        assert!(screen.contains("t theme"), "{}", screen);
+    }
 }
@@ -0,0 +1,313 @@
+//! The menu shown before a single-player game, for picking the mode, how the
+//! speed picks up, how big the board is and the colours it's drawn in.
+
+use anyhow::Result;
+use crossterm::{event::KeyCode, terminal};
+use serde::{Deserialize, Serialize};
+use tui::{
+    layout::{Alignment, Rect},
+    style::{Color, Modifier, Style},
+    text::{Span, Spans},
+    widgets::{Block, Borders, Paragraph},
+};
+
+use rsnake::{Difficulty, Dimensions};
+
+use crate::{
+    centered_rect, config::Config, fit_hints, footer_rows, handle_event, is_quit,
+    theme::available_themes, AppState, BoardLayout, Cli, EventSource, Input, Term,
+};
+
+/// How long a timed game lasts, unless `--time-limit` says otherwise.
+const TIMED_SECS: u64 = 60;
+
+/// The boards offered, cells across and down, as far as they fit in the
+/// terminal.
+const SIZES: [(u16, u16); 3] = [(20, 10), (30, 15), (40, 20)];
+
+const DIFFICULTIES: [Option<Difficulty>; 4] = [
+    None,
+    Some(Difficulty::Easy),
+    Some(Difficulty::Normal),
+    Some(Difficulty::Hard),
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Mode {
+    /// Edges wrap or not as the config says.
+    Classic,
+    /// Every edge is a wall.
+    Walls,
+    /// The game ends after a while, however it's going.
+    Timed,
+}
+
+pub const MODES: [Mode; 3] = [Mode::Classic, Mode::Walls, Mode::Timed];
+
+/// The names of every mode, in the order the menu offers them.
+pub fn available_modes() -> Vec<&'static str> {
+    MODES.iter().map(Mode::name).collect()
+}
+
+impl Mode {
+    /// The mode `cli` asks for.
+    pub fn of(cli: &Cli) -> Mode {
+        if cli.time_limit.is_some() {
+            Mode::Timed
+        } else if cli.walls {
+            Mode::Walls
+        } else {
+            Mode::Classic
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Mode::Classic => "classic",
+            Mode::Walls => "walls",
+            Mode::Timed => "timed",
+        }
+    }
+}
+
+/// The rows of the menu, each a choice between a few options.
+const ROWS: usize = 4;
+const MODE: usize = 0;
+const DIFFICULTY: usize = 1;
+const SIZE: usize = 2;
+const THEME: usize = 3;
+
+struct Menu {
+    row: usize,
+    /// The option picked on each row.
+    picked: [usize; ROWS],
+    sizes: Vec<(u16, u16)>,
+    secs: u64,
+}
+
+impl Menu {
+    /// A menu starting from what the command line and config already ask
+    /// for.
+    fn new(config: &Config, cli: &Cli, sizes: Vec<(u16, u16)>) -> Menu {
+        let mode = Mode::of(cli);
+        let difficulty = cli.difficulty.or(config.difficulty);
+        let dimensions = cli.dimensions();
+        let size = (dimensions.x.1, dimensions.y.1);
+
+        Menu {
+            row: 0,
+            picked: [
+                MODES.iter().position(|m| *m == mode).unwrap_or(0),
+                DIFFICULTIES
+                    .iter()
+                    .position(|d| *d == difficulty)
+                    .unwrap_or(0),
+                sizes.iter().position(|s| *s == size).unwrap_or(0),
+                config.theme,
+            ],
+            sizes,
+            secs: cli.time_limit.unwrap_or(TIMED_SECS),
+        }
+    }
+
+    fn options(&self, row: usize) -> Vec<String> {
+        match row {
+            MODE => MODES
+                .iter()
+                .map(|mode| match mode {
+                    Mode::Timed => format!("{}, {}s", mode.name(), self.secs),
+                    _ => String::from(mode.name()),
+                })
+                .collect(),
+            DIFFICULTY => DIFFICULTIES
+                .iter()
+                .map(|difficulty| match difficulty {
+                    None => "steady",
+                    Some(Difficulty::Easy) => "easy",
+                    Some(Difficulty::Normal) => "normal",
+                    Some(Difficulty::Hard) => "hard",
+                })
+                .map(String::from)
+                .collect(),
+            SIZE => self
+                .sizes
+                .iter()
+                .map(|(width, height)| format!("{}x{}", width, height))
+                .collect(),
+            _ => available_themes().into_iter().map(String::from).collect(),
+        }
+    }
+
+    /// Moves the current row's pick `by` options along, wrapping round.
+    fn change(&mut self, by: isize) {
+        let count = self.options(self.row).len() as isize;
+        let picked = self.picked[self.row] as isize;
+        self.picked[self.row] = (picked + by).rem_euclid(count) as usize;
+    }
+
+    fn lines(&self) -> Vec<Spans<'static>> {
+        const LABELS: [&str; ROWS] = ["mode", "speed", "board", "theme"];
+
+        LABELS
+            .iter()
+            .enumerate()
+            .map(|(row, label)| {
+                let option = &self.options(row)[self.picked[row]];
+                let style = if row == self.row {
+                    Style::default().add_modifier(Modifier::REVERSED)
+                } else {
+                    Style::default()
+                };
+                Spans::from(vec![
+                    Span::raw(format!(" {:<7}", label)),
+                    Span::styled(format!("‹ {:^11} ›", option), style),
+                    Span::raw(" "),
+                ])
+            })
+            .collect()
+    }
+
+    /// The flags and config the picks add up to.
+    fn picked(&self, config: &Config, cli: &Cli) -> (Config, Cli) {
+        let mode = MODES[self.picked[MODE]];
+        let (width, height) = self.sizes[self.picked[SIZE]];
+
+        let mut cli = cli.clone();
+        cli.walls = mode == Mode::Walls;
+        cli.time_limit = (mode == Mode::Timed).then_some(self.secs);
+        cli.difficulty = None;
+        cli.width = Some(width);
+        cli.height = Some(height);
+
+        let mut config = config.clone();
+        config.difficulty = DIFFICULTIES[self.picked[DIFFICULTY]];
+        config.theme = self.picked[THEME];
+        (config, cli)
+    }
+}
+
+/// The boards from `SIZES` that fit in the terminal, along with the one `cli`
+/// asks for. A `--level` only
+/// fits the board it was read for.
+fn sizes(config: &Config, cli: &Cli) -> Result<Vec<(u16, u16)>> {
+    let dimensions = cli.dimensions();
+    let asked = (dimensions.x.1, dimensions.y.1);
+    if cli.level.is_some() {
+        return Ok(vec![asked]);
+    }
+
+    let (columns, rows) = terminal::size()?;
+    let layout = BoardLayout::new(config);
+    let fits = |&(width, height): &(u16, u16)| {
+        let dimensions = Dimensions::new(width, height);
+        layout.board_size(&dimensions).is_ok_and(|(width, height)| {
+            width <= columns && height.saturating_add(footer_rows(config)) <= rows
+        })
+    };
+
+    let mut sizes: Vec<(u16, u16)> = SIZES.into_iter().filter(fits).collect();
+    if !sizes.contains(&asked) {
+        sizes.push(asked);
+        sizes.sort();
+    }
+    Ok(sizes)
+}
+
+/// Shows the menu until a game is started, returning the config and flags
+/// to play it with, or `None` if the player quit instead.
+pub fn run(
+    terminal: &mut Term,
+    events: &mut dyn EventSource,
+    config: &Config,
+    cli: &Cli,
+) -> Result<Option<(Config, Cli)>> {
+    let mut menu = Menu::new(config, cli, sizes(config, cli)?);
+
+    loop {
+        terminal.draw(|f| {
+            let lines = menu.lines();
+            let width = lines.iter().map(Spans::width).max().unwrap_or(0) as u16 + 2;
+            let height = lines.len() as u16 + 2;
+            let area = centered_rect(f.size(), width, height);
+            f.render_widget(
+                Paragraph::new(lines).block(Block::default().title("rsnake").borders(Borders::ALL)),
+                area,
+            );
+
+            if config.show_hints {
+                let below = area.bottom().min(f.size().bottom().saturating_sub(1));
+                let line = Rect::new(0, below, f.size().width, 1);
+                let hints = fit_hints(&AppState::Menu.key_hints(), line.width);
+                f.render_widget(
+                    Paragraph::new(hints)
+                        .alignment(Alignment::Center)
+                        .style(Style::default().fg(Color::DarkGray)),
+                    line,
+                );
+            }
+        })?;
+
+        match handle_event(events.read()?) {
+            Input::Key(key) if is_quit(&key) => return Ok(None),
+            Input::Key(key) => match key.code {
+                KeyCode::Up => menu.row = (menu.row + ROWS - 1) % ROWS,
+                KeyCode::Down => menu.row = (menu.row + 1) % ROWS,
+                KeyCode::Left => menu.change(-1),
+                KeyCode::Right => menu.change(1),
+                KeyCode::Enter | KeyCode::Char(' ') => return Ok(Some(menu.picked(config, cli))),
+                _ => (),
+            },
+            Input::Resize | Input::FocusLost | Input::Ignored => (),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::Parser;
+
+    fn menu() -> Menu {
+        let cli = Cli::parse_from(["rsnake"]);
+        Menu::new(&Config::default(), &cli, SIZES.to_vec())
+    }
+
+    #[test]
+    fn every_mode_offered_is_listed() {
+        let modes = available_modes();
+        let offered = menu().options(MODE);
+        assert_eq!(offered.len(), modes.len());
+        for (option, name) in offered.iter().zip(&modes) {
+            assert!(
+                option.starts_with(name),
+                "{} isn't listed as {}",
+                option,
+                name
+            );
+        }
+    }
+
+    #[test]
+    fn modes_are_listed_by_the_names_scores_are_saved_under() {
+        assert_eq!(available_modes(), ["classic", "walls", "timed"]);
+        for (mode, name) in MODES.iter().zip(available_modes()) {
+            assert_eq!(serde_json::to_string(mode).unwrap(), format!("{:?}", name));
+        }
+    }
+
+    #[test]
+    fn every_theme_offered_is_listed() {
+        assert_eq!(menu().options(THEME), available_themes());
+    }
+
+    #[test]
+    fn picking_a_theme_sets_it() {
+        let mut menu = menu();
+        menu.row = THEME;
+        menu.change(-1);
+        let cli = Cli::parse_from(["rsnake"]);
+        let (config, _) = menu.picked(&Config::default(), &cli);
+        assert_eq!(config.theme().name, *available_themes().last().unwrap());
+    }
+}
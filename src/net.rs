@@ -0,0 +1,455 @@
+//! Two-player games over TCP.
+//!
+//! The host runs the authoritative `Game` and plays as player one. The joining
+//! client plays as player two: it only sends its `Direction` inputs and renders
+//! the state snapshots the host broadcasts after every tick. Any number of
+//! spectators may also connect, before or during the match, and receive the
+//! same snapshots read-only.
+//!
+//! Every message is a bincode payload prefixed with its length as a big-endian
+//! `u32`. Clients open the connection with `Join` or `Spectate`.
+
+use anyhow::{anyhow, Result};
+use log::info;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use std::{
+    io::{self, Read, Write},
+    net::{TcpListener, TcpStream},
+    sync::mpsc::{self, Receiver, TryRecvError},
+    thread,
+    time::{Duration, Instant},
+};
+
+use rsnake::{Direction, Game};
+
+use crate::{
+    config::Config, draw, handle_event, is_quit, key_direction, AppState, CatchUp, Debounce,
+    EventSource, GameClock, Input, Overlay, Term, TICK,
+};
+
+/// The keys a player in a networked game can use.
+const PLAYING: AppState = AppState::Playing {
+    undo: false,
+    screenshot: false,
+    diagonal: false,
+    minimap: false,
+    theme_key: None,
+};
+
+fn overlay(config: &Config, state: AppState) -> Overlay<'static> {
+    Overlay {
+        hints: config.show_hints.then_some(state),
+        ..Overlay::default()
+    }
+}
+
+/// Frames larger than this are treated as a corrupt stream.
+const MAX_FRAME_LEN: usize = 1 << 20;
+
+/// How long the host waits on a client's opening message before dropping it.
+const HELLO_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Spectators that can't take a snapshot within this long are disconnected
+/// rather than allowed to stall the host's tick.
+const SPECTATOR_WRITE_TIMEOUT: Duration = Duration::from_millis(50);
+
+#[derive(Serialize, Deserialize)]
+enum ClientMessage {
+    Join,
+    Spectate,
+    Input(Direction),
+}
+
+#[derive(Serialize, Deserialize)]
+enum ServerMessage {
+    State(Box<Game>),
+    GameOver(String),
+}
+
+fn write_frame<W: Write, T: Serialize>(writer: &mut W, message: &T) -> Result<()> {
+    let payload = bincode::serialize(message)?;
+    writer.write_all(&(payload.len() as u32).to_be_bytes())?;
+    writer.write_all(&payload)?;
+    writer.flush()?;
+    Ok(())
+}
+
+fn read_frame<R: Read, T: DeserializeOwned>(reader: &mut R) -> Result<T> {
+    let mut len = [0; 4];
+    reader.read_exact(&mut len)?;
+
+    let len = u32::from_be_bytes(len) as usize;
+    if len > MAX_FRAME_LEN {
+        return Err(anyhow!("frame of {} bytes is too large", len));
+    }
+
+    let mut payload = vec![0; len];
+    reader.read_exact(&mut payload)?;
+    Ok(bincode::deserialize(&payload)?)
+}
+
+/// Reads messages on a background thread until the connection drops, at which
+/// point the returned receiver reports `Disconnected`.
+fn spawn_reader<T, R>(mut reader: R) -> Receiver<T>
+where
+    T: DeserializeOwned + Send + 'static,
+    R: Read + Send + 'static,
+{
+    let (tx, rx) = mpsc::channel();
+
+    thread::spawn(move || {
+        while let Ok(message) = read_frame(&mut reader) {
+            if tx.send(message).is_err() {
+                break;
+            }
+        }
+    });
+
+    rx
+}
+
+/// Drains everything the host has sent so far and returns only the newest
+/// snapshot, so a client on a slow connection never renders stale frames.
+/// `Err` carries the reason the match ended.
+fn latest_state(updates: &Receiver<ServerMessage>) -> Result<Option<Game>, String> {
+    let mut latest = None;
+
+    loop {
+        match updates.try_recv() {
+            Ok(ServerMessage::State(game)) => latest = Some(*game),
+            Ok(ServerMessage::GameOver(message)) => return Err(message),
+            Err(TryRecvError::Empty) => return Ok(latest),
+            Err(TryRecvError::Disconnected) => return Err(String::from("host disconnected")),
+        }
+    }
+}
+
+pub struct Host {
+    listener: TcpListener,
+    stream: TcpStream,
+    inputs: Receiver<ClientMessage>,
+    spectators: Vec<TcpStream>,
+}
+
+impl Host {
+    /// Blocks until a second player connects on `port`. Spectators that
+    /// connect in the meantime are kept for the match.
+    pub fn listen(port: u16) -> Result<Host> {
+        let listener = TcpListener::bind(("0.0.0.0", port))?;
+        println!("waiting for a player to join on port {}...", port);
+
+        let mut spectators = Vec::new();
+
+        let stream = loop {
+            let (mut stream, addr) = listener.accept()?;
+            stream.set_read_timeout(Some(HELLO_TIMEOUT))?;
+
+            match read_frame(&mut stream) {
+                Ok(ClientMessage::Join) => {
+                    info!("player 2 joined from {}", addr);
+                    break stream;
+                }
+                Ok(ClientMessage::Spectate) => {
+                    info!("spectator connected from {}", addr);
+                    stream.set_write_timeout(Some(SPECTATOR_WRITE_TIMEOUT))?;
+                    spectators.push(stream);
+                }
+                _ => (),
+            }
+        };
+
+        stream.set_read_timeout(None)?;
+        stream.set_nodelay(true)?;
+        listener.set_nonblocking(true)?;
+
+        Ok(Host {
+            listener,
+            inputs: spawn_reader(stream.try_clone()?),
+            stream,
+            spectators,
+        })
+    }
+
+    /// Once the match is running every new connection spectates, so their
+    /// opening message is never read.
+    fn accept_spectators(&mut self) -> Result<()> {
+        loop {
+            match self.listener.accept() {
+                Ok((stream, addr)) => {
+                    info!("spectator connected from {}", addr);
+                    stream.set_nonblocking(false)?;
+                    stream.set_nodelay(true)?;
+                    stream.set_write_timeout(Some(SPECTATOR_WRITE_TIMEOUT))?;
+                    self.spectators.push(stream);
+                }
+                Err(err) if err.kind() == io::ErrorKind::WouldBlock => return Ok(()),
+                Err(err) => return Err(err.into()),
+            }
+        }
+    }
+
+    /// Sends `message` to everyone, dropping spectators that fail to keep up.
+    /// Only a failure to reach the other player is reported.
+    fn broadcast(&mut self, message: &ServerMessage) -> Result<()> {
+        self.spectators
+            .retain_mut(|spectator| write_frame(spectator, message).is_ok());
+        write_frame(&mut self.stream, message)
+    }
+
+    fn finish(&mut self, message: String) -> String {
+        info!("match over: {}", message);
+        let _ = self.broadcast(&ServerMessage::GameOver(message.clone()));
+        message
+    }
+
+    pub fn run(
+        mut self,
+        terminal: &mut Term,
+        events: &mut dyn EventSource,
+        config: &Config,
+    ) -> Result<String> {
+        let mut game = Game::new(2);
+        game.set_food_table(config.food_table.clone());
+        game.set_food_count(config.food_count);
+        game.keep_food_clear_of_heads(config.food_clear_of_head);
+        game.set_min_length(config.min_length);
+        game.allow_reverse(config.allow_reverse);
+        game.disable_growth(config.no_growth);
+        game.set_wrap(config.wrap_x, config.wrap_y);
+        game.set_bounce(config.bounce);
+        game.enable_combos(config.combos);
+        game.set_wrap_penalty(config.wrap_penalty);
+        game.place_obstacles(config.obstacles);
+        let mut debounce = Debounce::new(config.repeat_debounce);
+        let clock = GameClock::new();
+        // Ticks fall due on a clock of their own, so keys pressed in between
+        // are handled straight away without holding the next tick back.
+        let mut next_tick = Instant::now() + TICK;
+        let mut catch_up = CatchUp::default();
+
+        loop {
+            if !catch_up.catching_up() {
+                draw(terminal, config, &game, &overlay(config, PLAYING))?;
+            }
+
+            let timeout = next_tick.saturating_duration_since(Instant::now());
+            if events.poll(timeout)? {
+                if let Input::Key(key) = handle_event(events.read()?) {
+                    if is_quit(&key) {
+                        return Ok(self.finish(String::from("player 1 left the game")));
+                    }
+                    if let Some(direction) =
+                        key_direction(&key).filter(|d| debounce.accept(*d, clock.now()))
+                    {
+                        game.change_direction(0, direction);
+                    }
+                }
+                continue;
+            }
+
+            loop {
+                match self.inputs.try_recv() {
+                    Ok(ClientMessage::Input(direction)) => {
+                        game.change_direction(1, direction);
+                    }
+                    Ok(_) => (),
+                    Err(TryRecvError::Empty) => break,
+                    Err(TryRecvError::Disconnected) => {
+                        return Ok(self.finish(String::from("player 2 disconnected")))
+                    }
+                }
+            }
+
+            if let Err(err) = game.tick() {
+                return Ok(self.finish(err.to_string()));
+            }
+
+            self.accept_spectators()?;
+            if self
+                .broadcast(&ServerMessage::State(Box::new(game.clone())))
+                .is_err()
+            {
+                return Ok(self.finish(String::from("player 2 disconnected")));
+            }
+            // Fallen behind, the missed ticks are taken without drawing them,
+            // though each is still sent out.
+            catch_up.behind(
+                &mut next_tick,
+                Instant::now(),
+                TICK.mul_f64(game.interval_scale()),
+            );
+        }
+    }
+}
+
+pub fn join(
+    terminal: &mut Term,
+    events: &mut dyn EventSource,
+    config: &Config,
+    addr: &str,
+) -> Result<String> {
+    let mut stream = TcpStream::connect(addr)?;
+    stream.set_nodelay(true)?;
+    write_frame(&mut stream, &ClientMessage::Join)?;
+
+    let updates = spawn_reader(stream.try_clone()?);
+    let mut debounce = Debounce::new(config.repeat_debounce);
+    let clock = GameClock::new();
+
+    loop {
+        match latest_state(&updates) {
+            Ok(Some(game)) => {
+                draw(terminal, config, &game, &overlay(config, PLAYING))?;
+            }
+            Ok(None) => (),
+            Err(message) => return Ok(message),
+        }
+
+        if events.poll(Duration::from_millis(10))? {
+            if let Input::Key(key) = handle_event(events.read()?) {
+                if is_quit(&key) {
+                    return Ok(String::from("left the game"));
+                }
+                if let Some(direction) =
+                    key_direction(&key).filter(|d| debounce.accept(*d, clock.now()))
+                {
+                    write_frame(&mut stream, &ClientMessage::Input(direction))?;
+                }
+            }
+        }
+    }
+}
+
+/// Watches a hosted match without taking part; only quitting is handled
+/// locally.
+pub fn spectate(
+    terminal: &mut Term,
+    events: &mut dyn EventSource,
+    config: &Config,
+    addr: &str,
+) -> Result<String> {
+    let mut stream = TcpStream::connect(addr)?;
+    write_frame(&mut stream, &ClientMessage::Spectate)?;
+
+    let updates = spawn_reader(stream);
+
+    loop {
+        match latest_state(&updates) {
+            Ok(Some(game)) => {
+                draw(
+                    terminal,
+                    config,
+                    &game,
+                    &overlay(config, AppState::Spectating),
+                )?;
+            }
+            Ok(None) => (),
+            Err(message) => return Ok(message),
+        }
+
+        if events.poll(Duration::from_millis(10))? {
+            if let Input::Key(key) = handle_event(events.read()?) {
+                if is_quit(&key) {
+                    return Ok(String::from("stopped spectating"));
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    /// A snapshot after each of the first `ticks` ticks of a match.
+    fn snapshots(ticks: u16) -> Vec<Game> {
+        let mut game = Game::new(2);
+        (0..ticks)
+            .map(|_| {
+                game.tick().unwrap();
+                game.clone()
+            })
+            .collect()
+    }
+
+    /// How far player one's head is from where it started, which is how
+    /// many ticks in a snapshot is.
+    fn ticks(game: &Game) -> u16 {
+        game.snakes()[0].body().head().0 - 5
+    }
+
+    fn stream(messages: &[ServerMessage]) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        for message in messages {
+            write_frame(&mut bytes, message).unwrap();
+        }
+        bytes
+    }
+
+    #[test]
+    fn snapshots_decode_in_order() {
+        let games = snapshots(3);
+        let messages: Vec<ServerMessage> = games
+            .iter()
+            .map(|game| ServerMessage::State(Box::new(game.clone())))
+            .chain([ServerMessage::GameOver(String::from(
+                "player 1 left the game",
+            ))])
+            .collect();
+        let mut reader = Cursor::new(stream(&messages));
+
+        for game in &games {
+            match read_frame(&mut reader).unwrap() {
+                ServerMessage::State(read) => {
+                    assert_eq!(ticks(&read), ticks(game));
+                    assert_eq!(read.snakes()[1].body(), game.snakes()[1].body());
+                    assert_eq!(read.food(), game.food());
+                }
+                ServerMessage::GameOver(_) => panic!("game over before the last snapshot"),
+            }
+        }
+        match read_frame(&mut reader).unwrap() {
+            ServerMessage::GameOver(message) => assert_eq!(message, "player 1 left the game"),
+            ServerMessage::State(_) => panic!("snapshot after the game ended"),
+        }
+        assert!(read_frame::<_, ServerMessage>(&mut reader).is_err());
+    }
+
+    #[test]
+    fn oversized_frames_are_refused() {
+        let mut bytes = ((MAX_FRAME_LEN + 1) as u32).to_be_bytes().to_vec();
+        bytes.extend([0; 8]);
+        assert!(read_frame::<_, ServerMessage>(&mut Cursor::new(bytes)).is_err());
+    }
+
+    #[test]
+    fn a_slow_spectator_skips_to_the_newest_snapshot() {
+        let (tx, rx) = mpsc::channel();
+        for game in snapshots(4) {
+            tx.send(ServerMessage::State(Box::new(game))).unwrap();
+        }
+        assert_eq!(ticks(&latest_state(&rx).unwrap().unwrap()), 4);
+        assert!(latest_state(&rx).unwrap().is_none());
+
+        tx.send(ServerMessage::GameOver(String::from("over")))
+            .unwrap();
+        assert!(matches!(latest_state(&rx), Err(message) if message == "over"));
+    }
+
+    #[test]
+    fn a_dropped_stream_disconnects() {
+        let messages: Vec<ServerMessage> = snapshots(2)
+            .into_iter()
+            .map(|game| ServerMessage::State(Box::new(game)))
+            .collect();
+        let rx = spawn_reader::<ServerMessage, _>(Cursor::new(stream(&messages)));
+        let mut seen = 0;
+        while let Ok(ServerMessage::State(game)) = rx.recv() {
+            seen += 1;
+            assert_eq!(ticks(&game), seen);
+        }
+        assert_eq!(seen, 2);
+        assert!(matches!(latest_state(&rx), Err(message) if message == "host disconnected"));
+    }
+}
@@ -0,0 +1,141 @@
+use anyhow::{anyhow, Context, Result};
+use serde::Deserialize;
+
+use crate::{read_layout, Dimensions, FoodSource, Game, Vector};
+
+/// A board laid out by hand, cleared by eating a fixed run of food and rated
+/// by how few ticks that took. Nothing about it is left to chance, so the
+/// same moves always play out the same way.
+///
+/// ```toml
+/// # Cells across and down, 20 by 10 if left out.
+/// width = 20
+/// height = 10
+///
+/// # Drawn like a `--level` file. The snake starts on the fifth cell of the
+/// # fifth row, heading right.
+/// board = """
+///        #
+///        #
+///        #
+/// """
+///
+/// # Where each piece of food appears, one after another.
+/// food = [[12, 5], [12, 9], [3, 2]]
+///
+/// # The most ticks clearing it can take for one, two and three stars.
+/// stars = [60, 45, 35]
+/// ```
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PuzzleDef {
+    #[serde(default = "default_width")]
+    pub width: u16,
+    #[serde(default = "default_height")]
+    pub height: u16,
+    #[serde(default)]
+    pub board: String,
+    #[serde(default = "wraps")]
+    pub wrap_x: bool,
+    #[serde(default = "wraps")]
+    pub wrap_y: bool,
+    pub food: Vec<Vector>,
+    pub stars: [u64; 3],
+}
+
+impl PuzzleDef {
+    /// Reads a puzzle written as TOML, checking that it can be played.
+    pub fn from_toml(text: &str) -> Result<PuzzleDef> {
+        let puzzle: PuzzleDef = toml::from_str(text)?;
+        puzzle.game()?;
+        Ok(puzzle)
+    }
+
+    /// A fresh game of the puzzle.
+    pub fn game(&self) -> Result<Game> {
+        if self.food.is_empty() {
+            return Err(anyhow!("a puzzle needs at least one piece of food"));
+        }
+        if self.stars.windows(2).any(|pair| pair[1] > pair[0]) {
+            return Err(anyhow!(
+                "stars must each allow no more ticks than the one before"
+            ));
+        }
+
+        let dimensions = Dimensions::new(self.width, self.height);
+        let mut game = Game::with_dimensions(1, 0, dimensions.clone())?;
+        if !self.board.trim().is_empty() {
+            let layout = read_layout(self.board.as_bytes(), &dimensions).context("board")?;
+            game.set_layout(layout.obstacles)?;
+            game.set_terrain(layout.terrain)?;
+        }
+        game.set_wrap(self.wrap_x, self.wrap_y);
+        game.set_food_source(FoodSource::Scripted(self.food.clone()))
+            .context("food")?;
+
+        Ok(game)
+    }
+
+    /// How many stars clearing the puzzle in `ticks` ticks earns, from none
+    /// for missing even the first to three.
+    pub fn stars(&self, ticks: u64) -> u8 {
+        self.stars.iter().filter(|&&most| ticks <= most).count() as u8
+    }
+}
+
+fn default_width() -> u16 {
+    let x = Dimensions::default().x;
+    x.1 - x.0 + 1
+}
+
+fn default_height() -> u16 {
+    let y = Dimensions::default().y;
+    y.1 - y.0 + 1
+}
+
+fn wraps() -> bool {
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::CellKind;
+
+    const PUZZLE: &str = r#"
+        board = "\n       #\n       #\n       #"
+        food = [[12, 5], [12, 9], [3, 2]]
+        stars = [60, 45, 35]
+    "#;
+
+    #[test]
+    fn results_earn_stars_against_the_thresholds() {
+        let puzzle = PuzzleDef::from_toml(PUZZLE).unwrap();
+        let stars: Vec<_> = [1, 35, 36, 45, 46, 60, 61, 1000]
+            .into_iter()
+            .map(|ticks| puzzle.stars(ticks))
+            .collect();
+        assert_eq!(stars, [3, 3, 2, 2, 1, 1, 0, 0]);
+    }
+
+    #[test]
+    fn a_puzzle_sets_up_its_board_and_food() {
+        let game = PuzzleDef::from_toml(PUZZLE).unwrap().game().unwrap();
+        assert_eq!(game.dimensions(), &Dimensions::new(20, 10));
+        let grid = game.grid_snapshot();
+        for y in 2..=4 {
+            assert_eq!(grid.get(&Vector::new(8, y)), Some(CellKind::Obstacle));
+        }
+        let game = game.with_food();
+        assert_eq!(game.food()[0].at, Vector::new(12, 5));
+    }
+
+    #[test]
+    fn unplayable_puzzles_are_rejected() {
+        assert!(PuzzleDef::from_toml("food = []\nstars = [3, 2, 1]").is_err());
+        assert!(PuzzleDef::from_toml("food = [[9, 5]]\nstars = [1, 2, 3]").is_err());
+        assert!(PuzzleDef::from_toml("food = [[99, 5]]\nstars = [3, 2, 1]").is_err());
+        assert!(PuzzleDef::from_toml("food = [[9, 5]]\nstars = [3, 2, 1]\npar = 1").is_err());
+        assert!(PuzzleDef::from_toml("food = [[9, 5]]\nstars = [3, 2, 1]").is_ok());
+    }
+}
@@ -0,0 +1,410 @@
+use serde::{Deserialize, Serialize};
+
+use crate::{Difficulty, Dimensions, Direction, FoodSource, FoodTable, Game, TerrainKind, Vector};
+
+/// Which way of playing a seed out runs are recorded for, bumped whenever
+/// the same seed and turns would come out differently. Version 2 started
+/// putting food on the last row and column, and version 3 starving a snake
+/// that rotten food would shrink away to nothing at a minimum length of one.
+const VERSION: u32 = 3;
+
+/// A single-player run, stored as its seed and the turns taken, which is
+/// enough to play it back exactly.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Replay {
+    #[serde(default = "one")]
+    version: u32,
+    seed: u64,
+    #[serde(default)]
+    dimensions: Dimensions,
+    food_table: FoodTable,
+    /// How many obstacles were placed before the first tick.
+    #[serde(default)]
+    obstacles: u16,
+    #[serde(default = "one")]
+    lives: u32,
+    #[serde(default)]
+    regenerate_obstacles: bool,
+    #[serde(default)]
+    shrink_every: u64,
+    #[serde(default)]
+    food_delay: u64,
+    #[serde(default = "one")]
+    food_count: u32,
+    #[serde(default)]
+    diagonal: bool,
+    #[serde(default = "one")]
+    min_length: u32,
+    #[serde(default)]
+    allow_reverse: bool,
+    #[serde(default)]
+    no_growth: bool,
+    #[serde(default)]
+    combos: bool,
+    #[serde(default)]
+    no_self_collision: bool,
+    #[serde(default)]
+    food_clear_of_heads: bool,
+    #[serde(default = "wraps")]
+    wrap: (bool, bool),
+    #[serde(default)]
+    wrap_penalty: u32,
+    #[serde(default)]
+    bounce: bool,
+    #[serde(default)]
+    food_source: FoodSource,
+    #[serde(default)]
+    grace: u64,
+    #[serde(default)]
+    hunger_every: u64,
+    #[serde(default)]
+    time_limit: u64,
+    #[serde(default)]
+    layout: Vec<Vector>,
+    #[serde(default)]
+    terrain: Vec<(Vector, TerrainKind)>,
+    #[serde(default)]
+    difficulty: Option<Difficulty>,
+    /// Each turn and the tick it was applied on.
+    turns: Vec<(u64, Direction)>,
+    /// How many ticks the run survived.
+    ticks: u64,
+}
+
+impl Replay {
+    pub(crate) fn new(seed: u64, dimensions: Dimensions) -> Replay {
+        Replay {
+            version: VERSION,
+            seed,
+            dimensions,
+            food_table: FoodTable::default(),
+            obstacles: 0,
+            lives: 1,
+            regenerate_obstacles: false,
+            shrink_every: 0,
+            food_delay: 0,
+            food_count: 1,
+            diagonal: false,
+            min_length: 1,
+            allow_reverse: false,
+            no_growth: false,
+            combos: false,
+            no_self_collision: false,
+            food_clear_of_heads: false,
+            wrap: (true, true),
+            wrap_penalty: 0,
+            bounce: false,
+            food_source: FoodSource::Random,
+            grace: 0,
+            hunger_every: 0,
+            time_limit: 0,
+            layout: Vec::new(),
+            terrain: Vec::new(),
+            difficulty: None,
+            turns: Vec::new(),
+            ticks: 0,
+        }
+    }
+
+    pub(crate) fn set_food_table(&mut self, table: FoodTable) {
+        self.food_table = table;
+    }
+
+    pub(crate) fn set_obstacles(&mut self, count: u16) {
+        self.obstacles = count;
+    }
+
+    pub(crate) fn set_lives(&mut self, lives: u32) {
+        self.lives = lives;
+    }
+
+    pub(crate) fn set_regenerate_obstacles(&mut self, regenerate: bool) {
+        self.regenerate_obstacles = regenerate;
+    }
+
+    pub(crate) fn set_layout(&mut self, layout: Vec<Vector>) {
+        self.layout = layout;
+    }
+
+    pub(crate) fn set_terrain(&mut self, terrain: Vec<(Vector, TerrainKind)>) {
+        self.terrain = terrain;
+    }
+
+    pub(crate) fn set_grace(&mut self, ticks: u64) {
+        self.grace = ticks;
+    }
+
+    pub(crate) fn set_hunger_every(&mut self, ticks: u64) {
+        self.hunger_every = ticks;
+    }
+
+    pub(crate) fn set_time_limit(&mut self, ticks: u64) {
+        self.time_limit = ticks;
+    }
+
+    pub(crate) fn set_shrink_every(&mut self, ticks: u64) {
+        self.shrink_every = ticks;
+    }
+
+    pub(crate) fn set_food_delay(&mut self, ticks: u64) {
+        self.food_delay = ticks;
+    }
+
+    pub(crate) fn set_food_count(&mut self, count: u32) {
+        self.food_count = count;
+    }
+
+    pub(crate) fn set_diagonal(&mut self, diagonal: bool) {
+        self.diagonal = diagonal;
+    }
+
+    pub(crate) fn set_min_length(&mut self, length: u32) {
+        self.min_length = length;
+    }
+
+    pub(crate) fn set_food_clear_of_heads(&mut self, clear: bool) {
+        self.food_clear_of_heads = clear;
+    }
+
+    pub(crate) fn set_no_growth(&mut self, disabled: bool) {
+        self.no_growth = disabled;
+    }
+
+    pub(crate) fn set_combos(&mut self, enabled: bool) {
+        self.combos = enabled;
+    }
+
+    pub(crate) fn set_no_self_collision(&mut self, disabled: bool) {
+        self.no_self_collision = disabled;
+    }
+
+    pub(crate) fn set_allow_reverse(&mut self, reverse: bool) {
+        self.allow_reverse = reverse;
+    }
+
+    pub(crate) fn set_wrap(&mut self, x: bool, y: bool) {
+        self.wrap = (x, y);
+    }
+
+    pub(crate) fn set_bounce(&mut self, bounce: bool) {
+        self.bounce = bounce;
+    }
+
+    pub(crate) fn set_wrap_penalty(&mut self, points: u32) {
+        self.wrap_penalty = points;
+    }
+
+    pub(crate) fn set_difficulty(&mut self, difficulty: Option<Difficulty>) {
+        self.difficulty = difficulty;
+    }
+
+    pub(crate) fn set_food_source(&mut self, source: FoodSource) {
+        self.food_source = source;
+    }
+
+    pub(crate) fn record_turn(&mut self, tick: u64, direction: Direction) {
+        self.turns.push((tick, direction));
+    }
+
+    pub(crate) fn record_tick(&mut self) {
+        self.ticks += 1;
+    }
+
+    /// Forgets everything recorded from `tick` on.
+    pub(crate) fn rewind(&mut self, tick: u64) {
+        self.turns.retain(|(at, _)| *at < tick);
+        self.ticks = tick;
+    }
+
+    /// The version of the game the run was recorded in.
+    pub fn version(&self) -> u32 {
+        self.version
+    }
+
+    /// Whether the run plays back as it was played. One from another version
+    /// would send its snake after food that isn't there.
+    pub fn is_current(&self) -> bool {
+        self.version == VERSION
+    }
+
+    /// The version runs are recorded in now.
+    pub fn current_version() -> u32 {
+        VERSION
+    }
+
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    pub fn ticks(&self) -> u64 {
+        self.ticks
+    }
+
+    pub fn dimensions(&self) -> &Dimensions {
+        &self.dimensions
+    }
+
+    /// Plays the run back, returning the snake's body (head first) before the
+    /// first tick and after every tick it survived, so it can be indexed by
+    /// tick.
+    pub fn frames(&self) -> Vec<Vec<Vector>> {
+        let mut game = match self.start() {
+            Some(game) => game,
+            None => return Vec::new(),
+        };
+        let mut frames = vec![game.snakes()[0].body().iter().cloned().collect()];
+
+        while self.advance(&mut game) {
+            frames.push(game.snakes()[0].body().iter().cloned().collect());
+        }
+
+        frames
+    }
+
+    /// The whole game as it stood after `tick` ticks, found by playing the
+    /// run back from the start. `None` past the end of the run.
+    pub fn state_at(&self, tick: u64) -> Option<Game> {
+        let mut game = self.start()?;
+        self.seek(&mut game, tick).then_some(game)
+    }
+
+    /// Plays `game`, somewhere part way through this run, on until it's
+    /// `tick` ticks in. Returns false if the run ends first, leaving `game`
+    /// at the end.
+    pub fn seek(&self, game: &mut Game, tick: u64) -> bool {
+        while game.ticks() < tick {
+            if !self.advance(game) {
+                return false;
+            }
+        }
+        game.ticks() == tick
+    }
+
+    /// The game as it stood before the first tick.
+    pub fn start(&self) -> Option<Game> {
+        // Only a hand-edited run could have a board too small to play on,
+        // and there is nothing to play back from one.
+        let mut game = Game::with_dimensions(1, self.seed, self.dimensions.clone()).ok()?;
+        game.set_food_table(self.food_table.clone());
+        // These were checked against where the snake starts and each other
+        // when the run was played, as the food source below was against the
+        // obstacles.
+        let _ = game.set_layout(self.layout.iter().cloned().collect());
+        let _ = game.set_terrain(self.terrain.iter().cloned().collect());
+        game.place_obstacles(self.obstacles);
+        game.set_lives(self.lives);
+        game.regenerate_obstacles_on_respawn(self.regenerate_obstacles);
+        game.shrink_every(self.shrink_every);
+        game.set_food_delay(self.food_delay);
+        game.set_food_count(self.food_count);
+        game.allow_diagonals(self.diagonal);
+        game.set_min_length(self.min_length);
+        game.allow_reverse(self.allow_reverse);
+        game.disable_growth(self.no_growth);
+        game.enable_combos(self.combos);
+        game.disable_self_collision(self.no_self_collision);
+        game.keep_food_clear_of_heads(self.food_clear_of_heads);
+        game.set_wrap(self.wrap.0, self.wrap.1);
+        game.set_bounce(self.bounce);
+        game.set_wrap_penalty(self.wrap_penalty);
+        let _ = game.set_food_source(self.food_source.clone());
+        game.set_grace_period(self.grace);
+        game.set_hunger_interval(self.hunger_every);
+        game.set_difficulty(self.difficulty);
+        game.set_time_limit(self.time_limit);
+
+        Some(game)
+    }
+
+    /// Takes the turns made on `game`'s next tick and plays it. Returns
+    /// false if the run has no ticks left or the snake crashed on this one.
+    fn advance(&self, game: &mut Game) -> bool {
+        let tick = game.ticks();
+        if tick >= self.ticks {
+            return false;
+        }
+
+        let from = self.turns.partition_point(|(at, _)| *at < tick);
+        for (_, direction) in self.turns[from..].iter().take_while(|(at, _)| *at == tick) {
+            game.change_direction(0, *direction);
+        }
+
+        game.tick().is_ok()
+    }
+}
+
+/// Runs saved before versions, lives, more food or a minimum length existed
+/// were version one and only had the one.
+fn one() -> u32 {
+    1
+}
+
+/// Every edge wrapped before walls existed.
+fn wraps() -> (bool, bool) {
+    (true, true)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A seeded run turning now and then, with the game after each tick as
+    /// it was played.
+    fn played_games() -> Vec<Game> {
+        let mut game = Game::with_seed(1, 11);
+        game.place_obstacles(6);
+        game.set_food_count(3);
+        let mut games = vec![game.clone()];
+        let turns = [
+            Direction::Down,
+            Direction::Left,
+            Direction::Up,
+            Direction::Right,
+        ];
+        for tick in 0..40 {
+            if tick % 5 == 0 {
+                game.change_direction(0, turns[tick / 5 % turns.len()]);
+            }
+            if game.tick().is_err() {
+                break;
+            }
+            games.push(game.clone());
+        }
+        games
+    }
+
+    /// The run from `played_games`, with the snake's body after each tick.
+    fn played() -> (Game, Vec<Vec<Vector>>) {
+        let games = played_games();
+        let frames = games
+            .iter()
+            .map(|game| game.snakes()[0].body().iter().cloned().collect())
+            .collect();
+        (games.last().unwrap().clone(), frames)
+    }
+
+    #[test]
+    fn ghost_frames_match_the_run_tick_for_tick() {
+        let (game, live) = played();
+        let frames = game.replay().frames();
+        assert_eq!(frames.len() as u64, game.ticks() + 1);
+        assert_eq!(frames, live);
+        // Past the end of the run there's no ghost to draw.
+        assert!(frames.get(game.ticks() as usize + 1).is_none());
+    }
+
+    #[test]
+    fn state_at_matches_the_game_as_it_was_played() {
+        let games = played_games();
+        let replay = games.last().unwrap().replay();
+        let json = |game: &Game| serde_json::to_value(game).unwrap();
+        for tick in [0, 1, 7, 20, games.len() - 1] {
+            let state = replay.state_at(tick as u64).unwrap();
+            assert_eq!(state.ticks(), tick as u64);
+            assert_eq!(state.food(), games[tick].food());
+            assert_eq!(state.obstacles(), games[tick].obstacles());
+            assert_eq!(json(&state)["snakes"], json(&games[tick])["snakes"]);
+        }
+        assert!(replay.state_at(games.len() as u64).is_none());
+    }
+}
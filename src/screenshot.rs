@@ -0,0 +1,185 @@
+//! Saving the rendered board as a PNG.
+//!
+//! Encoding needs the `screenshot` cargo feature; without it every capture
+//! reports an error instead.
+
+use anyhow::Result;
+use std::{
+    path::PathBuf,
+    sync::mpsc::{self, Receiver},
+    thread,
+};
+
+use tui::buffer::Buffer;
+
+/// Encodes and writes `buffer` on a background thread so the tick loop isn't
+/// held up. The receiver yields the written path once it's done.
+pub fn capture(buffer: Buffer) -> Receiver<Result<PathBuf>> {
+    let (tx, rx) = mpsc::channel();
+
+    thread::spawn(move || {
+        let _ = tx.send(save(&buffer));
+    });
+
+    rx
+}
+
+#[cfg(not(feature = "screenshot"))]
+fn save(_buffer: &Buffer) -> Result<PathBuf> {
+    Err(anyhow::anyhow!("built without screenshot support"))
+}
+
+#[cfg(feature = "screenshot")]
+fn save(buffer: &Buffer) -> Result<PathBuf> {
+    use image::{ImageFormat, Rgb, RgbImage};
+    use std::io::BufWriter;
+    use tui::style::Color;
+
+    /// Pixels per terminal cell, roughly matching a cell's aspect ratio.
+    const CELL_WIDTH: u32 = 4;
+    const CELL_HEIGHT: u32 = 8;
+
+    let area = drawn_area(buffer);
+    let image = RgbImage::from_fn(
+        area.width as u32 * CELL_WIDTH,
+        area.height as u32 * CELL_HEIGHT,
+        |x, y| {
+            let cell = buffer.get(
+                area.x + (x / CELL_WIDTH) as u16,
+                area.y + (y / CELL_HEIGHT) as u16,
+            );
+
+            // Cells showing a glyph on the default background (the border and
+            // title) are filled with their foreground so they stay visible.
+            let color = match (cell.bg, cell.fg) {
+                (Color::Reset, _) if is_blank(cell) => Color::Reset,
+                (Color::Reset, Color::Reset) => Color::White,
+                (Color::Reset, fg) => fg,
+                (bg, _) => bg,
+            };
+
+            Rgb(rgb(color))
+        },
+    );
+
+    let (path, file) = create_unique()?;
+    image.write_to(&mut BufWriter::new(file), ImageFormat::Png)?;
+    Ok(path)
+}
+
+#[cfg(feature = "screenshot")]
+fn is_blank(cell: &tui::buffer::Cell) -> bool {
+    cell.bg == tui::style::Color::Reset && cell.symbol.trim().is_empty()
+}
+
+/// The smallest area holding everything that was drawn, so the image isn't
+/// padded out to the size of the terminal.
+#[cfg(feature = "screenshot")]
+fn drawn_area(buffer: &Buffer) -> tui::layout::Rect {
+    let area = buffer.area;
+    let (mut right, mut bottom) = (area.x, area.y);
+
+    for y in area.top()..area.bottom() {
+        for x in area.left()..area.right() {
+            if !is_blank(buffer.get(x, y)) {
+                right = right.max(x + 1);
+                bottom = bottom.max(y + 1);
+            }
+        }
+    }
+
+    tui::layout::Rect::new(area.x, area.y, right - area.x, bottom - area.y)
+}
+
+/// Opens a new timestamped file in the current directory, adding a counter
+/// rather than overwriting an earlier screenshot.
+#[cfg(feature = "screenshot")]
+fn create_unique() -> Result<(PathBuf, std::fs::File)> {
+    use std::{
+        fs::OpenOptions,
+        io::ErrorKind,
+        time::{SystemTime, UNIX_EPOCH},
+    };
+
+    let timestamp = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs();
+
+    for n in 0.. {
+        let path = if n == 0 {
+            PathBuf::from(format!("rsnake-{}.png", timestamp))
+        } else {
+            PathBuf::from(format!("rsnake-{}-{}.png", timestamp, n))
+        };
+
+        match OpenOptions::new().write(true).create_new(true).open(&path) {
+            Ok(file) => return Ok((path, file)),
+            Err(err) if err.kind() == ErrorKind::AlreadyExists => continue,
+            Err(err) => return Err(err.into()),
+        }
+    }
+
+    unreachable!()
+}
+
+#[cfg(feature = "screenshot")]
+fn rgb(color: tui::style::Color) -> [u8; 3] {
+    use tui::style::Color;
+
+    match color {
+        Color::Reset | Color::Black => [0, 0, 0],
+        Color::Red => [205, 0, 0],
+        Color::Green => [0, 205, 0],
+        Color::Yellow => [205, 205, 0],
+        Color::Blue => [0, 0, 238],
+        Color::Magenta => [205, 0, 205],
+        Color::Cyan => [0, 205, 205],
+        Color::Gray => [229, 229, 229],
+        Color::DarkGray => [127, 127, 127],
+        Color::LightRed => [255, 0, 0],
+        Color::LightGreen => [0, 255, 0],
+        Color::LightYellow => [255, 255, 0],
+        Color::LightBlue => [92, 92, 255],
+        Color::LightMagenta => [255, 0, 255],
+        Color::LightCyan => [0, 255, 255],
+        Color::White => [255, 255, 255],
+        Color::Rgb(r, g, b) => [r, g, b],
+        Color::Indexed(i) => indexed_rgb(i),
+    }
+}
+
+/// The xterm 256-color palette.
+#[cfg(feature = "screenshot")]
+fn indexed_rgb(i: u8) -> [u8; 3] {
+    use tui::style::Color;
+
+    const BASIC: [Color; 16] = [
+        Color::Black,
+        Color::Red,
+        Color::Green,
+        Color::Yellow,
+        Color::Blue,
+        Color::Magenta,
+        Color::Cyan,
+        Color::Gray,
+        Color::DarkGray,
+        Color::LightRed,
+        Color::LightGreen,
+        Color::LightYellow,
+        Color::LightBlue,
+        Color::LightMagenta,
+        Color::LightCyan,
+        Color::White,
+    ];
+
+    match i {
+        0..=15 => rgb(BASIC[i as usize]),
+        16..=231 => {
+            let level = |n: u8| if n == 0 { 0 } else { 55 + n * 40 };
+            let i = i - 16;
+            [level(i / 36), level(i / 6 % 6), level(i % 6)]
+        }
+        _ => {
+            let level = 8 + (i - 232) * 10;
+            [level, level, level]
+        }
+    }
+}
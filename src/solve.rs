@@ -0,0 +1,96 @@
+//! Lets the computer player loose on a seeded board with no terminal, to
+//! record a run worth sharing.
+
+use anyhow::Result;
+use std::{fmt, path::Path};
+
+use rsnake::{ai, Game, GameOverReason, GameState};
+
+use crate::best::BestRun;
+
+/// Why a solved run stopped.
+enum End {
+    Died(GameOverReason),
+    /// The snake filled the board.
+    Won,
+    /// The snake ate the last of the scripted food.
+    Cleared,
+    /// The tick limit ran out first.
+    Limit,
+}
+
+impl fmt::Display for End {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            End::Died(reason) => write!(f, "died, the snake {}", reason),
+            End::Won => f.write_str("won, the snake filled the board"),
+            End::Cleared => f.write_str("won, the snake ate all the food"),
+            End::Limit => f.write_str("stopped at the tick limit"),
+        }
+    }
+}
+
+/// Plays `game` out for at most `limit` ticks, printing how it went and
+/// writing the replay to `record` if given.
+pub fn run(mut game: Game, limit: u64, record: Option<&Path>) -> Result<()> {
+    let end = loop {
+        if game.is_full() {
+            break End::Won;
+        }
+        if game.state() == GameState::Cleared {
+            break End::Cleared;
+        }
+        if game.ticks() >= limit {
+            break End::Limit;
+        }
+
+        if let Some(direction) = ai::next_move(&game) {
+            game.change_direction(0, direction);
+        }
+
+        if let Err(over) = game.tick() {
+            break End::Died(over.reason);
+        }
+    };
+
+    let score = game.snakes()[0].score();
+    println!(
+        "seed {}: scored {} in {} ticks and {}",
+        game.replay().seed(),
+        score,
+        game.ticks(),
+        end
+    );
+
+    if let Some(path) = record {
+        let run = BestRun {
+            score,
+            replay: game.replay().clone(),
+        };
+        run.export(path)?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{env, fs};
+
+    /// The replay solving the board for `seed` records, as saved.
+    fn solved(seed: u64, name: &str) -> String {
+        let path = env::temp_dir().join(format!("rsnake-{}-{}", std::process::id(), name));
+        run(Game::with_seed(1, seed), 300, Some(&path)).unwrap();
+        let saved = fs::read_to_string(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+        saved
+    }
+
+    #[test]
+    fn solving_the_same_seed_twice_records_the_same_run() {
+        let first = solved(5, "solve-first.json");
+        assert_eq!(first, solved(5, "solve-second.json"));
+        assert_ne!(first, solved(6, "solve-other.json"));
+    }
+}
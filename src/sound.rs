@@ -0,0 +1,143 @@
+//! Noises for what happens during a game.
+
+use serde::Deserialize;
+use std::{
+    io::{self, Write},
+    time::{Duration, Instant},
+};
+
+use rsnake::FoodKind;
+
+/// Long enough between rings that the terminal doesn't run them together.
+const RING_GAP: Duration = Duration::from_millis(150);
+
+/// Which sounds to make, if any.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SoundProfile {
+    #[default]
+    Silent,
+    /// The terminal bell, rung a different number of times for each sound.
+    Bell,
+}
+
+/// Something worth making a noise about.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Sound {
+    Ate(FoodKind),
+    Died,
+}
+
+impl SoundProfile {
+    /// How many times to ring the bell for `sound`.
+    pub fn rings(&self, sound: Sound) -> u32 {
+        match self {
+            SoundProfile::Silent => 0,
+            SoundProfile::Bell => match sound {
+                Sound::Ate(FoodKind::Golden) => 2,
+                Sound::Ate(_) => 1,
+                Sound::Died => 3,
+            },
+        }
+    }
+}
+
+/// Queues up sounds for the loop drawing the board to ring out between
+/// frames, so a bell never lands in the middle of one and the tick loop never
+/// waits on them.
+pub struct Speaker {
+    profile: SoundProfile,
+    /// Rings still to come.
+    pending: u32,
+    /// When the next one can ring, `RING_GAP` after the last.
+    next_at: Instant,
+}
+
+impl Speaker {
+    pub fn new(profile: SoundProfile) -> Speaker {
+        Speaker {
+            profile,
+            pending: 0,
+            next_at: Instant::now(),
+        }
+    }
+
+    /// Queues `sound` behind any still to ring.
+    pub fn play(&mut self, sound: Sound) {
+        self.pending += self.profile.rings(sound);
+    }
+
+    /// Rings the bell on `out` if a ring is due. Call it just after drawing a
+    /// frame to the same terminal.
+    pub fn ring(&mut self, out: &mut impl Write) -> io::Result<()> {
+        let now = Instant::now();
+        if self.pending > 0 && now >= self.next_at {
+            out.write_all(b"\x07")?;
+            out.flush()?;
+            self.pending -= 1;
+            self.next_at = now + RING_GAP;
+        }
+        Ok(())
+    }
+
+    /// How long until the next ring is due, if there's one to come.
+    pub fn next_ring(&self) -> Option<Duration> {
+        (self.pending > 0).then(|| self.next_at.saturating_duration_since(Instant::now()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn each_sound_rings_its_own_count() {
+        assert_eq!(SoundProfile::Bell.rings(Sound::Ate(FoodKind::Normal)), 1);
+        assert_eq!(SoundProfile::Bell.rings(Sound::Ate(FoodKind::Golden)), 2);
+        assert_eq!(SoundProfile::Bell.rings(Sound::Died), 3);
+        assert_eq!(SoundProfile::Silent.rings(Sound::Died), 0);
+    }
+
+    #[test]
+    fn every_kind_of_food_makes_a_noise() {
+        for kind in [
+            FoodKind::Normal,
+            FoodKind::Rotten,
+            FoodKind::Magnet,
+            FoodKind::Pickaxe,
+            FoodKind::Speed,
+        ] {
+            assert_eq!(SoundProfile::Bell.rings(Sound::Ate(kind)), 1, "{:?}", kind);
+        }
+        assert_eq!(SoundProfile::default(), SoundProfile::Silent);
+    }
+
+    #[test]
+    fn sounds_queue_up_behind_each_other() {
+        let mut speaker = Speaker::new(SoundProfile::Bell);
+        speaker.play(Sound::Ate(FoodKind::Golden));
+        speaker.play(Sound::Died);
+        assert_eq!(speaker.pending, 5);
+    }
+
+    #[test]
+    fn rings_one_at_a_time_a_gap_apart() {
+        let mut speaker = Speaker::new(SoundProfile::Bell);
+        speaker.play(Sound::Died);
+        let mut out = Vec::new();
+        speaker.ring(&mut out).unwrap();
+        speaker.ring(&mut out).unwrap();
+        assert_eq!(out, b"\x07");
+        assert!(speaker.next_ring().is_some_and(|wait| wait <= RING_GAP));
+    }
+
+    #[test]
+    fn silence_rings_nothing() {
+        let mut speaker = Speaker::new(SoundProfile::Silent);
+        speaker.play(Sound::Died);
+        let mut out = Vec::new();
+        speaker.ring(&mut out).unwrap();
+        assert!(out.is_empty());
+        assert_eq!(speaker.next_ring(), None);
+    }
+}
@@ -0,0 +1,39 @@
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::collections::HashMap;
+
+use crate::Vector;
+
+/// Floor that changes how fast the snakes go for a while once a head lands
+/// on it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TerrainKind {
+    Fast,
+    Slow,
+}
+
+impl TerrainKind {
+    /// How many times as long each tick takes while the effect lasts.
+    pub fn interval_scale(&self) -> f64 {
+        match self {
+            TerrainKind::Fast => 0.5,
+            TerrainKind::Slow => 2.0,
+        }
+    }
+}
+
+/// Terrain as a list of cells, since JSON only has strings for map keys.
+pub(crate) fn serialize<S: Serializer>(
+    terrain: &HashMap<Vector, TerrainKind>,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    serializer.collect_seq(terrain)
+}
+
+pub(crate) fn deserialize<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<HashMap<Vector, TerrainKind>, D::Error> {
+    Ok(Vec::<(Vector, TerrainKind)>::deserialize(deserializer)?
+        .into_iter()
+        .collect())
+}
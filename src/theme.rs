@@ -0,0 +1,80 @@
+//! The named colour schemes the board can be drawn in.
+
+use tui::style::Color;
+
+/// What the snakes and what they run into are drawn in. Food keeps its own
+/// colours whatever the theme, since they tell its kinds apart.
+#[derive(Debug, PartialEq, Eq)]
+pub struct Theme {
+    pub name: &'static str,
+    pub players: [Color; 2],
+    /// Each player's colour dimmed, for segments out in the fog.
+    pub faded_players: [Color; 2],
+    pub obstacle: Color,
+    pub wall: Color,
+}
+
+/// Every theme there is, the first drawn unless the config picks another.
+pub const THEMES: [Theme; 4] = [
+    Theme {
+        name: "classic",
+        players: [Color::Green, Color::Blue],
+        faded_players: [Color::Indexed(22), Color::Indexed(18)],
+        obstacle: Color::Gray,
+        wall: Color::Gray,
+    },
+    Theme {
+        name: "ocean",
+        players: [Color::LightCyan, Color::LightBlue],
+        faded_players: [Color::Indexed(30), Color::Indexed(25)],
+        obstacle: Color::Indexed(67),
+        wall: Color::Indexed(24),
+    },
+    Theme {
+        name: "ember",
+        players: [Color::Indexed(208), Color::Indexed(160)],
+        faded_players: [Color::Indexed(130), Color::Indexed(88)],
+        obstacle: Color::Indexed(95),
+        wall: Color::Indexed(52),
+    },
+    Theme {
+        name: "mono",
+        players: [Color::White, Color::Gray],
+        faded_players: [Color::DarkGray, Color::DarkGray],
+        obstacle: Color::Gray,
+        wall: Color::DarkGray,
+    },
+];
+
+/// The names of every theme, in the order they're offered.
+pub fn available_themes() -> Vec<&'static str> {
+    THEMES.iter().map(|theme| theme.name).collect()
+}
+
+/// The theme after the one at `index` in `THEMES`, wrapping round to the
+/// first.
+pub fn next(index: usize) -> usize {
+    (index + 1) % THEMES.len()
+}
+
+/// Where the theme called `name` is in `THEMES`.
+pub fn position(name: &str) -> Option<usize> {
+    THEMES.iter().position(|theme| theme.name == name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn next_cycles_through_every_theme_and_wraps() {
+        let mut index = 0;
+        let mut seen = vec![THEMES[index].name];
+        for _ in 1..THEMES.len() {
+            index = next(index);
+            seen.push(THEMES[index].name);
+        }
+        assert_eq!(seen, available_themes());
+        assert_eq!(next(index), 0);
+    }
+}
@@ -0,0 +1,137 @@
+//! Two players on one keyboard, the first steering with the arrow keys and
+//! the second with WASD, until one of them crashes.
+
+use anyhow::Result;
+use std::time::{Duration, Instant};
+
+use crossterm::event::{KeyCode, KeyEvent};
+
+use rsnake::{Direction, Game, GameOver, GameOverReason, Layout};
+
+use crate::{
+    config::Config, draw, handle_event, is_quit, key_direction, ticks, AppState, CatchUp, Cli,
+    Debounce, EventSource, GameClock, Input, Overlay, Term,
+};
+
+fn wasd_direction(key: &KeyEvent) -> Option<Direction> {
+    match key.code {
+        KeyCode::Char('w') | KeyCode::Char('W') => Some(Direction::Up),
+        KeyCode::Char('s') | KeyCode::Char('S') => Some(Direction::Down),
+        KeyCode::Char('a') | KeyCode::Char('A') => Some(Direction::Left),
+        KeyCode::Char('d') | KeyCode::Char('D') => Some(Direction::Right),
+        _ => None,
+    }
+}
+
+fn versus_game(config: &Config, cli: &Cli, layout: &Layout, tick: Duration) -> Result<Game> {
+    let seed = cli.seed.unwrap_or_else(rand::random);
+    let mut game = Game::with_dimensions(2, seed, cli.dimensions())?;
+    game.set_food_table(config.food_table.clone());
+    if !layout.obstacles.is_empty() {
+        game.set_layout(layout.obstacles.clone())?;
+    }
+    game.place_obstacles(config.obstacles);
+    game.set_time_limit(
+        cli.time_limit
+            .map_or(0, |secs| ticks(Duration::from_secs(secs), tick)),
+    );
+    game.set_food_count(config.food_count);
+    game.keep_food_clear_of_heads(config.food_clear_of_head);
+    game.set_min_length(config.min_length);
+    game.allow_reverse(config.allow_reverse);
+    game.disable_growth(config.no_growth);
+    game.set_wrap(config.wrap_x && !cli.walls, config.wrap_y && !cli.walls);
+    game.set_bounce(config.bounce);
+    game.enable_combos(config.combos);
+    game.set_wrap_penalty(config.wrap_penalty);
+    Ok(game)
+}
+
+/// Who won and how, with both scores. A crash loses the game for whoever
+/// made it, except head-on where neither snake gets out of the way; running
+/// out of time goes to the higher score.
+fn result(game: &Game, over: GameOver) -> String {
+    let scores: Vec<u32> = game.snakes().iter().map(|snake| snake.score()).collect();
+    let winner = match over.reason {
+        GameOverReason::HeadOn => None,
+        GameOverReason::TimeUp if scores[0] == scores[1] => None,
+        GameOverReason::TimeUp if scores[0] > scores[1] => Some(0),
+        GameOverReason::TimeUp => Some(1),
+        _ => Some(1 - over.player),
+    };
+    let how = match over.reason {
+        GameOverReason::HeadOn => String::from("the snakes crashed head-on"),
+        GameOverReason::TimeUp => String::from("time's up"),
+        _ => over.to_string(),
+    };
+    let verdict = match winner {
+        Some(player) => format!("player {} wins", player + 1),
+        None => String::from("it's a draw"),
+    };
+    format!(
+        "{}, {} (p1: {}  p2: {})",
+        how, verdict, scores[0], scores[1]
+    )
+}
+
+/// Plays a match between two players at the one terminal, returning how it
+/// ended.
+pub fn run(
+    terminal: &mut Term,
+    events: &mut dyn EventSource,
+    config: &Config,
+    cli: &Cli,
+    layout: &Layout,
+) -> Result<String> {
+    let tick = cli.tick();
+    let mut game = versus_game(config, cli, layout, tick)?;
+    let mut arrows = Debounce::new(config.repeat_debounce);
+    let mut wasd = Debounce::new(config.repeat_debounce);
+    let clock = GameClock::new();
+    let mut next_tick = Instant::now() + tick;
+    let mut catch_up = CatchUp::default();
+
+    loop {
+        if !catch_up.catching_up() {
+            draw(
+                terminal,
+                config,
+                &game,
+                &Overlay {
+                    hints: config.show_hints.then_some(AppState::Versus),
+                    ..Overlay::default()
+                },
+            )?;
+        }
+
+        let timeout = next_tick.saturating_duration_since(Instant::now());
+        if events.poll(timeout)? {
+            if let Input::Key(key) = handle_event(events.read()?) {
+                if is_quit(&key) {
+                    return Ok(String::from("left the game"));
+                }
+                if let Some(direction) =
+                    key_direction(&key).filter(|d| arrows.accept(*d, clock.now()))
+                {
+                    game.change_direction(0, direction);
+                } else if let Some(direction) =
+                    wasd_direction(&key).filter(|d| wasd.accept(*d, clock.now()))
+                {
+                    game.change_direction(1, direction);
+                }
+            }
+            continue;
+        }
+
+        if let Err(over) = game.tick() {
+            return Ok(result(&game, over));
+        }
+        // Fallen behind, the missed ticks are taken without drawing them, as
+        // `play` does.
+        catch_up.behind(
+            &mut next_tick,
+            Instant::now(),
+            tick.mul_f64(game.interval_scale()),
+        );
+    }
+}
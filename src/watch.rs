@@ -0,0 +1,149 @@
+//! Watching a saved run back, with controls to pause it, step through it a
+//! tick at a time either way and change how fast it plays.
+
+use anyhow::{anyhow, Result};
+use std::time::{Duration, Instant};
+
+use crossterm::event::KeyCode;
+
+use rsnake::{Game, Replay};
+
+use crate::{
+    config::Config, draw, handle_event, is_quit, AppState, EventSource, Input, Notice, Overlay,
+    Term,
+};
+
+/// How many ticks apart the games kept to step back from are, trading memory
+/// for how far back a step has to play from.
+const KEYFRAME_EVERY: u64 = 100;
+
+/// How many times the normal speed a run can be played back at.
+const SPEEDS: [f64; 7] = [0.25, 0.5, 1.0, 2.0, 4.0, 8.0, 16.0];
+const NORMAL_SPEED: usize = 2;
+
+/// A run that can be moved to any tick, by playing on from the last keyframe
+/// before it.
+struct Scrubber<'a> {
+    replay: &'a Replay,
+    /// The game every `KEYFRAME_EVERY` ticks, from the first.
+    keyframes: Vec<Game>,
+    game: Game,
+}
+
+impl Scrubber<'_> {
+    fn new(replay: &Replay) -> Result<Scrubber<'_>> {
+        let game = replay
+            .start()
+            .ok_or_else(|| anyhow!("the run's board is too small to play back"))?;
+
+        Ok(Scrubber {
+            replay,
+            keyframes: vec![game.clone()],
+            game,
+        })
+    }
+
+    fn tick(&self) -> u64 {
+        self.game.ticks()
+    }
+
+    fn at_end(&self) -> bool {
+        self.tick() >= self.replay.ticks()
+    }
+
+    fn forward(&mut self) {
+        let tick = self.tick() + 1;
+        if tick <= self.replay.ticks() && self.replay.seek(&mut self.game, tick) {
+            self.keep(tick);
+        }
+    }
+
+    fn back(&mut self) {
+        if let Some(tick) = self.tick().checked_sub(1) {
+            let nearest = (tick / KEYFRAME_EVERY) as usize;
+            let mut game = self.keyframes[nearest.min(self.keyframes.len() - 1)].clone();
+            if self.replay.seek(&mut game, tick) {
+                self.game = game;
+            }
+        }
+    }
+
+    /// Keeps the game as a keyframe if it's the first to reach `tick` and
+    /// one is due there.
+    fn keep(&mut self, tick: u64) {
+        if tick.is_multiple_of(KEYFRAME_EVERY)
+            && tick / KEYFRAME_EVERY == self.keyframes.len() as u64
+        {
+            self.keyframes.push(self.game.clone());
+        }
+    }
+}
+
+/// Plays `replay` back until it's quit. Space pauses, the left and right
+/// arrows pause and step a tick, and `+` and `-` change the speed.
+pub fn run(
+    terminal: &mut Term,
+    events: &mut dyn EventSource,
+    config: &Config,
+    replay: &Replay,
+    tick: Duration,
+) -> Result<()> {
+    let mut scrubber = Scrubber::new(replay)?;
+    let mut paused = false;
+    let mut speed = NORMAL_SPEED;
+    let mut next_tick = Instant::now() + tick;
+
+    loop {
+        let status = format!(
+            "tick {}/{}  {}x{}",
+            scrubber.tick(),
+            replay.ticks(),
+            SPEEDS[speed],
+            if paused || scrubber.at_end() {
+                "  paused"
+            } else {
+                ""
+            }
+        );
+        let notice = Notice::new(status);
+        draw(
+            terminal,
+            config,
+            &scrubber.game,
+            &Overlay {
+                notice: Some(&notice),
+                hints: config.show_hints.then_some(AppState::Watching { paused }),
+                ..Overlay::default()
+            },
+        )?;
+
+        let timeout = next_tick.saturating_duration_since(Instant::now());
+        if paused || scrubber.at_end() || events.poll(timeout)? {
+            match handle_event(events.read()?) {
+                Input::Key(key) if is_quit(&key) => return Ok(()),
+                Input::Key(key) => match key.code {
+                    KeyCode::Char(' ') => paused = !paused,
+                    KeyCode::Right => {
+                        paused = true;
+                        scrubber.forward();
+                    }
+                    KeyCode::Left => {
+                        paused = true;
+                        scrubber.back();
+                    }
+                    KeyCode::Char('+') | KeyCode::Char('=') => {
+                        speed = (speed + 1).min(SPEEDS.len() - 1)
+                    }
+                    KeyCode::Char('-') => speed = speed.saturating_sub(1),
+                    _ => (),
+                },
+                Input::Resize | Input::FocusLost | Input::Ignored => (),
+            }
+            next_tick = Instant::now() + tick.div_f64(SPEEDS[speed]);
+            continue;
+        }
+
+        scrubber.forward();
+        next_tick += tick.div_f64(SPEEDS[speed]);
+    }
+}
@@ -0,0 +1,315 @@
+//! A headless game driven over WebSocket, so a browser or script can play.
+//!
+//! Controllers send text messages of the form
+//!
+//! ```json
+//! {"direction": "up"}
+//! ```
+//!
+//! where the direction is one of `left`, `up`, `right` or `down`. Anything
+//! else is ignored. When several controllers steer during the same tick the
+//! last message read wins.
+//!
+//! After every tick each controller is sent the state:
+//!
+//! ```json
+//! {"type": "state", "tick": 12, "snake": [[6, 5], [5, 5]], "food": [{"at": [3, 8], "kind": "golden"}], "score": 2}
+//! ```
+//!
+//! `snake` lists the segments head first. `food` lists every piece on the
+//! board, which may be none, and each kind is one of `normal`, `golden`,
+//! `rotten`, `magnet`, `pickaxe` or `speed`. When the snake crashes
+//! controllers receive `{"type": "game_over", "reason": "hit_self"}` and a new
+//! game starts.
+
+use anyhow::{anyhow, Result};
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+use std::{
+    io,
+    net::{TcpListener, TcpStream},
+    thread,
+    time::Duration,
+};
+
+use tungstenite::{Message, WebSocket};
+
+use rsnake::{Direction, Food, Game, GameOverReason, Vector};
+
+use crate::{config::Config, Cli};
+
+/// Clients that don't finish the WebSocket handshake within this long are
+/// dropped.
+const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(5);
+
+#[derive(Deserialize)]
+struct Command {
+    direction: Direction,
+}
+
+#[derive(Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum Update<'a> {
+    State {
+        tick: u64,
+        snake: Vec<&'a Vector>,
+        food: &'a [Food],
+        score: u32,
+    },
+    GameOver {
+        reason: GameOverReason,
+    },
+}
+
+fn accept(stream: TcpStream) -> Result<WebSocket<TcpStream>> {
+    stream.set_nonblocking(false)?;
+    stream.set_read_timeout(Some(HANDSHAKE_TIMEOUT))?;
+
+    let socket = tungstenite::accept(stream).map_err(|err| anyhow!("{}", err))?;
+    socket.get_ref().set_read_timeout(None)?;
+    socket.get_ref().set_nonblocking(true)?;
+    Ok(socket)
+}
+
+fn would_block(err: &tungstenite::Error) -> bool {
+    matches!(err, tungstenite::Error::Io(err) if err.kind() == io::ErrorKind::WouldBlock)
+}
+
+/// Reads whatever a controller has sent since the last tick, returning the
+/// last valid direction and whether the connection is still usable.
+fn read_commands(socket: &mut WebSocket<TcpStream>) -> (Option<Direction>, bool) {
+    let mut direction = None;
+
+    loop {
+        match socket.read() {
+            Ok(Message::Text(text)) => {
+                if let Ok(command) = serde_json::from_str::<Command>(text.as_str()) {
+                    direction = Some(command.direction);
+                }
+            }
+            Ok(Message::Close(_)) => return (direction, false),
+            Ok(_) => (),
+            Err(err) if would_block(&err) => return (direction, true),
+            Err(_) => return (direction, false),
+        }
+    }
+}
+
+/// Queues `update` on every controller, dropping the ones that have gone away.
+fn broadcast(sockets: &mut Vec<WebSocket<TcpStream>>, update: &Update) -> Result<()> {
+    let text = serde_json::to_string(update)?;
+
+    sockets.retain_mut(|socket| match socket.send(Message::text(text.clone())) {
+        Ok(()) => true,
+        Err(err) => would_block(&err),
+    });
+
+    Ok(())
+}
+
+/// A game on the board `--width` and `--height` ask for, from `--seed` if
+/// it's given so every game served is the same.
+fn new_game(config: &Config, cli: &Cli) -> Result<Game> {
+    let seed = cli.seed.unwrap_or_else(rand::random);
+    let mut game = Game::with_dimensions(1, seed, cli.dimensions())?;
+    game.set_food_table(config.food_table.clone());
+    game.set_food_count(config.food_count);
+    game.keep_food_clear_of_heads(config.food_clear_of_head);
+    game.set_min_length(config.min_length);
+    game.allow_reverse(config.allow_reverse);
+    game.disable_growth(config.no_growth);
+    game.set_wrap(config.wrap_x, config.wrap_y);
+    game.set_bounce(config.bounce);
+    game.enable_combos(config.combos);
+    game.set_wrap_penalty(config.wrap_penalty);
+    game.place_obstacles(config.obstacles);
+    Ok(game)
+}
+
+pub fn serve(port: u16, config: &Config, cli: &Cli) -> Result<()> {
+    let listener = TcpListener::bind(("0.0.0.0", port))?;
+    listener.set_nonblocking(true)?;
+    println!("serving on ws://0.0.0.0:{}", port);
+
+    let mut sockets: Vec<WebSocket<TcpStream>> = Vec::new();
+    let mut game = new_game(config, cli)?;
+    let mut tick = 0;
+
+    loop {
+        loop {
+            match listener.accept() {
+                Ok((stream, addr)) => match accept(stream) {
+                    Ok(socket) => {
+                        info!("controller connected from {}", addr);
+                        sockets.push(socket);
+                    }
+                    Err(err) => warn!("handshake with {} failed: {}", addr, err),
+                },
+                Err(err) if err.kind() == io::ErrorKind::WouldBlock => break,
+                Err(err) => return Err(err.into()),
+            }
+        }
+
+        let mut direction = None;
+        sockets.retain_mut(|socket| {
+            let (latest, open) = read_commands(socket);
+            direction = latest.or(direction);
+            open
+        });
+
+        if let Some(direction) = direction {
+            game.change_direction(0, direction);
+        }
+
+        match game.tick() {
+            Ok(_) => {
+                tick += 1;
+                let snake = &game.snakes()[0];
+                broadcast(
+                    &mut sockets,
+                    &Update::State {
+                        tick,
+                        snake: snake.body().iter().collect(),
+                        food: game.food(),
+                        score: snake.score(),
+                    },
+                )?;
+            }
+            Err(over) => {
+                broadcast(
+                    &mut sockets,
+                    &Update::GameOver {
+                        reason: over.reason,
+                    },
+                )?;
+                game = new_game(config, cli)?;
+                tick = 0;
+            }
+        }
+
+        thread::sleep(cli.tick());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::Parser;
+    use rsnake::FoodKind;
+
+    /// A server-side socket and the controller connected to it.
+    fn connect() -> (WebSocket<TcpStream>, WebSocket<TcpStream>) {
+        let listener = TcpListener::bind(("127.0.0.1", 0)).unwrap();
+        let addr = listener.local_addr().unwrap();
+        let client = thread::spawn(move || {
+            let stream = TcpStream::connect(addr).unwrap();
+            tungstenite::client(format!("ws://{}/", addr), stream)
+                .unwrap()
+                .0
+        });
+        let (stream, _) = listener.accept().unwrap();
+        let server = accept(stream).unwrap();
+        (server, client.join().unwrap())
+    }
+
+    #[test]
+    fn controllers_steer_and_get_the_state_back() {
+        let (mut server, mut client) = connect();
+        for text in [
+            r#"{"direction": "up"}"#,
+            "not json",
+            r#"{"direction": "sideways"}"#,
+            r#"{"direction": "left"}"#,
+        ] {
+            client.send(Message::text(text)).unwrap();
+        }
+
+        // The last a controller sent wins, and anything malformed is skipped.
+        let mut direction = None;
+        for _ in 0..100 {
+            let (latest, open) = read_commands(&mut server);
+            assert!(open);
+            direction = latest.or(direction);
+            if direction == Some(Direction::Left) {
+                break;
+            }
+            thread::sleep(Duration::from_millis(10));
+        }
+        assert_eq!(direction, Some(Direction::Left));
+
+        let mut sockets = vec![server];
+        broadcast(
+            &mut sockets,
+            &Update::GameOver {
+                reason: GameOverReason::HitSelf,
+            },
+        )
+        .unwrap();
+        let reply = client.read().unwrap();
+        assert_eq!(
+            reply.to_text().unwrap(),
+            r#"{"type":"game_over","reason":"hit_self"}"#
+        );
+
+        client.close(None).unwrap();
+        let mut open = true;
+        for _ in 0..100 {
+            open = read_commands(&mut sockets[0]).1;
+            if !open {
+                break;
+            }
+            thread::sleep(Duration::from_millis(10));
+        }
+        assert!(!open);
+    }
+
+    #[test]
+    fn games_follow_the_seed_and_board_asked_for() {
+        let cli = Cli::try_parse_from([
+            "rsnake",
+            "--serve-ws",
+            "4000",
+            "--seed",
+            "9",
+            "--width",
+            "30",
+            "--height",
+            "15",
+        ])
+        .unwrap();
+        let config = Config::default();
+        let mut first = new_game(&config, &cli).unwrap();
+        let mut second = new_game(&config, &cli).unwrap();
+        assert_eq!(first.dimensions(), &cli.dimensions());
+        assert_eq!(first.replay().seed(), 9);
+        first.tick().unwrap();
+        second.tick().unwrap();
+        assert_eq!(first.food(), second.food());
+    }
+
+    #[test]
+    fn state_matches_the_documented_schema() {
+        let snake = [Vector::new(6, 5), Vector::new(5, 5)];
+        let food = [Food {
+            at: Vector::new(3, 8),
+            kind: FoodKind::Speed,
+        }];
+        let state = Update::State {
+            tick: 12,
+            snake: snake.iter().collect(),
+            food: &food,
+            score: 2,
+        };
+        assert_eq!(
+            serde_json::to_string(&state).unwrap(),
+            r#"{"type":"state","tick":12,"snake":[[6,5],[5,5]],"food":[{"at":[3,8],"kind":"speed"}],"score":2}"#
+        );
+        let over = Update::GameOver {
+            reason: GameOverReason::HitSelf,
+        };
+        assert_eq!(
+            serde_json::to_string(&over).unwrap(),
+            r#"{"type":"game_over","reason":"hit_self"}"#
+        );
+    }
+}